@@ -0,0 +1,148 @@
+use super::*;
+use std::thread;
+
+#[cfg(target_os = "linux")]
+mod sys {
+    /// matches glibc's `cpu_set_t`: 1024 cpus
+    pub const CPU_SET_WORDS: usize = 1024 / 64;
+
+    extern "C" {
+        pub fn sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u64) -> i32;
+        pub fn sched_getaffinity(pid: i32, cpusetsize: usize, mask: *mut u64) -> i32;
+    }
+}
+
+/// the cpu cores the calling thread may run on, in ascending order. only
+/// supported on linux; elsewhere this fails.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// # if cfg!(target_os = "linux") {
+/// let cores = allowed_cores().unwrap();
+/// assert!(!cores.is_empty());
+/// // pinning to cores we may already use always works
+/// pin_current_thread(&cores).unwrap();
+/// assert_eq!(allowed_cores().unwrap(), cores);
+/// # }
+/// ```
+pub fn allowed_cores() -> Out<Vec<usize>> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut mask = [0u64; sys::CPU_SET_WORDS];
+        // pid 0 is the calling thread
+        let rc =
+            unsafe { sys::sched_getaffinity(0, std::mem::size_of_val(&mask), mask.as_mut_ptr()) };
+        if rc != 0 {
+            return Err(Failure::Custom(format!(
+                "failed to read the thread's cpu cores: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok((0..sys::CPU_SET_WORDS * 64)
+            .filter(|core| mask[core / 64] & (1 << (core % 64)) != 0)
+            .collect())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Err(Failure::Custom(
+            "reading a thread's cpu cores is only supported on linux".to_string(),
+        ))
+    }
+}
+
+/// restrict the calling thread to the given cpu cores. only supported on
+/// linux; elsewhere this fails without changing anything.
+pub fn pin_current_thread(cores: &[usize]) -> Out<()> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut mask = [0u64; sys::CPU_SET_WORDS];
+        for &core in cores {
+            if core >= sys::CPU_SET_WORDS * 64 {
+                return Err(Failure::InvalidInput(format!("no such cpu core: {}", core)));
+            }
+            mask[core / 64] |= 1 << (core % 64);
+        }
+        // pid 0 is the calling thread
+        let rc = unsafe { sys::sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr()) };
+        if rc != 0 {
+            return Err(Failure::Custom(format!(
+                "failed to pin thread to cores {:?}: {}",
+                cores,
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Err(Failure::Custom(
+            "pinning threads to cores is only supported on linux".to_string(),
+        ))
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// like `for_each`, but the items are split into one contiguous chunk per
+    /// core in `cores`, each processed on a thread pinned to that core. the
+    /// outputs keep the input order. meant for cache-sensitive numeric stages
+    /// that suffer from threads migrating between cores.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// # if cfg!(target_os = "linux") {
+    /// // up to two of the cores this process may use
+    /// let cores: Vec<usize> = allowed_cores().unwrap().into_iter().take(2).collect();
+    /// let squares = Reactor::<Vec<u64>>::input((1..=8).collect())
+    ///     .for_each_pinned(&cores, |x: u64| Ok(x * x))
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(squares, vec![1, 4, 9, 16, 25, 36, 49, 64]);
+    /// # }
+    /// ```
+    pub fn for_each_pinned<O, T>(self, cores: &[usize], transform: T) -> Reactor<Vec<O>, E>
+    where
+        I: IntoIterator,
+        I::Item: Send,
+        O: Send,
+        E: Send,
+        T: Act<I::Item, O, E> + Sync,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                if cores.is_empty() {
+                    return Err(Failure::InvalidInput("no cores to pin to".to_string()).into());
+                }
+                let mut items: Vec<I::Item> = i.into_iter().collect();
+                let chunk = items.len().div_ceil(cores.len()).max(1);
+                let mut chunks = Vec::new();
+                while !items.is_empty() {
+                    let rest = items.split_off(chunk.min(items.len()));
+                    chunks.push(mem::replace(&mut items, rest));
+                }
+                let transform = &transform;
+                thread::scope(|scope| {
+                    let threads: Vec<_> = chunks
+                        .into_iter()
+                        .zip(cores)
+                        .map(|(chunk, &core)| {
+                            scope.spawn(move || -> Out<Vec<O>, E> {
+                                pin_current_thread(&[core])?;
+                                chunk.into_iter().map(|item| transform.act(item)).collect()
+                            })
+                        })
+                        .collect();
+                    let mut out = Vec::new();
+                    for t in threads {
+                        out.extend(t.join().expect("pinned worker thread panicked")?);
+                    }
+                    Ok(out)
+                })
+            }),
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use super::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// an `Act` that returns a future, e.g. an http call or async file io.
+/// implemented for every `Fn(I) -> impl Future<Output = Out<O, E>>`, which
+/// includes async closures and functions.
+pub trait AsyncAct<I, O, E = Failure>
+where
+    E: Debug,
+{
+    fn act_async(&self, input: I) -> impl Future<Output = Out<O, E>>;
+}
+
+impl<I, O, E, F, Fut> AsyncAct<I, O, E> for F
+where
+    F: Fn(I) -> Fut,
+    Fut: Future<Output = Out<O, E>>,
+    E: Debug,
+{
+    fn act_async(&self, input: I) -> impl Future<Output = Out<O, E>> {
+        self(input)
+    }
+}
+
+type Pending<'a, I, E> = Pin<Box<dyn Future<Output = Out<I, E>> + 'a>>;
+
+/// the async counterpart of `Reactor`: each stage is awaited before the next
+/// one starts. nothing runs until the future returned by `run` is polled, by
+/// any runtime or by `block_on`. its future isn't `Send`, so stages may hold
+/// `Rc`s and the like; use `SendAsyncReactor` to run on a multi-threaded
+/// runtime.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// async fn fetch(id: u32) -> Out<String> {
+///     Ok(format!("user-{}", id))
+/// }
+///
+/// let out = block_on(
+///     AsyncReactor::<u32>::input(7)
+///         .then(fetch)
+///         .then_sync(|s: String| Ok(s.len()))
+///         .map(|n| n * 2)
+///         .run(),
+/// );
+/// assert_eq!(out.unwrap(), 12);
+/// ```
+pub struct AsyncReactor<'a, I, E = Failure> {
+    input: Pending<'a, I, E>,
+}
+
+impl<'a, I, E> AsyncReactor<'a, I, E>
+where
+    I: 'a,
+    E: Debug + 'a,
+{
+    pub fn input(input: I) -> Self {
+        AsyncReactor {
+            input: Box::pin(async move { Ok(input) }),
+        }
+    }
+
+    /// start from the output of a future
+    pub fn from_future<F>(future: F) -> Self
+    where
+        F: Future<Output = Out<I, E>> + 'a,
+    {
+        AsyncReactor {
+            input: Box::pin(future),
+        }
+    }
+
+    pub fn then<O, T>(self, transform: T) -> AsyncReactor<'a, O, E>
+    where
+        T: AsyncAct<I, O, E> + 'a,
+    {
+        let input = self.input;
+        AsyncReactor {
+            input: Box::pin(async move { transform.act_async(input.await?).await }),
+        }
+    }
+
+    /// append a synchronous act
+    pub fn then_sync<O, T>(self, transform: T) -> AsyncReactor<'a, O, E>
+    where
+        T: Act<I, O, E> + 'a,
+    {
+        let input = self.input;
+        AsyncReactor {
+            input: Box::pin(async move { transform.act(input.await?) }),
+        }
+    }
+
+    pub fn if_else<O1, O2, C, T1, T2>(
+        self,
+        condition: C,
+        true_transform: T1,
+        false_transform: T2,
+    ) -> AsyncReactor<'a, Either<O1, O2>, E>
+    where
+        C: Fn(&I) -> bool + 'a,
+        T1: AsyncAct<I, O1, E> + 'a,
+        T2: AsyncAct<I, O2, E> + 'a,
+    {
+        let input = self.input;
+        AsyncReactor {
+            input: Box::pin(async move {
+                let i = input.await?;
+                if condition(&i) {
+                    true_transform.act_async(i).await.map(Either::Left)
+                } else {
+                    false_transform.act_async(i).await.map(Either::Right)
+                }
+            }),
+        }
+    }
+
+    /// run `transform` on every item, one after another
+    pub fn for_each<O, T>(self, transform: T) -> AsyncReactor<'a, Vec<O>, E>
+    where
+        I: IntoIterator,
+        T: AsyncAct<I::Item, O, E> + 'a,
+    {
+        let input = self.input;
+        AsyncReactor {
+            input: Box::pin(async move {
+                let mut out = Vec::new();
+                for item in input.await? {
+                    out.push(transform.act_async(item).await?);
+                }
+                Ok(out)
+            }),
+        }
+    }
+
+    pub fn map<O, F>(self, f: F) -> AsyncReactor<'a, O, E>
+    where
+        F: FnOnce(I) -> O + 'a,
+    {
+        let input = self.input;
+        AsyncReactor {
+            input: Box::pin(async move { input.await.map(f) }),
+        }
+    }
+
+    pub fn and_then<O, F>(self, f: F) -> AsyncReactor<'a, O, E>
+    where
+        F: FnOnce(I) -> Out<O, E> + 'a,
+    {
+        let input = self.input;
+        AsyncReactor {
+            input: Box::pin(async move { input.await.and_then(f) }),
+        }
+    }
+
+    pub async fn run(self) -> Out<I, E> {
+        self.input.await
+    }
+}
+
+type SendPending<'a, I, E> = Pin<Box<dyn Future<Output = Out<I, E>> + Send + 'a>>;
+
+/// an `AsyncReactor` whose future is `Send`, so a multi-threaded runtime can
+/// move it between worker threads (e.g. `tokio::spawn`). every stage, value
+/// and future in it has to be `Send` too.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// async fn fetch(id: u32) -> Out<String> {
+///     Ok(format!("user-{}", id))
+/// }
+///
+/// let run = SendAsyncReactor::<Vec<u32>>::input(vec![7, 42])
+///     .for_each(fetch)
+///     .then_sync(|names: Vec<String>| Ok(names.join(",")))
+///     .run();
+/// let out = std::thread::spawn(move || block_on(run)).join().unwrap();
+/// assert_eq!(out.unwrap(), "user-7,user-42");
+/// ```
+pub struct SendAsyncReactor<'a, I, E = Failure> {
+    input: SendPending<'a, I, E>,
+}
+
+impl<'a, I, E> SendAsyncReactor<'a, I, E>
+where
+    I: Send + 'a,
+    E: Debug + Send + 'a,
+{
+    pub fn input(input: I) -> Self {
+        SendAsyncReactor {
+            input: Box::pin(async move { Ok(input) }),
+        }
+    }
+
+    /// start from the output of a future
+    pub fn from_future<F>(future: F) -> Self
+    where
+        F: Future<Output = Out<I, E>> + Send + 'a,
+    {
+        SendAsyncReactor {
+            input: Box::pin(future),
+        }
+    }
+
+    pub fn then<O, T, Fut>(self, transform: T) -> SendAsyncReactor<'a, O, E>
+    where
+        T: Fn(I) -> Fut + Send + 'a,
+        Fut: Future<Output = Out<O, E>> + Send,
+    {
+        let input = self.input;
+        SendAsyncReactor {
+            input: Box::pin(async move {
+                let i = input.await?;
+                transform(i).await
+            }),
+        }
+    }
+
+    /// append a synchronous act
+    pub fn then_sync<O, T>(self, transform: T) -> SendAsyncReactor<'a, O, E>
+    where
+        T: Act<I, O, E> + Send + 'a,
+    {
+        let input = self.input;
+        SendAsyncReactor {
+            input: Box::pin(async move {
+                let i = input.await?;
+                transform.act(i)
+            }),
+        }
+    }
+
+    /// run `transform` on every item, one after another
+    pub fn for_each<O, T, Fut>(self, transform: T) -> SendAsyncReactor<'a, Vec<O>, E>
+    where
+        I: IntoIterator,
+        I::IntoIter: Send,
+        I::Item: Send,
+        O: Send,
+        T: Fn(I::Item) -> Fut + Send + 'a,
+        Fut: Future<Output = Out<O, E>> + Send,
+    {
+        let input = self.input;
+        SendAsyncReactor {
+            input: Box::pin(async move {
+                let mut out = Vec::new();
+                for item in input.await? {
+                    let next = transform(item);
+                    out.push(next.await?);
+                }
+                Ok(out)
+            }),
+        }
+    }
+
+    pub fn map<O, F>(self, f: F) -> SendAsyncReactor<'a, O, E>
+    where
+        F: FnOnce(I) -> O + Send + 'a,
+    {
+        let input = self.input;
+        SendAsyncReactor {
+            input: Box::pin(async move { input.await.map(f) }),
+        }
+    }
+
+    pub fn and_then<O, F>(self, f: F) -> SendAsyncReactor<'a, O, E>
+    where
+        F: FnOnce(I) -> Out<O, E> + Send + 'a,
+    {
+        let input = self.input;
+        SendAsyncReactor {
+            input: Box::pin(async move { input.await.and_then(f) }),
+        }
+    }
+
+    /// the chain as one `Send` future
+    pub fn run(self) -> impl Future<Output = Out<I, E>> + Send + 'a {
+        self.input
+    }
+}
+
+/// wakes the thread blocked in `block_on`
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// run a future to completion on the current thread, for callers without an
+/// async runtime. futures that need a particular runtime's reactor (tokio's
+/// io and timers) have to be run by that runtime instead.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(out) => return out,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
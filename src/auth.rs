@@ -0,0 +1,313 @@
+use super::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// what a guard needs to know about an authenticated principal. implement it
+/// for your jwt claims, or directly for message types that carry their own
+/// credentials.
+pub trait Claims {
+    /// expiry as seconds since the unix epoch, if the credential expires
+    fn expires_at(&self) -> Option<u64> {
+        None
+    }
+
+    fn has_role(&self, role: &str) -> bool;
+}
+
+/// a request that passed authentication, along with the decoded claims.
+#[derive(Debug, Clone)]
+pub struct Authenticated<C> {
+    pub request: Request,
+    pub claims: C,
+}
+
+impl<C: Claims> Claims for Authenticated<C> {
+    fn expires_at(&self) -> Option<u64> {
+        self.claims.expires_at()
+    }
+
+    fn has_role(&self, role: &str) -> bool {
+        self.claims.has_role(role)
+    }
+}
+
+fn unauthorized(msg: &str) -> Failure {
+    Failure::Unauthorized(msg.to_string())
+}
+
+/// compare two byte strings without leaking where they differ through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// strict base64url: a single leftover character can't encode a byte, and the
+/// unused low bits of the last character must be zero, so every input decodes
+/// from exactly one encoding
+fn decode_base64url(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    if s.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut buf = 0u32;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None,
+        };
+        buf = (buf << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+        }
+    }
+    (buf & ((1 << bits) - 1) == 0).then_some(out)
+}
+
+/// the `alg` of a jwt header, read without a json parser. values with escapes
+/// aren't valid algorithm names and are treated as missing.
+fn jwt_alg(header: &[u8]) -> Option<&str> {
+    let header = std::str::from_utf8(header).ok()?;
+    let rest = &header[header.find("\"alg\"")? + 5..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let value = rest.strip_prefix('"')?;
+    let value = &value[..value.find('"')?];
+    (!value.contains('\\')).then_some(value)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// the token from an `authorization: Bearer <token>` header
+pub fn bearer_token(req: &Request) -> Option<&str> {
+    let value = req.header("authorization")?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// validate the request's bearer jwt and pass it on with its claims.
+///
+/// the crate doesn't bundle crypto or json, so both are plugged in: `verify`
+/// checks the signature over the `header.payload` signing input (e.g. with an
+/// hmac-sha256 or rsa key), and `decode` turns the payload json into claims.
+/// tokens whose header names the `none` algorithm, or none at all, and expired
+/// tokens are rejected. use `require_jwt_alg` to accept only the algorithm
+/// `verify` implements.
+pub fn require_jwt<C, V, D>(verify: V, decode: D) -> impl Fn(Request) -> Out<Authenticated<C>>
+where
+    C: Claims,
+    V: Fn(&[u8], &[u8]) -> bool,
+    D: Fn(&[u8]) -> Out<C>,
+{
+    jwt_guard(None, verify, decode)
+}
+
+/// like `require_jwt`, but only tokens whose header names `alg` are accepted,
+/// so a token can't pick a weaker algorithm than the one `verify` implements.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// # fn b64(bytes: &[u8]) -> String {
+/// #     const A: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+/// #     bytes.chunks(3).flat_map(|c| {
+/// #         let n = c.iter().fold(0u32, |n, b| n << 8 | *b as u32) << (8 * (3 - c.len()));
+/// #         (0..=c.len()).map(move |i| A[(n >> (18 - 6 * i) & 63) as usize] as char)
+/// #     }).collect()
+/// # }
+///
+/// struct User {
+///     exp: u64,
+/// }
+///
+/// impl Claims for User {
+///     fn expires_at(&self) -> Option<u64> {
+///         Some(self.exp)
+///     }
+///     fn has_role(&self, _: &str) -> bool {
+///         false
+///     }
+/// }
+///
+/// // stand-ins for an hmac and a json decoder
+/// let mac = |input: &[u8]| input.iter().fold(vec![0u8; 4], |mut m, b| {
+///     m.rotate_left(1);
+///     m[0] ^= b;
+///     m
+/// });
+/// let verify = move |input: &[u8], signature: &[u8]| signature == mac(input);
+/// let decode = |payload: &[u8]| {
+///     let exp = std::str::from_utf8(payload).ok().and_then(|p| p.parse().ok());
+///     exp.map(|exp| User { exp }).ok_or(Failure::InvalidInput("bad claims".into()))
+/// };
+/// let guard = require_jwt_alg("HS256", verify, decode);
+///
+/// let token = |alg: &str, exp: &str| {
+///     let input = format!("{}.{}", b64(format!("{{\"alg\":\"{}\"}}", alg).as_bytes()), b64(exp.as_bytes()));
+///     format!("{}.{}", input, b64(&mac(input.as_bytes())))
+/// };
+/// let call = |token: &str| {
+///     guard(Request::new("GET", "/").with_header("authorization", &format!("Bearer {}", token)))
+///         .map(|a| a.claims.exp)
+///         .map_err(|e| e.to_string())
+/// };
+///
+/// let valid = token("HS256", "4102444800");
+/// assert_eq!(call(&valid), Ok(4102444800));
+///
+/// let mut tampered = valid.clone();
+/// tampered.replace_range(tampered.len() - 2.., "AA");
+/// assert_eq!(call(&tampered).unwrap_err(), "Unauthorized: invalid token signature");
+///
+/// assert_eq!(call(&token("HS256", "1000")).unwrap_err(), "Unauthorized: token expired");
+/// assert_eq!(call(&token("none", "4102444800")).unwrap_err(), "Unauthorized: unsupported token algorithm");
+/// assert_eq!(call(&token("RS256", "4102444800")).unwrap_err(), "Unauthorized: unsupported token algorithm");
+///
+/// let segments: Vec<&str> = valid.split('.').collect();
+/// for malformed in [
+///     format!("{}.{}", segments[0], segments[1]),
+///     format!("{}.{}.{}.x", segments[0], segments[1], segments[2]),
+///     format!("{}.{}.{}", segments[0], segments[1], "A"), // an impossible length
+///     format!("{}.{}.{}", segments[0], segments[1], "AB"), // non-zero trailing bits
+///     format!("{}!.{}.{}", segments[0], segments[1], segments[2]),
+/// ] {
+///     assert_eq!(call(&malformed).unwrap_err(), "Unauthorized: malformed token");
+/// }
+/// ```
+pub fn require_jwt_alg<C, V, D>(
+    alg: &str,
+    verify: V,
+    decode: D,
+) -> impl Fn(Request) -> Out<Authenticated<C>>
+where
+    C: Claims,
+    V: Fn(&[u8], &[u8]) -> bool,
+    D: Fn(&[u8]) -> Out<C>,
+{
+    jwt_guard(Some(alg.to_string()), verify, decode)
+}
+
+fn jwt_guard<C, V, D>(
+    alg: Option<String>,
+    verify: V,
+    decode: D,
+) -> impl Fn(Request) -> Out<Authenticated<C>>
+where
+    C: Claims,
+    V: Fn(&[u8], &[u8]) -> bool,
+    D: Fn(&[u8]) -> Out<C>,
+{
+    move |request| {
+        let token = bearer_token(&request).ok_or_else(|| unauthorized("missing bearer token"))?;
+        let mut parts = token.split('.');
+        let (header, payload, signature) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(h), Some(p), Some(s), None) => (h, p, s),
+                _ => return Err(unauthorized("malformed token")),
+            };
+        let header_json =
+            decode_base64url(header).ok_or_else(|| unauthorized("malformed token"))?;
+        let accepted = match (jwt_alg(&header_json), &alg) {
+            (None, _) => false,
+            (Some(given), _) if given.eq_ignore_ascii_case("none") => false,
+            (Some(given), Some(alg)) => given == alg,
+            (Some(_), None) => true,
+        };
+        if !accepted {
+            return Err(unauthorized("unsupported token algorithm"));
+        }
+        let signature =
+            decode_base64url(signature).ok_or_else(|| unauthorized("malformed token"))?;
+        let signing_input = &token[..header.len() + 1 + payload.len()];
+        if !verify(signing_input.as_bytes(), &signature) {
+            return Err(unauthorized("invalid token signature"));
+        }
+        let payload = decode_base64url(payload).ok_or_else(|| unauthorized("malformed token"))?;
+        let claims =
+            decode(&payload).map_err(|e| unauthorized(&format!("invalid claims: {}", e)))?;
+        if claims.expires_at().is_some_and(|exp| exp <= now()) {
+            return Err(unauthorized("token expired"));
+        }
+        Ok(Authenticated { request, claims })
+    }
+}
+
+/// check an hmac signature carried by the item. `signature` extracts the
+/// hex-encoded signature, `payload` the signed bytes, and `mac` computes the
+/// expected mac for them with the shared secret. works on requests and on
+/// queue messages alike.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// // a stand-in for an hmac with the shared secret
+/// let mac = |body: &[u8]| vec![body.iter().fold(0x5a, |m, b| m ^ b)];
+/// let guard = require_signature_header("x-signature", mac);
+///
+/// let signed = |sig: &str| Request::new("POST", "/hook").with_header("x-signature", sig).with_body("hi");
+/// let expected = format!("{:02x}", mac(b"hi")[0]);
+/// assert!(guard(signed(&expected)).is_ok());
+/// assert_eq!(guard(signed("00")).unwrap_err().to_string(), "Unauthorized: signature mismatch");
+/// for malformed in ["0", "zz", "0000", ""] {
+///     assert!(guard(signed(malformed)).is_err());
+/// }
+/// assert!(guard(Request::new("POST", "/hook").with_body("hi")).is_err());
+/// ```
+pub fn require_hmac<T, S, P, M>(signature: S, payload: P, mac: M) -> impl Fn(T) -> Out<T>
+where
+    S: Fn(&T) -> Option<&str>,
+    P: Fn(&T) -> &[u8],
+    M: Fn(&[u8]) -> Vec<u8>,
+{
+    move |item| {
+        let given = signature(&item)
+            .and_then(decode_hex)
+            .ok_or_else(|| unauthorized("missing or malformed signature"))?;
+        if !constant_time_eq(&given, &mac(payload(&item))) {
+            return Err(unauthorized("signature mismatch"));
+        }
+        Ok(item)
+    }
+}
+
+/// check the hex hmac in header `name` against the request body
+pub fn require_signature_header<M>(name: &str, mac: M) -> impl Fn(Request) -> Out<Request>
+where
+    M: Fn(&[u8]) -> Vec<u8>,
+{
+    let name = name.to_string();
+    require_hmac(
+        move |req: &Request| req.header(&name),
+        |req: &Request| &req.body,
+        mac,
+    )
+}
+
+/// pass the authenticated item through only if it holds `role`
+pub fn require_role<T: Claims>(role: &str) -> impl Fn(T) -> Out<T> {
+    let role = role.to_string();
+    move |item| {
+        if item.has_role(&role) {
+            Ok(item)
+        } else {
+            Err(unauthorized(&format!("missing role '{}'", role)))
+        }
+    }
+}
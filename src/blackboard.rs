@@ -0,0 +1,89 @@
+use super::*;
+use std::any::{type_name, Any, TypeId};
+
+/// a store holding at most one value per type, shared by the stages of a run
+/// so a value produced early (an auth token, a schema) can reach a much later
+/// stage without being threaded through every type in between. clones share
+/// the same store, so give each stage a clone.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// #[derive(Clone)]
+/// struct Token(String);
+///
+/// let board = Blackboard::new();
+/// let (login, upload) = (board.clone(), board.clone());
+/// let out = Reactor::<&str>::input("alice")
+///     .then(move |user: &str| {
+///         login.put(Token(format!("token-of-{}", user)));
+///         Ok(user.len())
+///     })
+///     .then(|n: usize| Ok(n * 2))
+///     .then(move |n: usize| Ok(format!("{} bytes with {}", n, upload.require::<Token>()?.0)))
+///     .run();
+/// assert_eq!(out.unwrap(), "10 bytes with token-of-alice");
+/// ```
+#[derive(Clone, Default)]
+pub struct Blackboard {
+    values: Rc<RefCell<HashMap<TypeId, Box<dyn Any>>>>,
+}
+
+impl Blackboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// store `value`, returning the value of the same type it replaces
+    pub fn put<T: 'static>(&self, value: T) -> Option<T> {
+        self.values
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|old| *old.downcast::<T>().expect("values are keyed by type"))
+    }
+
+    /// a copy of the stored `T`
+    pub fn get<T: Clone + 'static>(&self) -> Option<T> {
+        self.with(T::clone)
+    }
+
+    /// a copy of the stored `T`, or a failure naming the missing type
+    pub fn require<T: Clone + 'static>(&self) -> Out<T> {
+        self.get().ok_or_else(|| {
+            Failure::InvalidInput(format!("no {} on the blackboard", type_name::<T>()))
+        })
+    }
+
+    /// call `f` with the stored `T`, without copying it
+    pub fn with<T: 'static, R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let values = self.values.borrow();
+        values
+            .get(&TypeId::of::<T>())
+            .map(|v| f(v.downcast_ref::<T>().expect("values are keyed by type")))
+    }
+
+    /// remove the stored `T` and hand it back
+    pub fn take<T: 'static>(&self) -> Option<T> {
+        self.values
+            .borrow_mut()
+            .remove(&TypeId::of::<T>())
+            .map(|v| *v.downcast::<T>().expect("values are keyed by type"))
+    }
+
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.values.borrow().contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.borrow().is_empty()
+    }
+
+    /// forget every value, e.g. between runs
+    pub fn clear(&self) {
+        self.values.borrow_mut().clear();
+    }
+}
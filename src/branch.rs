@@ -0,0 +1,91 @@
+use super::*;
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// send a copy of the value through each of two acts and pair up their
+    /// outputs. the second act only runs if the first succeeds.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let words = |s: String| Ok(s.split_whitespace().count());
+    /// let shout = (|s: String| Ok(s.to_uppercase())).then(|s: String| Ok(s + "!"));
+    /// let (count, loud) = Reactor::<String>::input("hello there".to_string())
+    ///     .split(words, shout)
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(count, 2);
+    /// assert_eq!(loud, "HELLO THERE!");
+    /// ```
+    pub fn split<O1, O2, T1, T2>(self, first: T1, second: T2) -> Reactor<(O1, O2), E>
+    where
+        I: Clone,
+        T1: Act<I, O1, E>,
+        T2: Act<I, O2, E>,
+    {
+        Reactor {
+            input: self
+                .input
+                .and_then(|i| Ok((first.act(i.clone())?, second.act(i)?))),
+        }
+    }
+
+    /// run `side` on a copy of the value and pass the original on, e.g. for
+    /// logging or metrics. a failing side branch fails the chain; recover
+    /// inside it if it shouldn't.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::cell::RefCell;
+    ///
+    /// let seen = RefCell::new(Vec::new());
+    /// let out = Reactor::<i32>::input(4)
+    ///     .then(|x: i32| Ok(x * 10))
+    ///     .tee(|x: i32| -> Out<()> {
+    ///         seen.borrow_mut().push(x);
+    ///         Ok(())
+    ///     })
+    ///     .then(|x: i32| Ok(x + 1))
+    ///     .run();
+    /// assert_eq!(out.unwrap(), 41);
+    /// assert_eq!(seen.into_inner(), [40]);
+    /// ```
+    pub fn tee<O, T>(self, side: T) -> Reactor<I, E>
+    where
+        I: Clone,
+        T: Act<I, O, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                side.act(i.clone())?;
+                Ok(i)
+            }),
+        }
+    }
+
+    /// combine this reactor's value with `other`'s using `merge`. fails with
+    /// the first failure of the two.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let price = Reactor::<&str>::input("12.5").then(|s: &str| {
+    ///     s.parse::<f64>().map_err(|e| Failure::InvalidInput(e.to_string()))
+    /// });
+    /// let quantity = Reactor::<u32>::input(4);
+    /// let total = price
+    ///     .join(quantity, |(p, q): (f64, u32)| Ok(p * q as f64))
+    ///     .run();
+    /// assert_eq!(total.unwrap(), 50.0);
+    /// ```
+    pub fn join<I2, O, T>(self, other: Reactor<I2, E>, merge: T) -> Reactor<O, E>
+    where
+        T: Act<(I, I2), O, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|i| merge.act((i, other.run()?))),
+        }
+    }
+}
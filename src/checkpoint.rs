@@ -0,0 +1,199 @@
+use super::*;
+use crate::sink::write_atomically;
+use std::fs;
+use std::path::PathBuf;
+
+/// how far a long fold got: the number of items processed and the encoded
+/// accumulator after them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub processed: usize,
+    pub state: String,
+}
+
+/// where checkpoints are kept between runs.
+pub trait CheckpointStore {
+    fn load(&self) -> Out<Option<Checkpoint>>;
+    fn save(&mut self, checkpoint: &Checkpoint) -> Out<()>;
+    /// forget the checkpoint once the run completed
+    fn clear(&mut self) -> Out<()>;
+}
+
+/// keeps the checkpoint in a file, replaced atomically on every save.
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        FileCheckpointStore { path: path.into() }
+    }
+
+    fn failure(&self, e: impl std::fmt::Display) -> Failure {
+        Failure::Custom(format!("checkpoint {}: {}", self.path.display(), e))
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Out<Option<Checkpoint>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(self.failure(e)),
+        };
+        let (processed, state) = text.split_once('\n').unwrap_or((&text, ""));
+        let processed = processed.trim().parse().map_err(|e| self.failure(e))?;
+        Ok(Some(Checkpoint {
+            processed,
+            state: state.to_string(),
+        }))
+    }
+
+    fn save(&mut self, checkpoint: &Checkpoint) -> Out<()> {
+        let contents = format!("{}\n{}", checkpoint.processed, checkpoint.state);
+        write_atomically(&self.path, contents.as_bytes()).map_err(|e| self.failure(e))
+    }
+
+    fn clear(&mut self) -> Out<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(self.failure(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// a checkpoint store plus how often to save to it: every `n` items, every
+/// interval, or both (whichever comes first). with neither set, it saves
+/// every 1000 items.
+pub struct Checkpointer<S> {
+    store: S,
+    every_items: Option<usize>,
+    interval: Option<Duration>,
+}
+
+impl<S: CheckpointStore> Checkpointer<S> {
+    pub fn new(store: S) -> Self {
+        Checkpointer {
+            store,
+            every_items: None,
+            interval: None,
+        }
+    }
+
+    pub fn every_items(mut self, items: usize) -> Self {
+        self.every_items = Some(items.max(1));
+        self
+    }
+
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+fn fold_from_checkpoint<I, A, S, C, D, F, E>(
+    items: I,
+    checkpointer: &mut Checkpointer<S>,
+    init: A,
+    encode: C,
+    decode: D,
+    step: F,
+) -> Out<A, E>
+where
+    I: IntoIterator,
+    S: CheckpointStore,
+    C: Fn(&A) -> String,
+    D: Fn(&str) -> Out<A, E>,
+    F: Fn(A, I::Item) -> Out<A, E>,
+    E: From<Failure>,
+{
+    let every_items = match (checkpointer.every_items, checkpointer.interval) {
+        (None, None) => Some(1000),
+        (items, _) => items,
+    };
+    let (skip, mut acc) = match checkpointer.store.load()? {
+        Some(cp) => (cp.processed, decode(&cp.state)?),
+        None => (0, init),
+    };
+    let mut saved = (skip, Instant::now());
+    let mut processed = skip;
+    for item in items.into_iter().skip(skip) {
+        acc = step(acc, item)?;
+        processed += 1;
+        let due_items = every_items.is_some_and(|n| processed - saved.0 >= n);
+        let due_time = checkpointer
+            .interval
+            .is_some_and(|every| saved.1.elapsed() >= every);
+        if due_items || due_time {
+            checkpointer.store.save(&Checkpoint {
+                processed,
+                state: encode(&acc),
+            })?;
+            saved = (processed, Instant::now());
+        }
+    }
+    checkpointer.store.clear()?;
+    Ok(acc)
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// fold the items into an accumulator, saving progress through
+    /// `checkpointer` as it goes. a run that finds a checkpoint skips the items
+    /// it already covers and continues from the saved accumulator, so the
+    /// items must come in the same order every run. the checkpoint is cleared
+    /// once all items are folded.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let path = std::env::temp_dir().join(format!("chain_reaction-checkpoint-doc-{}", std::process::id()));
+    /// let mut checkpointer = Checkpointer::new(FileCheckpointStore::new(&path)).every_items(2);
+    ///
+    /// // the first run dies at item 5, after checkpointing 4 items
+    /// let failed = Reactor::<Vec<u64>>::input((1..=6).collect()).fold_checkpointed(
+    ///     &mut checkpointer,
+    ///     0,
+    ///     |sum: &u64| sum.to_string(),
+    ///     |s: &str| s.parse().map_err(|_| Failure::InvalidInput(s.to_string())),
+    ///     |sum, n| if n == 5 { Err(Failure::Custom("preempted".into())) } else { Ok(sum + n) },
+    /// );
+    /// assert!(failed.run().is_err());
+    ///
+    /// let sum = Reactor::<Vec<u64>>::input((1..=6).collect()).fold_checkpointed(
+    ///     &mut checkpointer,
+    ///     0,
+    ///     |sum: &u64| sum.to_string(),
+    ///     |s: &str| s.parse().map_err(|_| Failure::InvalidInput(s.to_string())),
+    ///     |sum, n| Ok(sum + n),
+    /// );
+    /// assert_eq!(sum.run().unwrap(), 21);
+    /// ```
+    pub fn fold_checkpointed<A, S, C, D, F>(
+        self,
+        checkpointer: &mut Checkpointer<S>,
+        init: A,
+        encode: C,
+        decode: D,
+        step: F,
+    ) -> Reactor<A, E>
+    where
+        I: IntoIterator,
+        S: CheckpointStore,
+        C: Fn(&A) -> String,
+        D: Fn(&str) -> Out<A, E>,
+        F: Fn(A, I::Item) -> Out<A, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|items| {
+                fold_from_checkpoint(items, checkpointer, init, encode, decode, step)
+            }),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use super::*;
+use crate::debug::Value;
+use std::any::{type_name, TypeId};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::str::FromStr;
+
+type Parser = Box<dyn Fn(&str) -> Result<Box<dyn Value>, String>>;
+
+const USAGE: &str = "usage:
+  run <pipeline> <input>            run a pipeline and print its output
+  list                              list the registered acts and pipelines
+  graph <pipeline> [dot|mermaid]    print a pipeline as a graph (default dot)
+  bench <pipeline> <input> [runs]   time every stage over many runs (default 100)
+
+<pipeline> is a defined pipeline or registered acts separated by commas";
+
+/// the command line front end for an `ActRegistry`: runs, lists, draws and
+/// benchmarks pipelines of registered acts, so a binary can be used as a data
+/// tool without writing code for every chain.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let mut registry = ActRegistry::<Failure>::new();
+/// registry
+///     .register("add2", |x: i32| Ok(x + 2))
+///     .register("square", |x: i32| Ok(x * x));
+/// let mut cli = Cli::new(registry);
+/// cli.pipeline("grow", &["add2", "square"]);
+///
+/// let mut out = Vec::new();
+/// cli.run(&["run", "grow", "3"], &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "25\n");
+///
+/// let mut out = Vec::new();
+/// cli.run(&["graph", "add2,square", "mermaid"], &mut out).unwrap();
+/// assert!(String::from_utf8(out).unwrap().starts_with("flowchart LR"));
+/// ```
+pub struct Cli<E = Failure> {
+    registry: ActRegistry<E>,
+    pipelines: BTreeMap<String, Vec<String>>,
+    parsers: Vec<(String, TypeId, Parser)>,
+}
+
+impl<E> Cli<E>
+where
+    E: Debug + 'static,
+{
+    /// a cli over `registry`, accepting `i32`, `i64`, `f64` and `String` inputs
+    pub fn new(registry: ActRegistry<E>) -> Self {
+        let mut cli = Cli {
+            registry,
+            pipelines: BTreeMap::new(),
+            parsers: Vec::new(),
+        };
+        cli.input_type::<i32>("i32")
+            .input_type::<i64>("i64")
+            .input_type::<f64>("f64")
+            .input_type::<String>("String");
+        cli
+    }
+
+    /// accept inputs of type `T`, parsed from text, for pipelines starting
+    /// with an act that takes `T`
+    pub fn input_type<T>(&mut self, name: &str) -> &mut Self
+    where
+        T: FromStr + Debug + 'static,
+        T::Err: std::fmt::Display,
+    {
+        self.parsers.push((
+            name.to_string(),
+            TypeId::of::<T>(),
+            Box::new(|s: &str| {
+                s.parse::<T>()
+                    .map(|v| Box::new(v) as Box<dyn Value>)
+                    .map_err(|e| format!("can't parse '{}' as {}: {}", s, type_name::<T>(), e))
+            }),
+        ));
+        self
+    }
+
+    /// define a pipeline of registered acts under `name`
+    pub fn pipeline(&mut self, name: &str, acts: &[&str]) -> &mut Self {
+        self.pipelines.insert(
+            name.to_string(),
+            acts.iter().map(|a| a.to_string()).collect(),
+        );
+        self
+    }
+
+    /// the registered acts a pipeline name or comma separated list stands for,
+    /// checked to fit together
+    fn resolve(&self, pipeline: &str) -> Result<Vec<&RegisteredAct<E>>, String> {
+        let names: Vec<&str> = match self.pipelines.get(pipeline) {
+            Some(names) => names.iter().map(String::as_str).collect(),
+            None => pipeline.split(',').map(str::trim).collect(),
+        };
+        let mut acts: Vec<&RegisteredAct<E>> = Vec::with_capacity(names.len());
+        for name in names {
+            let act = self
+                .registry
+                .get(name)
+                .ok_or_else(|| format!("no pipeline or act named '{}'", name))?;
+            if let Some(prev) = acts.last() {
+                if !prev.feeds(act) {
+                    return Err(format!(
+                        "'{}' takes {}, but '{}' produces {}",
+                        act.name,
+                        short_type_name(act.input_type),
+                        prev.name,
+                        short_type_name(prev.output_type)
+                    ));
+                }
+            }
+            acts.push(act);
+        }
+        Ok(acts)
+    }
+
+    /// parse `text` as whichever input type the first act takes
+    fn parse_input(&self, act: &RegisteredAct<E>, text: &str) -> Result<Box<dyn Value>, String> {
+        let (_, _, parse) = self
+            .parsers
+            .iter()
+            .find(|(_, id, _)| *id == act.input_id())
+            .ok_or_else(|| {
+                format!(
+                    "'{}' takes {}, which isn't a known input type",
+                    act.name,
+                    short_type_name(act.input_type)
+                )
+            })?;
+        parse(text)
+    }
+
+    /// run the acts on `text`, calling `timed` with the time each stage took
+    fn execute(
+        &self,
+        acts: &[&RegisteredAct<E>],
+        text: &str,
+        mut timed: impl FnMut(usize, Duration),
+    ) -> Result<Box<dyn Value>, String> {
+        let first = acts.first().ok_or("the pipeline has no stages")?;
+        let mut value = self.parse_input(first, text)?;
+        for (i, act) in acts.iter().enumerate() {
+            let start = Instant::now();
+            value = act
+                .run(value)
+                .map_err(|e| format!("'{}' failed: {:?}", act.name, e))?;
+            timed(i, start.elapsed());
+        }
+        Ok(value)
+    }
+
+    fn list(&self) -> String {
+        let mut out = String::from("acts:\n");
+        for act in self.registry.iter() {
+            out += &format!(
+                "  {}: {} -> {}\n",
+                act.name,
+                short_type_name(act.input_type),
+                short_type_name(act.output_type)
+            );
+        }
+        out += "pipelines:\n";
+        for (name, acts) in &self.pipelines {
+            out += &format!("  {}: {}\n", name, acts.join(" -> "));
+        }
+        out += "input types: ";
+        out += &self
+            .parsers
+            .iter()
+            .map(|(name, _, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        out
+    }
+
+    fn graph(
+        &self,
+        name: &str,
+        acts: &[&RegisteredAct<E>],
+        format: &str,
+    ) -> Result<String, String> {
+        let input = acts
+            .first()
+            .map(|a| short_type_name(a.input_type))
+            .unwrap_or_default();
+        let mut out = String::new();
+        match format {
+            "dot" => {
+                out += &format!("digraph \"{}\" {{\n  rankdir=LR;\n", name);
+                out += &format!("  input [label=\"{}\", shape=plaintext];\n", input);
+                let mut prev = "input".to_string();
+                for (i, act) in acts.iter().enumerate() {
+                    out += &format!("  s{} [label=\"{}\", shape=box];\n", i, act.name);
+                    out += &format!("  {} -> s{};\n", prev, i);
+                    prev = format!("s{}", i);
+                }
+                out += "}";
+            }
+            "mermaid" => {
+                out += "flowchart LR\n";
+                out += &format!("  input([{}])", input);
+                for (i, act) in acts.iter().enumerate() {
+                    out += &format!(" --> s{}[\"{}\"]", i, act.name);
+                }
+            }
+            other => {
+                return Err(format!(
+                    "unknown graph format '{}'; use dot or mermaid",
+                    other
+                ))
+            }
+        }
+        Ok(out)
+    }
+
+    fn bench(&self, acts: &[&RegisteredAct<E>], text: &str, runs: usize) -> Result<String, String> {
+        let mut totals = vec![Duration::ZERO; acts.len()];
+        for _ in 0..runs {
+            self.execute(acts, text, |i, took| totals[i] += took)?;
+        }
+        let width = acts.iter().map(|a| a.name.len()).max().unwrap_or(0);
+        let mut out = format!("{} runs\n", runs);
+        for (act, total) in acts.iter().zip(&totals) {
+            out += &format!(
+                "  {:width$}  total {:?}  mean {:?}\n",
+                act.name,
+                total,
+                *total / runs as u32,
+                width = width
+            );
+        }
+        out += &format!(
+            "  {:width$}  total {:?}",
+            "all",
+            totals.iter().sum::<Duration>(),
+            width = width
+        );
+        Ok(out)
+    }
+
+    /// handle one command line (without the program name), writing its
+    /// output. errors are returned as messages meant for the user.
+    pub fn run<S: AsRef<str>, W: Write>(&self, args: &[S], mut output: W) -> Result<(), String> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let out = match args.as_slice() {
+            ["run", pipeline, input] => {
+                let acts = self.resolve(pipeline)?;
+                format!("{:?}", self.execute(&acts, input, |_, _| {})?)
+            }
+            ["list"] => self.list(),
+            ["graph", pipeline] => self.graph(pipeline, &self.resolve(pipeline)?, "dot")?,
+            ["graph", pipeline, format] => {
+                self.graph(pipeline, &self.resolve(pipeline)?, format)?
+            }
+            ["bench", pipeline, input] => self.bench(&self.resolve(pipeline)?, input, 100)?,
+            ["bench", pipeline, input, runs] => {
+                let runs = runs
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|r| *r > 0)
+                    .ok_or_else(|| format!("'{}' isn't a number of runs", runs))?;
+                self.bench(&self.resolve(pipeline)?, input, runs)?
+            }
+            ["help"] | [] => USAGE.to_string(),
+            _ => return Err(USAGE.to_string()),
+        };
+        writeln!(output, "{}", out).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,595 @@
+use super::*;
+use std::hash::Hash;
+
+/// What `interleave` does once one side runs out of items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exhausted {
+    /// keep taking items from the side that still has some
+    Drain,
+    /// stop as soon as either side is empty
+    Stop,
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// round-robin the items of this reactor with the items of `other`,
+    /// starting with this side. useful for merging prioritized work queues.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let urgent = || Reactor::<Vec<&str>>::input(vec!["u1", "u2", "u3"]);
+    /// let normal = || Reactor::<Vec<&str>>::input(vec!["n1"]);
+    ///
+    /// let drained = urgent().interleave(normal(), Exhausted::Drain).run();
+    /// assert_eq!(drained.unwrap(), ["u1", "n1", "u2", "u3"]);
+    ///
+    /// let stopped = urgent().interleave(normal(), Exhausted::Stop).run();
+    /// assert_eq!(stopped.unwrap(), ["u1", "n1", "u2"]);
+    ///
+    /// let short_left = normal().interleave(urgent(), Exhausted::Stop).run();
+    /// assert_eq!(short_left.unwrap(), ["n1", "u1"]);
+    ///
+    /// let down = Reactor::<()>::input(()).and_then(|_| Err::<Vec<&str>, _>(Failure::Custom("queue down".into())));
+    /// let failed = urgent().interleave(down, Exhausted::Drain).run();
+    /// assert!(failed.is_err());
+    /// ```
+    pub fn interleave<T>(
+        self,
+        other: Reactor<Vec<T>, E>,
+        on_exhausted: Exhausted,
+    ) -> Reactor<Vec<T>, E>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Reactor {
+            input: self.input.and_then(|left| {
+                let right = other.run()?;
+                let mut left = left.into_iter();
+                let mut right = right.into_iter();
+                let mut out = Vec::new();
+                loop {
+                    match (left.next(), right.next()) {
+                        (Some(a), Some(b)) => {
+                            out.push(a);
+                            out.push(b);
+                        }
+                        (Some(a), None) => {
+                            out.push(a);
+                            if on_exhausted == Exhausted::Drain {
+                                out.extend(left);
+                            }
+                            break;
+                        }
+                        (None, Some(b)) => {
+                            if on_exhausted == Exhausted::Drain {
+                                out.push(b);
+                                out.extend(right);
+                            }
+                            break;
+                        }
+                        (None, None) => break,
+                    }
+                }
+                Ok(out)
+            }),
+        }
+    }
+}
+
+/// the largest number of pairs `cross_join` will produce before failing.
+pub const DEFAULT_CROSS_JOIN_LIMIT: usize = 1_000_000;
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// pair every item of this reactor with every item of `other`.
+    /// fails if the product would exceed `DEFAULT_CROSS_JOIN_LIMIT` pairs.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let sizes = || Reactor::<Vec<&str>>::input(vec!["S", "M"]);
+    /// let pairs = sizes().cross_join(Reactor::input(vec![1, 2])).run();
+    /// assert_eq!(pairs.unwrap(), [("S", 1), ("S", 2), ("M", 1), ("M", 2)]);
+    ///
+    /// let empty = sizes().cross_join(Reactor::input(Vec::<u8>::new())).run();
+    /// assert!(empty.unwrap().is_empty());
+    ///
+    /// let too_big = sizes().cross_join_with_limit(Reactor::input(vec![1, 2]), 3).run();
+    /// assert!(matches!(too_big, Err(Failure::InvalidInput(_))));
+    /// ```
+    pub fn cross_join<T, U>(self, other: Reactor<Vec<U>, E>) -> Reactor<Vec<(T, U)>, E>
+    where
+        I: IntoIterator<Item = T>,
+        T: Clone,
+        U: Clone,
+    {
+        self.cross_join_with_limit(other, DEFAULT_CROSS_JOIN_LIMIT)
+    }
+
+    /// same as `cross_join`, but with a caller-chosen limit on the number of pairs.
+    pub fn cross_join_with_limit<T, U>(
+        self,
+        other: Reactor<Vec<U>, E>,
+        limit: usize,
+    ) -> Reactor<Vec<(T, U)>, E>
+    where
+        I: IntoIterator<Item = T>,
+        T: Clone,
+        U: Clone,
+    {
+        Reactor {
+            input: self.input.and_then(|left| {
+                let right = other.run()?;
+                let left: Vec<T> = left.into_iter().collect();
+                let size = left.len().checked_mul(right.len());
+                match size {
+                    Some(n) if n <= limit => Ok(left
+                        .iter()
+                        .flat_map(|a| right.iter().map(move |b| (a.clone(), b.clone())))
+                        .collect()),
+                    _ => Err(Failure::InvalidInput(format!(
+                        "cross join of {} x {} items exceeds the limit of {}",
+                        left.len(),
+                        right.len(),
+                        limit
+                    ))
+                    .into()),
+                }
+            }),
+        }
+    }
+}
+
+/// orders heap entries with a borrowed comparison function, so `BinaryHeap`
+/// can be used for items that aren't `Ord` themselves.
+struct Ranked<'a, T, F> {
+    item: T,
+    cmp: &'a F,
+}
+
+impl<T, F> PartialEq for Ranked<'_, T, F>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn eq(&self, other: &Self) -> bool {
+        (self.cmp)(&self.item, &other.item) == Ordering::Equal
+    }
+}
+
+impl<T, F> Eq for Ranked<'_, T, F> where F: Fn(&T, &T) -> Ordering {}
+
+impl<T, F> PartialOrd for Ranked<'_, T, F>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F> Ord for Ranked<'_, T, F>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed, so the heap keeps the smallest retained item on top
+        (self.cmp)(&other.item, &self.item)
+    }
+}
+
+/// keeps the `k` greatest items according to `cmp`, largest first.
+fn select_k<T, F>(items: impl IntoIterator<Item = T>, k: usize, cmp: F) -> Vec<T>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    if k == 0 {
+        return Vec::new();
+    }
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+    for item in items {
+        heap.push(Ranked { item, cmp: &cmp });
+        if heap.len() > k {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec().into_iter().map(|r| r.item).collect()
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// select the `k` largest items according to `cmp`, largest first.
+    /// uses a heap bounded to `k` entries instead of sorting the whole collection.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let scores = || Reactor::<Vec<(&str, u32)>>::input(vec![("a", 3), ("b", 9), ("c", 1), ("d", 7)]);
+    /// let by_score = |x: &(&str, u32), y: &(&str, u32)| x.1.cmp(&y.1);
+    ///
+    /// assert_eq!(scores().top_k(2, by_score).run().unwrap(), [("b", 9), ("d", 7)]);
+    /// assert_eq!(scores().min_k(3, by_score).run().unwrap(), [("c", 1), ("a", 3), ("d", 7)]);
+    /// assert_eq!(scores().top_k(10, by_score).run().unwrap().len(), 4);
+    /// assert!(scores().top_k(0, by_score).run().unwrap().is_empty());
+    /// ```
+    pub fn top_k<T, F>(self, k: usize, cmp: F) -> Reactor<Vec<T>, E>
+    where
+        I: IntoIterator<Item = T>,
+        F: Fn(&T, &T) -> Ordering,
+    {
+        Reactor {
+            input: self.input.map(|i| select_k(i, k, cmp)),
+        }
+    }
+
+    /// select the `k` smallest items according to `cmp`, smallest first.
+    pub fn min_k<T, F>(self, k: usize, cmp: F) -> Reactor<Vec<T>, E>
+    where
+        I: IntoIterator<Item = T>,
+        F: Fn(&T, &T) -> Ordering,
+    {
+        Reactor {
+            input: self.input.map(|i| select_k(i, k, |a: &T, b: &T| cmp(b, a))),
+        }
+    }
+}
+
+/// which records a keyed join keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// only records with a match on both sides
+    Inner,
+    /// every record of the left side, matched or not
+    Left,
+    /// every record of both sides
+    Outer,
+}
+
+/// one row of a keyed join; the side without a match is `None`.
+pub type Joined<A, B> = (Option<A>, Option<B>);
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// join this reactor's records with `other`'s on the keys produced by
+    /// `key_a` and `key_b`. every pair of matching records is produced; the side
+    /// missing from an unmatched record is `None`. left records keep their order,
+    /// and for outer joins unmatched right records follow in their own order.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let users = || Reactor::<Vec<(u32, &str)>>::input(vec![(1, "ann"), (2, "bob"), (3, "cy")]);
+    /// let orders = || Reactor::<Vec<(u32, &str)>>::input(vec![(1, "book"), (4, "pen"), (1, "lamp")]);
+    /// let join = |kind| {
+    ///     users()
+    ///         .join_by_key(orders(), |u: &(u32, &str)| u.0, |o: &(u32, &str)| o.0, kind)
+    ///         .map(|rows| rows.into_iter().map(|(u, o)| (u.map(|u| u.1), o.map(|o| o.1))).collect::<Vec<_>>())
+    ///         .run()
+    ///         .unwrap()
+    /// };
+    ///
+    /// assert_eq!(join(JoinKind::Inner), [(Some("ann"), Some("book")), (Some("ann"), Some("lamp"))]);
+    /// assert_eq!(
+    ///     join(JoinKind::Left),
+    ///     [(Some("ann"), Some("book")), (Some("ann"), Some("lamp")), (Some("bob"), None), (Some("cy"), None)]
+    /// );
+    /// assert_eq!(join(JoinKind::Outer).last(), Some(&(None, Some("pen"))));
+    /// assert_eq!(join(JoinKind::Outer).len(), 5);
+    /// ```
+    pub fn join_by_key<A, B, K, KA, KB>(
+        self,
+        other: Reactor<Vec<B>, E>,
+        key_a: KA,
+        key_b: KB,
+        kind: JoinKind,
+    ) -> Reactor<Vec<Joined<A, B>>, E>
+    where
+        I: IntoIterator<Item = A>,
+        A: Clone,
+        B: Clone,
+        K: Hash + Eq,
+        KA: Fn(&A) -> K,
+        KB: Fn(&B) -> K,
+    {
+        Reactor {
+            input: self.input.and_then(|left| {
+                let right = other.run()?;
+                let mut index: HashMap<K, Vec<usize>> = HashMap::new();
+                for (n, b) in right.iter().enumerate() {
+                    index.entry(key_b(b)).or_default().push(n);
+                }
+                let mut matched = vec![false; right.len()];
+                let mut out = Vec::new();
+                for a in left {
+                    match index.get(&key_a(&a)) {
+                        Some(rows) => {
+                            for &n in rows {
+                                matched[n] = true;
+                                out.push((Some(a.clone()), Some(right[n].clone())));
+                            }
+                        }
+                        None if kind != JoinKind::Inner => out.push((Some(a), None)),
+                        None => {}
+                    }
+                }
+                if kind == JoinKind::Outer {
+                    out.extend(
+                        right
+                            .into_iter()
+                            .zip(matched)
+                            .filter(|(_, m)| !m)
+                            .map(|(b, _)| (None, Some(b))),
+                    );
+                }
+                Ok(out)
+            }),
+        }
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+    I: IntoIterator,
+{
+    /// like `for_each`, but collecting the outputs into any `FromIterator`
+    /// target instead of a `Vec`
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::collections::HashSet;
+    ///
+    /// let lengths = Reactor::<Vec<&str>>::input(vec!["a", "bb", "cc", "d"])
+    ///     .collect_into::<HashSet<_>, _, _>(|s: &str| Ok(s.len()))
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(lengths, HashSet::from([1, 2]));
+    /// ```
+    pub fn collect_into<C, O, T>(self, transform: T) -> Reactor<C, E>
+    where
+        C: FromIterator<O>,
+        T: Act<I::Item, O, E>,
+    {
+        Reactor {
+            input: self
+                .input
+                .and_then(|i| i.into_iter().map(|item| transform.act(item)).collect()),
+        }
+    }
+
+    /// run `transform` on every item, keeping the `Some` outputs
+    pub fn filter_map<O, T>(self, transform: T) -> Reactor<Vec<O>, E>
+    where
+        T: Act<I::Item, Option<O>, E>,
+    {
+        self.filter_map_into(transform)
+    }
+
+    /// like `filter_map`, but collecting into any `FromIterator` target
+    pub fn filter_map_into<C, O, T>(self, transform: T) -> Reactor<C, E>
+    where
+        C: FromIterator<O>,
+        T: Act<I::Item, Option<O>, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                i.into_iter()
+                    .filter_map(|item| transform.act(item).transpose())
+                    .collect()
+            }),
+        }
+    }
+}
+
+impl<A, B, E> Reactor<Vec<(A, B)>, E>
+where
+    E: Debug,
+{
+    /// split pairs into two collections, e.g. to send keys and values to
+    /// different sinks
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let (ids, scores) = Reactor::<Vec<(u32, f64)>>::input(vec![(1, 0.5), (2, 0.9)])
+    ///     .unzip()
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(ids, vec![1, 2]);
+    /// assert_eq!(scores, vec![0.5, 0.9]);
+    /// ```
+    pub fn unzip(self) -> Reactor<(Vec<A>, Vec<B>), E> {
+        self.map(|pairs| pairs.into_iter().unzip())
+    }
+}
+
+/// what `try_for_each` should do after an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemFlow<O, E = Failure> {
+    /// keep the output and go on with the next item
+    Continue(O),
+    /// drop this item and go on
+    SkipItem,
+    /// keep the output and stop, leaving the remaining items unprocessed
+    Stop(O),
+    /// fail the chain
+    Fail(E),
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+    I: IntoIterator,
+{
+    /// like `for_each`, but the act decides per item whether to keep going,
+    /// skip the item, stop early or fail. returning `Err` is the same as
+    /// `ItemFlow::Fail`.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// // take lines up to an end marker, ignoring comments
+    /// let lines = Reactor::<Vec<&str>>::input(vec!["a", "# note", "b", "END", "c"])
+    ///     .try_for_each(|line: &str| {
+    ///         Ok(match line {
+    ///             "END" => ItemFlow::Stop(line.to_lowercase()),
+    ///             l if l.starts_with('#') => ItemFlow::SkipItem,
+    ///             l => ItemFlow::Continue(l.to_string()),
+    ///         })
+    ///     })
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(lines, vec!["a", "b", "end"]);
+    /// ```
+    pub fn try_for_each<O, T>(self, transform: T) -> Reactor<Vec<O>, E>
+    where
+        T: Act<I::Item, ItemFlow<O, E>, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let mut out = Vec::new();
+                for item in i {
+                    match transform.act(item)? {
+                        ItemFlow::Continue(o) => out.push(o),
+                        ItemFlow::SkipItem => {}
+                        ItemFlow::Stop(o) => {
+                            out.push(o);
+                            break;
+                        }
+                        ItemFlow::Fail(e) => return Err(e),
+                    }
+                }
+                Ok(out)
+            }),
+        }
+    }
+
+    /// like `for_each`, but without collecting: the reactor holds an iterator
+    /// that runs `transform` on each item as it is pulled, so inputs of any
+    /// size can be streamed through in constant memory.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let total = Reactor::<std::ops::RangeInclusive<u64>>::input(1..=1_000_000)
+    ///     .for_each_lazy(|x: u64| Ok(x * 2))
+    ///     .and_then(|doubled| doubled.sum::<Out<u64>>())
+    ///     .run();
+    /// assert_eq!(total.unwrap(), 1_000_001_000_000);
+    /// ```
+    pub fn for_each_lazy<O, T>(self, transform: T) -> Reactor<impl Iterator<Item = Out<O, E>>, E>
+    where
+        T: Act<I::Item, O, E>,
+    {
+        Reactor {
+            input: self
+                .input
+                .map(|i| i.into_iter().map(move |item| transform.act(item))),
+        }
+    }
+}
+
+impl<T, E> Reactor<Vec<T>, E>
+where
+    E: Debug,
+{
+    /// a reactor over the items of a fallible iterator, such as `read_dir()`.
+    /// the items are collected up front, stopping at the first error, which
+    /// (converted into the reactor's error type) fails the reactor. use
+    /// `from_try_iter_lazy` to pull them one at a time instead.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let lines = std::io::Cursor::new("a\nb\n");
+    /// let out = Reactor::<Vec<String>>::from_try_iter(std::io::BufRead::lines(lines))
+    ///     .for_each(|s: String| Ok(s.to_uppercase()))
+    ///     .run();
+    /// assert_eq!(out.unwrap(), ["A", "B"]);
+    /// ```
+    pub fn from_try_iter<It, E2>(items: It) -> Self
+    where
+        It: IntoIterator<Item = Result<T, E2>>,
+        E: From<E2>,
+    {
+        Reactor {
+            input: items
+                .into_iter()
+                .map(|item| item.map_err(E::from))
+                .collect(),
+        }
+    }
+    /// like `from_try_iter`, but without collecting: the reactor holds an
+    /// iterator that yields each item, or its converted error, as it is
+    /// pulled, like `for_each_lazy`.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::cell::Cell;
+    ///
+    /// let pulled = Cell::new(0);
+    /// let numbers = (1..).map(|n: u64| {
+    ///     pulled.set(pulled.get() + 1);
+    ///     if n == 4 { Err("four".to_string()) } else { Ok(n) }
+    /// });
+    /// let out = Reactor::<Vec<u64>, String>::from_try_iter_lazy(numbers)
+    ///     .map(|items| items.take(2).collect::<Vec<_>>())
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(out, [Ok(1), Ok(2)]);
+    /// assert_eq!(pulled.get(), 2);
+    /// ```
+    pub fn from_try_iter_lazy<It, E2>(items: It) -> Reactor<impl Iterator<Item = Out<T, E>>, E>
+    where
+        It: IntoIterator<Item = Result<T, E2>>,
+        E: From<E2>,
+    {
+        Reactor {
+            input: Ok(items.into_iter().map(|item| item.map_err(E::from))),
+        }
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// collect items that are results themselves, failing on the first error.
+    /// the mid-chain form of `from_try_iter`.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let out = Reactor::<&str>::input("1,2,x")
+    ///     .then(|s: &str| {
+    ///         Ok(s.split(',')
+    ///             .map(|n| n.parse::<i32>().map_err(|e| Failure::InvalidInput(e.to_string())))
+    ///             .collect::<Vec<_>>())
+    ///     })
+    ///     .try_collect()
+    ///     .run();
+    /// assert!(matches!(out, Err(Failure::InvalidInput(_))));
+    /// ```
+    pub fn try_collect<T, E2>(self) -> Reactor<Vec<T>, E>
+    where
+        I: IntoIterator<Item = Result<T, E2>>,
+        E: From<E2>,
+    {
+        Reactor {
+            input: self.input.and_then(|items| {
+                items
+                    .into_iter()
+                    .map(|item| item.map_err(E::from))
+                    .collect()
+            }),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use super::*;
+
+/// an act that also gets mutable access to a context shared by the whole
+/// chain: configuration, a connection, counters. see `Reactor::with_context`.
+pub trait ActWithCtx<C, I, O, E = Failure>
+where
+    E: Debug,
+{
+    fn act_with(&self, ctx: &mut C, input: I) -> Out<O, E>;
+}
+
+impl<C, I, O, E, F> ActWithCtx<C, I, O, E> for F
+where
+    F: Fn(&mut C, I) -> Out<O, E>,
+    E: Debug,
+{
+    fn act_with(&self, ctx: &mut C, input: I) -> Out<O, E> {
+        self(ctx, input)
+    }
+}
+
+/// a reactor carrying a context for its stages. see `Reactor::with_context`.
+pub struct WithContext<I, C, E = Failure> {
+    reactor: Reactor<I, E>,
+    ctx: C,
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// start a chain on `input` with `ctx` available to every `then_ctx`
+    /// stage, instead of threading it through each stage's input and output.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// struct Ctx {
+    ///     rate: f64,
+    ///     conversions: u32,
+    /// }
+    ///
+    /// let convert = |ctx: &mut Ctx, usd: f64| {
+    ///     ctx.conversions += 1;
+    ///     Ok(usd * ctx.rate)
+    /// };
+    /// let (eur, ctx) = Reactor::with_context(Ctx { rate: 0.5, conversions: 0 }, "10")
+    ///     .then(|s: &str| s.parse::<f64>().map_err(|e| Failure::InvalidInput(e.to_string())))
+    ///     .then_ctx(convert)
+    ///     .then_ctx(convert)
+    ///     .run_with_context();
+    /// assert_eq!(eur.unwrap(), 2.5);
+    /// assert_eq!(ctx.conversions, 2);
+    /// ```
+    pub fn with_context<C>(ctx: C, input: I) -> WithContext<I, C, E> {
+        Reactor::input(input).context(ctx)
+    }
+
+    /// carry `ctx` for the stages from here on
+    pub fn context<C>(self, ctx: C) -> WithContext<I, C, E> {
+        WithContext { reactor: self, ctx }
+    }
+}
+
+impl<I, C, E> WithContext<I, C, E>
+where
+    E: Debug,
+{
+    /// run `transform` with the context
+    pub fn then_ctx<O, T>(mut self, transform: T) -> WithContext<O, C, E>
+    where
+        T: ActWithCtx<C, I, O, E>,
+    {
+        let ctx = &mut self.ctx;
+        WithContext {
+            reactor: self.reactor.and_then(|i| transform.act_with(ctx, i)),
+            ctx: self.ctx,
+        }
+    }
+
+    /// run `transform` without the context
+    pub fn then<O, T>(self, transform: T) -> WithContext<O, C, E>
+    where
+        T: Act<I, O, E>,
+    {
+        WithContext {
+            reactor: self.reactor.then(transform),
+            ctx: self.ctx,
+        }
+    }
+
+    pub fn ctx(&self) -> &C {
+        &self.ctx
+    }
+
+    pub fn ctx_mut(&mut self) -> &mut C {
+        &mut self.ctx
+    }
+
+    /// drop the context and continue with a plain reactor
+    pub fn into_reactor(self) -> Reactor<I, E> {
+        self.reactor
+    }
+
+    pub fn run(self) -> Out<I, E> {
+        self.reactor.run()
+    }
+
+    /// finish the chain like `run`, handing back the context as well
+    pub fn run_with_context(self) -> (Out<I, E>, C) {
+        (self.reactor.run(), self.ctx)
+    }
+}
@@ -0,0 +1,268 @@
+use super::*;
+use crate::describe::{write_arrows, write_tree};
+use std::any::{type_name, Any};
+
+/// a type-erased intermediate value that can still be printed
+pub(crate) trait Value: Any + Debug {
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn as_debug(&self) -> &dyn Debug;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + Debug> Value for T {
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn as_debug(&self) -> &dyn Debug {
+        self
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub(crate) type Stage<E> = Box<dyn Fn(Box<dyn Value>) -> Out<Box<dyn Value>, E>>;
+
+/// checks a stage's output and, if it matches, hands it to the hook
+type Breakpoint = Box<dyn FnMut(&dyn Value) -> bool>;
+
+/// what the debug runner knows about a stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageInfo {
+    pub index: usize,
+    pub name: String,
+    pub input_type: &'static str,
+    pub output_type: &'static str,
+}
+
+/// the outcome of `DebugRunner::step`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// the stage ran; its output is now the current value
+    Ran(StageInfo),
+    /// the stage ran and a breakpoint matched its output
+    Break(StageInfo),
+    /// the stage failed; the error is available from `DebugRunner::error`
+    Failed(StageInfo),
+    /// there are no stages left to run
+    Done,
+}
+
+/// runs a chain one stage at a time under the caller's control, exposing the
+/// intermediate value between stages. meant for tracking down where a long
+/// chain goes wrong, not for production runs: stages are type-erased, and
+/// every intermediate value must be `Debug`.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let mut runner = DebugRunner::<i32>::input(5)
+///     .stage("add", |x: i32| Ok(x + 2))
+///     .stage("square", |x: i32| Ok(x * x));
+///
+/// while let Step::Ran(stage) = runner.step() {
+///     println!("{} -> {:?}", stage.name, runner.current());
+/// }
+/// assert_eq!(runner.finish().unwrap(), 49);
+/// ```
+pub struct DebugRunner<O, E = Failure> {
+    input_type: &'static str,
+    stages: Vec<(StageInfo, Stage<E>)>,
+    breakpoints: Vec<(usize, Breakpoint)>,
+    value: Option<Box<dyn Value>>,
+    next: usize,
+    error: Option<E>,
+    _marker: PhantomData<O>,
+}
+
+impl<I, E> DebugRunner<I, E>
+where
+    I: Any + Debug,
+    E: Debug,
+{
+    pub fn input(input: I) -> Self {
+        DebugRunner {
+            input_type: type_name::<I>(),
+            stages: Vec::new(),
+            breakpoints: Vec::new(),
+            value: Some(Box::new(input)),
+            next: 0,
+            error: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<O, E> DebugRunner<O, E>
+where
+    O: Any + Debug,
+    E: Debug + 'static,
+{
+    /// append a named stage; nothing runs until `step` or `finish`
+    pub fn stage<O2, T>(self, name: &str, act: T) -> DebugRunner<O2, E>
+    where
+        O2: Any + Debug,
+        T: Act<O, O2, E> + 'static,
+    {
+        let info = StageInfo {
+            index: self.stages.len(),
+            name: name.to_string(),
+            input_type: type_name::<O>(),
+            output_type: type_name::<O2>(),
+        };
+        let stage: Stage<E> = Box::new(move |value: Box<dyn Value>| {
+            let input = value
+                .into_any()
+                .downcast::<O>()
+                .expect("debug runner stage received a value of the wrong type");
+            act.act(*input).map(|o| Box::new(o) as Box<dyn Value>)
+        });
+        let mut stages = self.stages;
+        stages.push((info, stage));
+        DebugRunner {
+            input_type: self.input_type,
+            stages,
+            breakpoints: self.breakpoints,
+            value: self.value,
+            next: self.next,
+            error: self.error,
+            _marker: PhantomData,
+        }
+    }
+
+    /// pause after the most recently added stage whenever its output matches
+    /// `predicate`, handing the value to `hook` first. breakpoints only exist on
+    /// the debug runner, so they cost nothing in regular runs.
+    pub fn break_when<P, H>(mut self, predicate: P, mut hook: H) -> Self
+    where
+        P: Fn(&O) -> bool + 'static,
+        H: FnMut(&O) + 'static,
+    {
+        assert!(
+            !self.stages.is_empty(),
+            "break_when needs a stage to watch; add one first"
+        );
+        let breakpoint: Breakpoint =
+            Box::new(
+                move |value: &dyn Value| match value.as_any().downcast_ref::<O>() {
+                    Some(v) if predicate(v) => {
+                        hook(v);
+                        true
+                    }
+                    _ => false,
+                },
+            );
+        self.breakpoints.push((self.stages.len() - 1, breakpoint));
+        self
+    }
+}
+
+impl<O, E> DebugRunner<O, E>
+where
+    O: Any,
+    E: Debug,
+{
+    /// run the next stage
+    pub fn step(&mut self) -> Step {
+        if self.error.is_some() || self.next >= self.stages.len() {
+            return Step::Done;
+        }
+        let (info, stage) = &self.stages[self.next];
+        let value = self.value.take().expect("debug runner has no value");
+        self.next += 1;
+        match stage(value) {
+            Ok(v) => {
+                let index = self.next - 1;
+                let mut hit = false;
+                for (_, breakpoint) in self.breakpoints.iter_mut().filter(|(i, _)| *i == index) {
+                    hit |= breakpoint(&*v);
+                }
+                self.value = Some(v);
+                if hit {
+                    Step::Break(info.clone())
+                } else {
+                    Step::Ran(info.clone())
+                }
+            }
+            Err(e) => {
+                self.error = Some(e);
+                Step::Failed(info.clone())
+            }
+        }
+    }
+
+    /// the value the next stage will receive (or the final output), if the
+    /// chain hasn't failed
+    pub fn current(&self) -> Option<&dyn Debug> {
+        self.value.as_ref().map(|v| v.as_debug())
+    }
+
+    /// the error of the failed stage, if any
+    pub fn error(&self) -> Option<&E> {
+        self.error.as_ref()
+    }
+
+    /// the stage `step` will run next
+    pub fn next_stage(&self) -> Option<&StageInfo> {
+        self.stages.get(self.next).map(|(info, _)| info)
+    }
+
+    pub fn stages(&self) -> impl Iterator<Item = &StageInfo> {
+        self.stages.iter().map(|(info, _)| info)
+    }
+
+    /// run stages until a breakpoint matches, a stage fails, or the chain ends
+    pub fn resume(&mut self) -> Step {
+        loop {
+            match self.step() {
+                Step::Ran(_) => {}
+                step => return step,
+            }
+        }
+    }
+
+    /// run the remaining stages and return the final output, passing through
+    /// breakpoints (their hooks still run)
+    pub fn finish(mut self) -> Out<O, E> {
+        while let Step::Ran(_) | Step::Break(_) = self.step() {}
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let value = self.value.take().expect("debug runner has no value");
+        Ok(*value
+            .into_any()
+            .downcast::<O>()
+            .expect("debug runner produced a value of the wrong type"))
+    }
+}
+
+/// the chain as an arrow diagram: `i32 -> add -> i32 -> square -> i32`
+impl<O, E> std::fmt::Display for DebugRunner<O, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write_arrows(f, self.input_type, self.stages.iter().map(|(info, _)| info))
+    }
+}
+
+/// one line per stage with its types; `>` marks the stage that runs next and
+/// `*` the stages with breakpoints
+impl<O, E> Debug for DebugRunner<O, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write_tree(
+            f,
+            "DebugRunner",
+            self.input_type,
+            self.stages.iter().map(|(info, _)| info),
+            |stage| {
+                if stage.index == self.next && self.error.is_none() {
+                    ">"
+                } else if self.breakpoints.iter().any(|(i, _)| *i == stage.index) {
+                    "*"
+                } else {
+                    " "
+                }
+            },
+        )
+    }
+}
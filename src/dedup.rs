@@ -0,0 +1,153 @@
+use super::*;
+use crate::wire::{escape, unescape};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// remembers which record keys have already been emitted. implementations that
+/// persist their keys let a dedup stage skip records across process restarts.
+pub trait SeenStore {
+    /// true if `key` was recorded before
+    fn contains(&self, key: &str) -> bool;
+
+    /// record `key`, returning true if it wasn't seen before
+    fn insert(&mut self, key: &str) -> Out<bool>;
+
+    /// make sure everything recorded so far is durable
+    fn flush(&mut self) -> Out<()> {
+        Ok(())
+    }
+}
+
+/// an in-memory store, forgotten when the process exits
+impl SeenStore for HashSet<String> {
+    fn contains(&self, key: &str) -> bool {
+        HashSet::contains(self, key)
+    }
+
+    fn insert(&mut self, key: &str) -> Out<bool> {
+        Ok(HashSet::insert(self, key.to_string()))
+    }
+}
+
+/// a store backed by an append-only file holding one key per line, escaped
+/// like the wire format so any key fits on one line. keys found in the file on
+/// open are treated as already seen. a last line without its newline was cut
+/// off by a crash mid-write; it's dropped from the file, so that key counts
+/// as unseen.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::io::Write;
+///
+/// let path = std::env::temp_dir().join(format!("seen-{}.txt", std::process::id()));
+/// let _ = std::fs::remove_file(&path);
+/// let records = || vec!["a", "b\r", "a", "c\nd"];
+///
+/// let mut store = FileSeenStore::open(&path).unwrap();
+/// let first = Reactor::<Vec<&str>>::input(records()).dedup_seen(&mut store, |r| r.to_string()).run();
+/// assert_eq!(first.unwrap(), ["a", "b\r", "c\nd"]);
+/// drop(store);
+///
+/// // a restart remembers every key, and a torn write is forgotten
+/// std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"e").unwrap();
+/// let mut store = FileSeenStore::open(&path).unwrap();
+/// assert_eq!(store.len(), 3);
+/// let again = Reactor::<Vec<&str>>::input(vec!["b\r", "c\nd", "e"]).dedup_seen(&mut store, |r| r.to_string()).run();
+/// assert_eq!(again.unwrap(), ["e"]);
+/// drop(store);
+/// assert_eq!(FileSeenStore::open(&path).unwrap().len(), 4);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct FileSeenStore {
+    keys: HashSet<String>,
+    file: BufWriter<File>,
+}
+
+impl FileSeenStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Out<Self> {
+        let path = path.as_ref();
+        let io_failure =
+            |e: std::io::Error| Failure::Custom(format!("seen store {}: {}", path.display(), e));
+        let mut keys = HashSet::new();
+        let mut complete = None;
+        if path.exists() {
+            let contents = fs::read_to_string(path).map_err(io_failure)?;
+            let end = contents.rfind('\n').map_or(0, |n| n + 1);
+            keys.extend(contents[..end].split_terminator('\n').map(unescape));
+            if end < contents.len() {
+                complete = Some(end as u64);
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(io_failure)?;
+        if let Some(len) = complete {
+            file.set_len(len).map_err(io_failure)?;
+        }
+        Ok(FileSeenStore {
+            keys,
+            file: BufWriter::new(file),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl SeenStore for FileSeenStore {
+    fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    fn insert(&mut self, key: &str) -> Out<bool> {
+        if !self.keys.insert(key.to_string()) {
+            return Ok(false);
+        }
+        writeln!(self.file, "{}", escape(key))
+            .map_err(|e| Failure::Custom(format!("seen store write failed: {}", e)))?;
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Out<()> {
+        self.file
+            .flush()
+            .map_err(|e| Failure::Custom(format!("seen store flush failed: {}", e)))
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// drop every record whose key is already in `store`, and record the keys of
+    /// the ones passed on. the store is flushed before the stage completes, so a
+    /// persistent store won't hand out the same records on the next run.
+    pub fn dedup_seen<T, S, K>(self, store: &mut S, key: K) -> Reactor<Vec<T>, E>
+    where
+        I: IntoIterator<Item = T>,
+        S: SeenStore,
+        K: Fn(&T) -> String,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let mut out = Vec::new();
+                for item in i {
+                    if store.insert(&key(&item))? {
+                        out.push(item);
+                    }
+                }
+                store.flush()?;
+                Ok(out)
+            }),
+        }
+    }
+}
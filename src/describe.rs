@@ -0,0 +1,160 @@
+use super::*;
+use std::any::type_name;
+use std::fmt::{self, Formatter};
+
+/// a type name without module paths, e.g. `Vec<String>` rather than
+/// `alloc::vec::Vec<alloc::string::String>`.
+pub fn short_type_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut segment = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' || c == '{' || c == '}' {
+            segment.push(c);
+        } else {
+            out.push_str(segment.rsplit("::").next().unwrap_or(""));
+            segment.clear();
+            out.push(c);
+        }
+    }
+    out.push_str(segment.rsplit("::").next().unwrap_or(""));
+    out
+}
+
+/// `i32 -> add -> i32 -> square -> i32`
+pub(crate) fn write_arrows<'a>(
+    f: &mut Formatter,
+    input_type: &str,
+    stages: impl Iterator<Item = &'a StageInfo>,
+) -> fmt::Result {
+    write!(f, "{}", short_type_name(input_type))?;
+    for stage in stages {
+        write!(
+            f,
+            " -> {} -> {}",
+            stage.name,
+            short_type_name(stage.output_type)
+        )?;
+    }
+    Ok(())
+}
+
+/// one line per stage, with an optional marker in front of each
+pub(crate) fn write_tree<'a>(
+    f: &mut Formatter,
+    title: &str,
+    input_type: &str,
+    stages: impl Iterator<Item = &'a StageInfo>,
+    marker: impl Fn(&StageInfo) -> &'static str,
+) -> fmt::Result {
+    writeln!(f, "{} ({})", title, short_type_name(input_type))?;
+    let stages: Vec<_> = stages.collect();
+    for (n, stage) in stages.iter().enumerate() {
+        let branch = if n + 1 == stages.len() {
+            "└─"
+        } else {
+            "├─"
+        };
+        writeln!(
+            f,
+            "{}{} {}. {}: {} -> {}",
+            marker(stage),
+            branch,
+            stage.index,
+            stage.name,
+            short_type_name(stage.input_type),
+            short_type_name(stage.output_type)
+        )?;
+    }
+    Ok(())
+}
+
+impl<A, B, I, O1, O2, E> Debug for Chain<A, B, I, O1, O2, E>
+where
+    A: Act<I, O1, E>,
+    B: Act<O1, O2, E>,
+    E: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Chain({} -> {} -> {})",
+            short_type_name(type_name::<I>()),
+            short_type_name(type_name::<O1>()),
+            short_type_name(type_name::<O2>())
+        )
+    }
+}
+
+/// the shape of a statically built act: how many stages it has and the types
+/// flowing between them, known without running it. closures are one stage;
+/// a `Chain` adds up its parts. other acts can opt in with an empty impl,
+/// counting as one stage.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let chain = (|x: i32| -> Out<i32> { Ok(x + 1) })
+///     .then(|x: i32| Ok(x.to_string()))
+///     .then(|s: String| Ok(s.len()));
+/// assert_eq!(chain.stage_count(), 3);
+/// assert_eq!(chain.type_names(), ["i32", "i32", "String", "usize"]);
+///
+/// fn two_stages<S: Shape<i32, String>>(_: &S) {
+///     const { assert!(S::STAGES == 2) }
+/// }
+/// two_stages(&(|x: i32| -> Out<i32> { Ok(x * 2) }).then(|x: i32| Ok(x.to_string())));
+/// ```
+pub trait Shape<I, O, E = Failure>: Act<I, O, E>
+where
+    E: Debug,
+{
+    const STAGES: usize = 1;
+
+    /// the full names of the input type, then the output type of every stage
+    fn stage_types() -> Vec<&'static str>
+    where
+        Self: Sized,
+    {
+        vec![type_name::<I>(), type_name::<O>()]
+    }
+
+    fn stage_count(&self) -> usize
+    where
+        Self: Sized,
+    {
+        Self::STAGES
+    }
+
+    /// `stage_types`, shortened with `short_type_name`
+    fn type_names(&self) -> Vec<String>
+    where
+        Self: Sized,
+    {
+        Self::stage_types()
+            .into_iter()
+            .map(short_type_name)
+            .collect()
+    }
+}
+
+impl<I, O, E, F> Shape<I, O, E> for F
+where
+    F: Fn(I) -> Out<O, E>,
+    E: Debug,
+{
+}
+
+impl<A, B, I, O1, O2, E> Shape<I, O2, E> for Chain<A, B, I, O1, O2, E>
+where
+    A: Shape<I, O1, E>,
+    B: Shape<O1, O2, E>,
+    E: Debug,
+{
+    const STAGES: usize = A::STAGES + B::STAGES;
+
+    fn stage_types() -> Vec<&'static str> {
+        let mut types = A::stage_types();
+        types.extend(B::stage_types().into_iter().skip(1));
+        types
+    }
+}
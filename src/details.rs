@@ -0,0 +1,242 @@
+use super::*;
+use std::any::Any;
+use std::error::Error;
+
+/// what `Failure::Detailed` carries: the failure itself plus details a caller
+/// can act on without parsing the message.
+pub struct FailureDetails {
+    pub source: Failure,
+    /// named values such as the offending record id or field
+    pub fields: Vec<(String, String)>,
+    /// any other value a stage wants to hand to whoever handles the failure
+    pub payload: Option<Box<dyn Any + Send + Sync>>,
+    /// the error this failure was caused by, returned by `Error::source`
+    pub cause: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl Debug for FailureDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("FailureDetails")
+            .field("source", &self.source)
+            .field("fields", &self.fields)
+            .field("payload", &self.payload.as_ref().map(|_| ".."))
+            .field("cause", &self.cause)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for FailureDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.source)?;
+        if !self.fields.is_empty() {
+            let fields: Vec<_> = self
+                .fields
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            write!(f, " [{}]", fields.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Failure {
+    fn into_details(self) -> Box<FailureDetails> {
+        match self {
+            Failure::Detailed(d) => d,
+            source => Box::new(FailureDetails {
+                source,
+                fields: Vec::new(),
+                payload: None,
+                cause: None,
+            }),
+        }
+    }
+
+    /// attach a named detail
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let failure = Failure::InvalidInput("age out of range".to_string())
+    ///     .with_field("record", 1042)
+    ///     .with_field("field", "age");
+    /// assert_eq!(failure.field("record"), Some("1042"));
+    /// assert!(matches!(failure.root(), Failure::InvalidInput(_)));
+    /// ```
+    pub fn with_field(self, key: &str, value: impl std::fmt::Display) -> Failure {
+        let mut details = self.into_details();
+        details.fields.push((key.to_string(), value.to_string()));
+        Failure::Detailed(details)
+    }
+
+    /// attach an arbitrary value, replacing any payload attached before
+    pub fn with_payload<T: Any + Send + Sync>(self, payload: T) -> Failure {
+        let mut details = self.into_details();
+        details.payload = Some(Box::new(payload));
+        Failure::Detailed(details)
+    }
+
+    /// keep the error this failure was caused by, replacing any cause kept
+    /// before
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::error::Error;
+    ///
+    /// let failure = Failure::from("x1".parse::<u8>().unwrap_err()).context("reading the level");
+    /// assert_eq!(
+    ///     failure.to_string(),
+    ///     "Invalid input: invalid digit found in string [context=reading the level]"
+    /// );
+    /// assert!(failure.source().unwrap().is::<std::num::ParseIntError>());
+    ///
+    /// // a snapshot's source is the failure it wraps, with its own cause
+    /// let snapshot = Failure::Snapshot(Box::new(StageSnapshot {
+    ///     stage: "level".into(),
+    ///     input: "\"x1\"".into(),
+    ///     source: failure,
+    /// }));
+    /// let wrapped = snapshot.source().unwrap();
+    /// assert!(wrapped.is::<Failure>());
+    /// assert!(wrapped.source().unwrap().is::<std::num::ParseIntError>());
+    /// ```
+    pub fn with_cause(self, cause: impl Error + Send + Sync + 'static) -> Failure {
+        let mut details = self.into_details();
+        details.cause = Some(Box::new(cause));
+        Failure::Detailed(details)
+    }
+
+    /// describe what was being done when this failed, like `anyhow`'s context.
+    /// kept as a `context` field.
+    pub fn context(self, context: impl std::fmt::Display) -> Failure {
+        self.with_field("context", context)
+    }
+
+    /// record the step this failed in, unless one is recorded already. kept
+    /// as a `step` field. acts given a name with `named` and pipeline stages
+    /// record theirs on their own.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let parse = |s: &str| Ok(s.parse::<i32>()?);
+    /// let out = Reactor::<&str>::input("12a").then(parse.named("parse")).run();
+    /// assert_eq!(out.unwrap_err().step(), Some("parse"));
+    ///
+    /// let p = Pipeline::<i32>::new("p").stage("invert", |x: i32| {
+    ///     100i32.checked_div(x).ok_or(Failure::ArithmeticError("division by zero".into()))
+    /// });
+    /// let failure = p.run(0).unwrap_err();
+    /// assert_eq!(failure.step(), Some("invert"));
+    /// assert_eq!(failure.to_string(), "Arithmetic error: division by zero [step=invert]");
+    /// ```
+    pub fn at_step(self, step: &str) -> Failure {
+        match self.step() {
+            Some(_) => self,
+            None => self.with_field("step", step),
+        }
+    }
+
+    /// the step this failed in, recorded with `at_step` or by a snapshot
+    pub fn step(&self) -> Option<&str> {
+        match self {
+            Failure::Snapshot(s) => Some(&s.stage),
+            f => f.field("step"),
+        }
+    }
+
+    /// the most recently attached detail named `key`, looking through
+    /// snapshots and nested details
+    pub fn field(&self, key: &str) -> Option<&str> {
+        match self {
+            Failure::Detailed(d) => d
+                .fields
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+                .or_else(|| d.source.field(key)),
+            Failure::Snapshot(s) => s.source.field(key),
+            _ => None,
+        }
+    }
+
+    /// the attached payload, if there is one of type `T`
+    pub fn payload<T: Any>(&self) -> Option<&T> {
+        match self {
+            Failure::Detailed(d) => d
+                .payload
+                .as_ref()
+                .and_then(|p| p.downcast_ref())
+                .or_else(|| d.source.payload()),
+            Failure::Snapshot(s) => s.source.payload(),
+            _ => None,
+        }
+    }
+}
+
+/// record `step` in `error` if it's a `Failure`; other error types are
+/// returned as they are
+pub(crate) fn record_step<E: 'static>(error: E, step: &str) -> E {
+    let mut slot = Some(error);
+    if let Some(failure) = (&mut slot as &mut dyn Any).downcast_mut::<Option<Failure>>() {
+        *failure = failure.take().map(|f| f.at_step(step));
+    }
+    slot.expect("only replaced, never taken")
+}
+
+/// `context` for results, turning the error into a `Failure` on the way.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let port: Out<u16> = "http".parse::<u16>().context("parsing the port");
+/// assert_eq!(port.unwrap_err().field("context"), Some("parsing the port"));
+/// ```
+pub trait Context<T> {
+    fn context(self, context: impl std::fmt::Display) -> Out<T>;
+
+    /// like `context`, building the message only on failure
+    fn with_context<C, F>(self, context: F) -> Out<T>
+    where
+        C: std::fmt::Display,
+        F: FnOnce() -> C;
+}
+
+impl<T, E> Context<T> for Result<T, E>
+where
+    E: Into<Failure>,
+{
+    fn context(self, context: impl std::fmt::Display) -> Out<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C, F>(self, context: F) -> Out<T>
+    where
+        C: std::fmt::Display,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|e| e.into().context(context()))
+    }
+}
+
+impl<I> Reactor<I, Failure> {
+    /// run `transform`, recording `step` in its failure
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let out = Reactor::<&str>::input("12a")
+    ///     .then_step("parse", |s: &str| Ok(s.parse::<i32>()?))
+    ///     .then_step("double", |x: i32| Ok(x * 2))
+    ///     .run();
+    /// assert_eq!(out.unwrap_err().step(), Some("parse"));
+    /// ```
+    pub fn then_step<O, T>(self, step: &str, transform: T) -> Reactor<O, Failure>
+    where
+        T: Act<I, O, Failure>,
+    {
+        self.then(|i| transform.act(i).map_err(|e| e.at_step(step)))
+    }
+}
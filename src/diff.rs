@@ -0,0 +1,183 @@
+use super::*;
+
+/// one line of a structural diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    /// only in the left run
+    Left(String),
+    /// only in the right run
+    Right(String),
+}
+
+/// where two runs first stopped agreeing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// the stage whose output differs, or `None` if the inputs already did
+    pub stage: Option<StageInfo>,
+    /// the pretty-printed values, or a note that the run had failed or ended
+    pub left: String,
+    pub right: String,
+    /// a line diff of `left` against `right`
+    pub diff: Vec<DiffLine>,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.stage {
+            Some(stage) => writeln!(
+                f,
+                "runs diverge after stage {} ({})",
+                stage.index, stage.name
+            )?,
+            None => writeln!(f, "runs diverge at the input")?,
+        }
+        for line in &self.diff {
+            match line {
+                DiffLine::Same(l) => writeln!(f, "  {}", l)?,
+                DiffLine::Left(l) => writeln!(f, "- {}", l)?,
+                DiffLine::Right(l) => writeln!(f, "+ {}", l)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the most cells the lcs table of `diff_lines` may have: 4 MiB of `u32`s
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// a line diff via the longest common subsequence; values printed with
+/// `{:#?}` put one field per line, so this reads as a structural diff.
+///
+/// lines shared at the start and end are matched up first. if what's left in
+/// between is too big for the lcs table, it's reported as a whole block
+/// removed and a whole block added, so memory stays bounded.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let diff = diff_lines("a\nb\nc", "a\nx\nc");
+/// assert_eq!(diff, [
+///     DiffLine::Same("a".into()),
+///     DiffLine::Right("x".into()),
+///     DiffLine::Left("b".into()),
+///     DiffLine::Same("c".into()),
+/// ]);
+///
+/// // two unrelated 2000-line values: too big to align, replaced as blocks
+/// let left: String = (0..2000).map(|n| format!("l{}\n", n)).collect();
+/// let right: String = (0..2000).map(|n| format!("r{}\n", n)).collect();
+/// let diff = diff_lines(&format!("start\n{}end", left), &format!("start\n{}end", right));
+/// assert_eq!(diff.len(), 4002);
+/// assert_eq!(diff[1], DiffLine::Left("l0".into()));
+/// assert_eq!(diff[2001], DiffLine::Right("r0".into()));
+/// assert_eq!(diff[4001], DiffLine::Same("end".into()));
+/// ```
+pub fn diff_lines(left: &str, right: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = left.lines().collect();
+    let b: Vec<&str> = right.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut out: Vec<DiffLine> = a[..prefix]
+        .iter()
+        .map(|l| DiffLine::Same(l.to_string()))
+        .collect();
+    if (a_mid.len() + 1).saturating_mul(b_mid.len() + 1) <= MAX_LCS_CELLS {
+        diff_middle(a_mid, b_mid, &mut out);
+    } else {
+        out.extend(a_mid.iter().map(|l| DiffLine::Left(l.to_string())));
+        out.extend(b_mid.iter().map(|l| DiffLine::Right(l.to_string())));
+    }
+    out.extend(
+        a[a.len() - suffix..]
+            .iter()
+            .map(|l| DiffLine::Same(l.to_string())),
+    );
+    out
+}
+
+fn diff_middle(a: &[&str], b: &[&str], out: &mut Vec<DiffLine>) {
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j < b.len()
+            && (i == a.len() || lcs[i * width + j + 1] >= lcs[(i + 1) * width + j])
+        {
+            out.push(DiffLine::Right(b[j].to_string()));
+            j += 1;
+        } else {
+            out.push(DiffLine::Left(a[i].to_string()));
+            i += 1;
+        }
+    }
+}
+
+fn rendered<O, E: Debug>(run: &Recording<O, E>, index: usize) -> String {
+    match (run.value_at(index), run.error()) {
+        (Some(v), _) => format!("{:#?}", v),
+        (None, Some(e)) => format!("<failed: {:?}>", e),
+        (None, None) => "<no such stage>".to_string(),
+    }
+}
+
+/// compare two recorded runs, e.g. two versions of a pipeline or one pipeline
+/// on two inputs, and report the first value they disagree on. values are
+/// compared by their `Debug` output.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let old = Recording::<i32>::input(3)
+///     .stage("double", |x: i32| Ok(x * 2))
+///     .stage("label", |x: i32| Ok(vec![x, x + 1]));
+/// let new = Recording::<i32>::input(3)
+///     .stage("double", |x: i32| Ok(x + x))
+///     .stage("label", |x: i32| Ok(vec![x, x - 1]));
+///
+/// let d = diff_runs(&old, &new).unwrap();
+/// assert_eq!(d.stage.unwrap().name, "label");
+/// assert!(d.diff.contains(&DiffLine::Right("    5,".to_string())));
+/// ```
+pub fn diff_runs<O1, O2, E1, E2>(
+    left: &Recording<O1, E1>,
+    right: &Recording<O2, E2>,
+) -> Option<Divergence>
+where
+    E1: Debug,
+    E2: Debug,
+{
+    let len = left.stages().count().max(right.stages().count()) + 1;
+    (0..len).find_map(|i| {
+        let (l, r) = (rendered(left, i), rendered(right, i));
+        (l != r).then(|| Divergence {
+            stage: i
+                .checked_sub(1)
+                .and_then(|s| left.stages().nth(s).or_else(|| right.stages().nth(s)))
+                .cloned(),
+            diff: diff_lines(&l, &r),
+            left: l,
+            right: r,
+        })
+    })
+}
@@ -0,0 +1,527 @@
+use super::*;
+use crate::wire::{escape, unescape};
+use std::any::Any;
+use std::collections::{HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+
+/// items sent to a worker before its first result comes back
+const IN_FLIGHT_PER_WORKER: usize = 4;
+
+// the protocol is one line per message: `id\tpayload` to the worker and
+// `id\tok\tpayload`, `id\tfailure\tencoded failure` or `id\terr\tmessage`
+// back. payloads are escaped so they can't contain tabs or newlines.
+
+/// answer work requests from a `WorkerPool` until `reader` ends: decode each
+/// item with `decode`, run `act` on it and send back the output rendered by
+/// `encode`, or the error. a `Failure` is sent in the `Wire` format and comes
+/// out of `WorkerPool::map` whole, with `item` and `worker` fields added;
+/// other errors arrive as their `Debug` text. run this from the same binary
+/// as the coordinator, e.g. when it's started with a `worker` argument.
+pub fn serve_worker<R, W, A, I, O, E, D, F>(
+    reader: R,
+    mut writer: W,
+    act: A,
+    decode: D,
+    encode: F,
+) -> std::io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+    A: Act<I, O, E>,
+    E: Debug + 'static,
+    D: Fn(&str) -> Out<I, E>,
+    F: Fn(&O) -> String,
+{
+    for line in reader.lines() {
+        let line = line?;
+        let Some((id, payload)) = line.split_once('\t') else {
+            continue;
+        };
+        let reply = match decode(&unescape(payload)).and_then(|i| act.act(i)) {
+            Ok(o) => format!("{}\tok\t{}", id, escape(&encode(&o))),
+            Err(e) => match (&e as &dyn Any).downcast_ref::<Failure>() {
+                Some(failure) => format!("{}\tfailure\t{}", id, escape(&failure.to_wire())),
+                None => format!("{}\terr\t{}", id, escape(&format!("{:?}", e))),
+            },
+        };
+        writeln!(writer, "{}", reply)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// `serve_worker` over the process's stdin and stdout, for workers started by
+/// `WorkerPool::spawn`
+pub fn serve_stdio<A, I, O, E, D, F>(act: A, decode: D, encode: F) -> std::io::Result<()>
+where
+    A: Act<I, O, E>,
+    E: Debug + 'static,
+    D: Fn(&str) -> Out<I, E>,
+    F: Fn(&O) -> String,
+{
+    serve_worker(
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+        act,
+        decode,
+        encode,
+    )
+}
+
+/// what a worker's reader thread reports back
+enum Reply {
+    Done(usize, Result<String, WorkerError>),
+    /// the worker's output ended or broke
+    Gone,
+}
+
+/// how a worker reported a failed item
+enum WorkerError {
+    /// a `Failure`, sent whole
+    Failure(Failure),
+    /// any other error, as its `Debug` rendering
+    Message(String),
+}
+
+struct Worker {
+    writer: Option<Box<dyn Write + Send>>,
+    /// the process, for workers started by `WorkerPool::spawn`
+    child: Option<Child>,
+    /// ids sent to this worker and not answered yet, with when they were sent
+    in_flight: Vec<(usize, Instant)>,
+}
+
+/// a set of worker processes or remote workers that `for_each_distributed`
+/// shards items across. workers are the same pipeline binary running
+/// `serve_worker`; items and outputs travel as text, encoded and decoded by
+/// closures, so any serialization format works.
+///
+/// workers that die have their items reassigned to the rest. once every item
+/// has been handed out, idle workers also take duplicates of items that have
+/// been outstanding longer than the straggler timeout, and whichever result
+/// comes back first is used.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::io::BufReader;
+/// use std::net::TcpListener;
+///
+/// // two workers on tcp that fail on 13
+/// let mut addrs = Vec::new();
+/// for _ in 0..2 {
+///     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+///     addrs.push(listener.local_addr().unwrap());
+///     std::thread::spawn(move || {
+///         let (stream, _) = listener.accept().unwrap();
+///         let square = |x: u64| match x {
+///             13 => Err(Failure::InvalidInput("unlucky".into())),
+///             _ => Ok(x * x),
+///         };
+///         let parse = |s: &str| s.parse::<u64>().map_err(|e| Failure::InvalidInput(e.to_string()));
+///         serve_worker(BufReader::new(stream.try_clone().unwrap()), stream, square, parse, |o: &u64| o.to_string())
+///     });
+/// }
+/// let mut pool = WorkerPool::connect(&addrs).unwrap();
+/// let decode = |s: &str| s.parse::<u64>().map_err(|e| Failure::InvalidInput(e.to_string()));
+///
+/// let squares = Reactor::<Vec<u64>>::input((1..=12).collect())
+///     .for_each_distributed(&mut pool, |x| x.to_string(), decode)
+///     .run();
+/// assert_eq!(squares.unwrap(), (1..=12).map(|x| x * x).collect::<Vec<_>>());
+///
+/// // a failure comes back whole, with the item and worker added
+/// let failure = pool.map((0..20).collect(), |x: &u64| x.to_string(), decode).unwrap_err();
+/// assert!(matches!(failure.root(), Failure::InvalidInput(m) if m == "unlucky"));
+/// assert_eq!(failure.field("item"), Some("13"));
+/// assert!(matches!(failure.field("worker"), Some("0" | "1")));
+/// ```
+pub struct WorkerPool {
+    workers: Vec<Worker>,
+    replies: Receiver<(usize, Reply)>,
+    sender: Sender<(usize, Reply)>,
+    straggler_after: Option<Duration>,
+    item_timeout: Option<Duration>,
+    shutdown_grace: Duration,
+    next_id: usize,
+}
+
+/// wait until `deadline` for `child` to exit, then kill it
+fn stop_child(child: &mut Child, deadline: Instant) {
+    while matches!(child.try_wait(), Ok(None)) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    if let Ok(None) = child.try_wait() {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+}
+
+impl WorkerPool {
+    pub fn new() -> Self {
+        let (sender, replies) = mpsc::channel();
+        WorkerPool {
+            workers: Vec::new(),
+            replies,
+            sender,
+            straggler_after: None,
+            item_timeout: None,
+            shutdown_grace: Duration::from_secs(5),
+            next_id: 0,
+        }
+    }
+
+    /// start `count` copies of `command` and talk to them over stdin/stdout
+    pub fn spawn(command: &mut Command, count: usize) -> std::io::Result<Self> {
+        let mut pool = WorkerPool::new();
+        for _ in 0..count {
+            let mut child = command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()?;
+            let stdin = child.stdin.take().expect("worker stdin is piped");
+            let stdout = child.stdout.take().expect("worker stdout is piped");
+            pool.add_worker(stdin, BufReader::new(stdout));
+            pool.workers.last_mut().expect("just added").child = Some(child);
+        }
+        Ok(pool)
+    }
+
+    /// connect to workers listening on tcp, one per address
+    pub fn connect<A: ToSocketAddrs>(addrs: &[A]) -> std::io::Result<Self> {
+        let mut pool = WorkerPool::new();
+        for addr in addrs {
+            let stream = TcpStream::connect(addr)?;
+            let reader = BufReader::new(stream.try_clone()?);
+            pool.add_worker(stream, reader);
+        }
+        Ok(pool)
+    }
+
+    /// add a worker reachable through any pair of streams
+    pub fn add_worker<W, R>(&mut self, writer: W, reader: R) -> &mut Self
+    where
+        W: Write + Send + 'static,
+        R: BufRead + Send + 'static,
+    {
+        let index = self.workers.len();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                let mut fields = line.splitn(3, '\t');
+                let (Some(id), Some(status), Some(payload)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                let Ok(id) = id.parse() else { continue };
+                let payload = unescape(payload);
+                let result = match status {
+                    "ok" => Ok(payload),
+                    "failure" => Err(Failure::from_wire(&payload)
+                        .map_or(WorkerError::Message(payload), WorkerError::Failure)),
+                    _ => Err(WorkerError::Message(payload)),
+                };
+                if sender.send((index, Reply::Done(id, result))).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send((index, Reply::Gone));
+        });
+        self.workers.push(Worker {
+            writer: Some(Box::new(writer)),
+            child: None,
+            in_flight: Vec::new(),
+        });
+        self
+    }
+
+    /// hand duplicates of items outstanding this long to idle workers
+    pub fn straggler_after(mut self, after: Duration) -> Self {
+        self.straggler_after = Some(after);
+        self
+    }
+
+    /// give up on a worker that hasn't answered an item for this long: it's
+    /// dropped from the pool like one that died, stopped the way dropping the
+    /// pool stops it, and its items go to the other workers. without this a
+    /// worker that hangs without exiting holds up `map` for good.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::io::{BufRead, BufReader, Write};
+    /// use std::net::TcpListener;
+    /// use std::process::Command;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// // the first worker takes items and never answers; the second works
+    /// let mut addrs = Vec::new();
+    /// for hangs in [true, false] {
+    ///     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    ///     addrs.push(listener.local_addr().unwrap());
+    ///     std::thread::spawn(move || {
+    ///         let (stream, _) = listener.accept().unwrap();
+    ///         if hangs {
+    ///             for _ in BufReader::new(stream).lines() {}
+    ///             return Ok(());
+    ///         }
+    ///         let parse = |s: &str| s.parse::<u64>().map_err(|e| Failure::InvalidInput(e.to_string()));
+    ///         serve_worker(BufReader::new(stream.try_clone().unwrap()), stream, |x: u64| Ok(x + 1), parse, |o: &u64| o.to_string())
+    ///     });
+    /// }
+    /// let mut pool = WorkerPool::connect(&addrs).unwrap().item_timeout(Duration::from_millis(200));
+    /// let decode = |s: &str| s.parse::<u64>().map_err(|e| Failure::InvalidInput(e.to_string()));
+    /// let out = pool.map((0..10).collect(), |x: &u64| x.to_string(), decode);
+    /// assert_eq!(out.unwrap(), (1..=10).collect::<Vec<u64>>());
+    /// assert_eq!(pool.live_workers(), 1);
+    ///
+    /// // a lone worker that hangs fails the call instead of blocking it
+    /// # if cfg!(unix) {
+    /// let mut pool = WorkerPool::spawn(Command::new("sleep").arg("30"), 1)
+    ///     .unwrap()
+    ///     .item_timeout(Duration::from_millis(100))
+    ///     .shutdown_grace(Duration::from_millis(50));
+    /// let start = Instant::now();
+    /// assert!(pool.map(vec![1u64], |x| x.to_string(), decode).is_err());
+    /// assert!(start.elapsed() < Duration::from_secs(5));
+    /// # }
+    /// ```
+    pub fn item_timeout(mut self, timeout: Duration) -> Self {
+        self.item_timeout = Some(timeout);
+        self
+    }
+
+    /// how long dropping the pool waits for spawned workers to exit after
+    /// their input is closed, before killing them (default 5s)
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::process::Command;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # if cfg!(unix) {
+    /// // a worker that ignores its input and never exits on its own
+    /// let pool = WorkerPool::spawn(Command::new("sleep").arg("30"), 2)
+    ///     .unwrap()
+    ///     .shutdown_grace(Duration::from_millis(50));
+    /// let start = Instant::now();
+    /// drop(pool);
+    /// assert!(start.elapsed() < Duration::from_secs(5));
+    /// # }
+    /// ```
+    pub fn shutdown_grace(mut self, grace: Duration) -> Self {
+        self.shutdown_grace = grace;
+        self
+    }
+
+    /// stop using worker `w`: close its input, stop its process if it has one,
+    /// and return its unanswered items
+    fn retire(&mut self, w: usize) -> Vec<usize> {
+        let worker = &mut self.workers[w];
+        worker.writer = None;
+        if let Some(mut child) = worker.child.take() {
+            stop_child(&mut child, Instant::now() + self.shutdown_grace);
+        }
+        worker.in_flight.drain(..).map(|(id, _)| id).collect()
+    }
+
+    /// the number of workers still reachable
+    pub fn live_workers(&self) -> usize {
+        self.workers.iter().filter(|w| w.writer.is_some()).count()
+    }
+
+    fn send(&mut self, worker: usize, id: usize, payload: &str) -> bool {
+        let w = &mut self.workers[worker];
+        let Some(writer) = w.writer.as_mut() else {
+            return false;
+        };
+        let sent = writeln!(writer, "{}\t{}", id, escape(payload)).and_then(|_| writer.flush());
+        if sent.is_err() {
+            w.writer = None;
+            return false;
+        }
+        w.in_flight.push((id, Instant::now()));
+        true
+    }
+
+    /// run every item on the workers, returning the outputs in input order.
+    /// the first item a worker reports as failed fails the whole call.
+    pub fn map<I, O, E, F, D>(&mut self, items: Vec<I>, encode: F, decode: D) -> Out<Vec<O>, E>
+    where
+        E: Debug + From<Failure>,
+        F: Fn(&I) -> String,
+        D: Fn(&str) -> Out<O, E>,
+    {
+        let base = self.next_id;
+        self.next_id += items.len();
+        let payloads: Vec<String> = items.iter().map(encode).collect();
+        let mut results: Vec<Option<O>> = (0..items.len()).map(|_| None).collect();
+        let mut remaining = items.len();
+        let mut pending: VecDeque<usize> = (0..items.len()).collect();
+        let mut duplicated = HashSet::new();
+        // results of an earlier call still on their way are ignored by id
+        let current = |id: usize| id >= base && id < base + payloads.len();
+
+        while remaining > 0 {
+            for w in 0..self.workers.len() {
+                while self.workers[w].writer.is_some()
+                    && self.workers[w].in_flight.len() < IN_FLIGHT_PER_WORKER
+                {
+                    let Some(i) = pending.pop_front() else { break };
+                    if results[i].is_some() {
+                        continue;
+                    }
+                    if !self.send(w, base + i, &payloads[i]) {
+                        pending.push_front(i);
+                    }
+                }
+            }
+            if let Some(after) = self.straggler_after.filter(|_| pending.is_empty()) {
+                let oldest = self
+                    .workers
+                    .iter()
+                    .flat_map(|w| w.in_flight.iter())
+                    .filter(|(id, at)| {
+                        current(*id)
+                            && results[id - base].is_none()
+                            && !duplicated.contains(id)
+                            && at.elapsed() >= after
+                    })
+                    .min_by_key(|(_, at)| *at)
+                    .map(|(id, _)| *id);
+                let idle = (0..self.workers.len()).find(|w| {
+                    self.workers[*w].writer.is_some() && self.workers[*w].in_flight.is_empty()
+                });
+                if let (Some(id), Some(w)) = (oldest, idle) {
+                    if self.send(w, id, &payloads[id - base]) {
+                        duplicated.insert(id);
+                    }
+                }
+            }
+            if let Some(timeout) = self.item_timeout {
+                let hung: Vec<usize> = (0..self.workers.len())
+                    .filter(|&w| {
+                        self.workers[w].writer.is_some()
+                            && self.workers[w].in_flight.iter().any(|(id, at)| {
+                                current(*id)
+                                    && results[id - base].is_none()
+                                    && at.elapsed() >= timeout
+                            })
+                    })
+                    .collect();
+                for w in hung {
+                    for id in self.retire(w) {
+                        if current(id) && results[id - base].is_none() {
+                            pending.push_back(id - base);
+                        }
+                    }
+                }
+            }
+            if self.live_workers() == 0 {
+                return Err(Failure::Custom(format!(
+                    "all workers are gone with {} item(s) left",
+                    remaining
+                ))
+                .into());
+            }
+
+            let wait = [self.straggler_after, self.item_timeout]
+                .into_iter()
+                .flatten()
+                .min()
+                .unwrap_or(Duration::from_millis(100));
+            let (w, reply) = match self.replies.recv_timeout(wait) {
+                Ok(reply) => reply,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => unreachable!("the pool holds a sender"),
+            };
+            match reply {
+                Reply::Done(id, result) => {
+                    self.workers[w].in_flight.retain(|(i, _)| *i != id);
+                    if !current(id) || results[id - base].is_some() {
+                        continue;
+                    }
+                    match result {
+                        Ok(payload) => {
+                            results[id - base] = Some(decode(&payload)?);
+                            remaining -= 1;
+                        }
+                        Err(WorkerError::Failure(failure)) => {
+                            return Err(failure
+                                .with_field("item", id - base)
+                                .with_field("worker", w)
+                                .into())
+                        }
+                        Err(WorkerError::Message(message)) => {
+                            return Err(Failure::Custom(format!(
+                                "item {} failed on worker {}: {}",
+                                id - base,
+                                w,
+                                message
+                            ))
+                            .into())
+                        }
+                    }
+                }
+                Reply::Gone => {
+                    for id in self.retire(w) {
+                        if current(id) && results[id - base].is_none() {
+                            pending.push_back(id - base);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(results.into_iter().map(|o| o.unwrap()).collect())
+    }
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // closing their input lets spawned workers finish and exit
+        for w in &mut self.workers {
+            w.writer = None;
+        }
+        let deadline = Instant::now() + self.shutdown_grace;
+        for w in &mut self.workers {
+            if let Some(child) = &mut w.child {
+                stop_child(child, deadline);
+            }
+        }
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// like `for_each`, but the items run on the pool's workers. `encode`
+    /// renders an item for the wire and `decode` parses a worker's output.
+    pub fn for_each_distributed<O, F, D>(
+        self,
+        pool: &mut WorkerPool,
+        encode: F,
+        decode: D,
+    ) -> Reactor<Vec<O>, E>
+    where
+        I: IntoIterator,
+        F: Fn(&I::Item) -> String,
+        D: Fn(&str) -> Out<O, E>,
+    {
+        Reactor {
+            input: self
+                .input
+                .and_then(|i| pool.map(i.into_iter().collect(), encode, decode)),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use super::*;
+
+impl<L, R> Either<L, R> {
+    pub fn is_left(&self) -> bool {
+        matches!(self, Either::Left(_))
+    }
+
+    pub fn is_right(&self) -> bool {
+        matches!(self, Either::Right(_))
+    }
+
+    pub fn left(self) -> Option<L> {
+        match self {
+            Either::Left(l) => Some(l),
+            Either::Right(_) => None,
+        }
+    }
+
+    pub fn right(self) -> Option<R> {
+        match self {
+            Either::Left(_) => None,
+            Either::Right(r) => Some(r),
+        }
+    }
+
+    pub fn as_ref(&self) -> Either<&L, &R> {
+        match self {
+            Either::Left(l) => Either::Left(l),
+            Either::Right(r) => Either::Right(r),
+        }
+    }
+
+    pub fn map_left<T, F: FnOnce(L) -> T>(self, f: F) -> Either<T, R> {
+        match self {
+            Either::Left(l) => Either::Left(f(l)),
+            Either::Right(r) => Either::Right(r),
+        }
+    }
+
+    pub fn map_right<T, F: FnOnce(R) -> T>(self, f: F) -> Either<L, T> {
+        match self {
+            Either::Left(l) => Either::Left(l),
+            Either::Right(r) => Either::Right(f(r)),
+        }
+    }
+
+    /// collapse both sides into one value
+    pub fn either<T, F, G>(self, left: F, right: G) -> T
+    where
+        F: FnOnce(L) -> T,
+        G: FnOnce(R) -> T,
+    {
+        match self {
+            Either::Left(l) => left(l),
+            Either::Right(r) => right(r),
+        }
+    }
+
+    pub fn flip(self) -> Either<R, L> {
+        match self {
+            Either::Left(l) => Either::Right(l),
+            Either::Right(r) => Either::Left(r),
+        }
+    }
+}
+
+impl<T> Either<T, T> {
+    /// the value, whichever side it's on
+    pub fn into_inner(self) -> T {
+        match self {
+            Either::Left(t) | Either::Right(t) => t,
+        }
+    }
+}
+
+/// one of two acts with the same signature, picked when the chain is built
+/// (e.g. from configuration) rather than per input
+impl<A, B, I, O, E> Act<I, O, E> for Either<A, B>
+where
+    A: Act<I, O, E>,
+    B: Act<I, O, E>,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        match self {
+            Either::Left(a) => a.act(input),
+            Either::Right(b) => b.act(input),
+        }
+    }
+}
+
+/// with the `serde` feature, as serde derives enums: `{"Left": value}` or
+/// `{"Right": value}`
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let parsed: Vec<Either<u32, String>> = vec![Either::Left(7), Either::Right("x7".into())];
+/// let json = serde_json::to_string(&parsed).unwrap();
+/// assert_eq!(json, r#"[{"Left":7},{"Right":"x7"}]"#);
+/// assert_eq!(serde_json::from_str::<Vec<Either<u32, String>>>(&json).unwrap(), parsed);
+/// assert!(serde_json::from_str::<Either<u32, String>>(r#"{"Middle":1}"#).is_err());
+/// ```
+#[cfg(feature = "serde")]
+impl<L, R> serde::Serialize for Either<L, R>
+where
+    L: serde::Serialize,
+    R: serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Either::Left(l) => serializer.serialize_newtype_variant("Either", 0, "Left", l),
+            Either::Right(r) => serializer.serialize_newtype_variant("Either", 1, "Right", r),
+        }
+    }
+}
+
+/// `Either` as serde's derive sees it, to deserialize through
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Either")]
+enum EitherRepr<L, R> {
+    Left(L),
+    Right(R),
+}
+
+#[cfg(feature = "serde")]
+impl<'de, L, R> serde::Deserialize<'de> for Either<L, R>
+where
+    L: serde::Deserialize<'de>,
+    R: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match EitherRepr::deserialize(deserializer)? {
+            EitherRepr::Left(l) => Either::Left(l),
+            EitherRepr::Right(r) => Either::Right(r),
+        })
+    }
+}
+
+impl<L, R, E> Reactor<Either<L, R>, E>
+where
+    E: Debug,
+{
+    /// continue an `if_else` with one act per branch, bringing both back to
+    /// the same output type
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let out = Reactor::<i32>::input(-4)
+    ///     .if_else(|x: &i32| *x >= 0, |x: i32| Ok(x as u32), |x: i32| Ok(x.to_string()))
+    ///     .either(|n: u32| Ok(n as usize), |s: String| Ok(s.len()))
+    ///     .run();
+    /// assert_eq!(out.unwrap(), 2);
+    /// ```
+    pub fn either<O, A, B>(self, left: A, right: B) -> Reactor<O, E>
+    where
+        A: Act<L, O, E>,
+        B: Act<R, O, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|e| match e {
+                Either::Left(l) => left.act(l),
+                Either::Right(r) => right.act(r),
+            }),
+        }
+    }
+
+    /// transform only left values, passing right ones through
+    pub fn then_left<O, A>(self, act: A) -> Reactor<Either<O, R>, E>
+    where
+        A: Act<L, O, E>,
+    {
+        self.either(|l| act.act(l).map(Either::Left), |r| Ok(Either::Right(r)))
+    }
+
+    /// transform only right values, passing left ones through
+    pub fn then_right<O, A>(self, act: A) -> Reactor<Either<L, O>, E>
+    where
+        A: Act<R, O, E>,
+    {
+        self.either(|l| Ok(Either::Left(l)), |r| act.act(r).map(Either::Right))
+    }
+}
+
+impl<T, E> Reactor<Either<T, T>, E>
+where
+    E: Debug,
+{
+    /// drop the `Either` once both branches produce the same type
+    pub fn unify(self) -> Reactor<T, E> {
+        self.map(Either::into_inner)
+    }
+}
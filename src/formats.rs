@@ -0,0 +1,186 @@
+use super::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{BufRead, Write};
+use std::marker::PhantomData;
+
+fn decode_failure(format: &str, e: impl std::error::Error + Send + Sync + 'static) -> Failure {
+    Failure::InvalidInput(format!("not valid {}: {}", format, e)).with_cause(e)
+}
+
+fn encode_failure(format: &str, e: impl std::error::Error + Send + Sync + 'static) -> Failure {
+    Failure::Custom(format!("failed to write {}: {}", format, e)).with_cause(e)
+}
+
+/// the act returned by `from_json`
+pub struct FromJson<T>(PhantomData<fn() -> T>);
+
+/// the act returned by `from_toml`
+pub struct FromToml<T>(PhantomData<fn() -> T>);
+
+/// the act returned by `to_json`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ToJson;
+
+/// the act returned by `to_toml`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ToToml;
+
+/// parse text as json into a `T`. text that isn't valid json for `T` fails
+/// with `InvalidInput`, keeping the `serde_json` error as its cause.
+///
+/// it takes any `AsRef<str>`, so it fits `then` on a `String` as well as the
+/// `&str` parsers of `from_lines` and `from_json_body_with`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct Order {
+///     item: String,
+///     qty: u32,
+/// }
+///
+/// let out = Reactor::<String>::input(r#"{"item":"pen","qty":2}"#.to_string())
+///     .then(from_json::<Order>())
+///     .then(|o: Order| Ok(Order { qty: o.qty * 10, ..o }))
+///     .then(to_json())
+///     .run();
+/// assert_eq!(out.unwrap(), r#"{"item":"pen","qty":20}"#);
+///
+/// let failure = from_json::<Order>().act(r#"{"item":"pen"}"#).unwrap_err();
+/// assert!(matches!(failure.root(), Failure::InvalidInput(m) if m.contains("missing field `qty`")));
+///
+/// // a json lines file, one record per line
+/// let parsed = Reactor::<Partial<Order>>::from_lines(
+///     "{\"item\":\"pen\",\"qty\":1}\nnot json\n".as_bytes(),
+///     |line: &str| from_json().act(line),
+/// )
+/// .run()
+/// .unwrap();
+/// assert_eq!(parsed.items.len(), 1);
+/// assert_eq!(parsed.failures[0].0, 2);
+/// ```
+pub fn from_json<T: DeserializeOwned>() -> FromJson<T> {
+    FromJson(PhantomData)
+}
+
+/// render a value as compact json. values json can't hold, like maps with
+/// non-string keys, fail with `Custom`.
+pub fn to_json() -> ToJson {
+    ToJson
+}
+
+/// parse text as a toml document into a `T`, failing with `InvalidInput`
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Settings {
+///     workers: u32,
+///     name: String,
+/// }
+///
+/// let out = Reactor::<&str>::input("workers = 4\nname = \"etl\"\n")
+///     .then(from_toml::<Settings>())
+///     .then(|s: Settings| Ok(Settings { workers: s.workers * 2, ..s }))
+///     .then(to_toml())
+///     .run();
+/// assert_eq!(out.unwrap(), "workers = 8\nname = \"etl\"\n");
+///
+/// assert!(from_toml::<Settings>().act("workers = \"four\"").is_err());
+/// ```
+pub fn from_toml<T: DeserializeOwned>() -> FromToml<T> {
+    FromToml(PhantomData)
+}
+
+/// render a value as a toml document. values that aren't a table at the top,
+/// or that toml can't hold, fail with `Custom`.
+pub fn to_toml() -> ToToml {
+    ToToml
+}
+
+impl<S, T> Act<S, T> for FromJson<T>
+where
+    S: AsRef<str>,
+    T: DeserializeOwned,
+{
+    fn act(&self, input: S) -> Out<T> {
+        serde_json::from_str(input.as_ref()).map_err(|e| decode_failure("json", e))
+    }
+}
+
+impl<S, T> Act<S, T> for FromToml<T>
+where
+    S: AsRef<str>,
+    T: DeserializeOwned,
+{
+    fn act(&self, input: S) -> Out<T> {
+        toml::from_str(input.as_ref()).map_err(|e| decode_failure("toml", e))
+    }
+}
+
+impl<T: Serialize> Act<T, String> for ToJson {
+    fn act(&self, value: T) -> Out<String> {
+        serde_json::to_string(&value).map_err(|e| encode_failure("json", e))
+    }
+}
+
+impl<T: Serialize> Act<T, String> for ToToml {
+    fn act(&self, value: T) -> Out<String> {
+        toml::to_string(&value).map_err(|e| encode_failure("toml", e))
+    }
+}
+
+impl<T, E> Reactor<Partial<T, E>, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// read JSON Lines from `reader`, one `T` per non-empty line. like
+    /// `from_lines`, lines that aren't valid json for `T` become failures
+    /// tagged with their line number instead of stopping the rest.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct Order {
+    ///     item: String,
+    ///     qty: u32,
+    /// }
+    ///
+    /// let input = "{\"item\":\"pen\",\"qty\":1}\n{\"item\":\"cap\"}\n\n{\"item\":\"ink\",\"qty\":3}\n";
+    /// let mut out = Vec::new();
+    /// let failures = Reactor::<Partial<Order>>::from_jsonl(input.as_bytes())
+    ///     .then_each(|o: Order| Ok(Order { qty: o.qty * 10, ..o }))
+    ///     .to_jsonl(&mut out)
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "{\"item\":\"pen\",\"qty\":10}\n{\"item\":\"ink\",\"qty\":30}\n"
+    /// );
+    /// assert_eq!(failures.len(), 1);
+    /// assert_eq!(failures[0].0, 2);
+    /// assert!(matches!(failures[0].1.root(), Failure::InvalidInput(m) if m.contains("missing field `qty`")));
+    /// ```
+    pub fn from_jsonl<R: BufRead>(reader: R) -> Self
+    where
+        T: DeserializeOwned,
+    {
+        Self::from_lines(reader, |line| Ok(from_json().act(line)?))
+    }
+
+    /// write every surviving record to `writer` as a line of compact json,
+    /// returning the failures as `to_lines` does
+    pub fn to_jsonl<W: Write>(self, writer: W) -> Reactor<Vec<(usize, E)>, E>
+    where
+        T: Serialize,
+    {
+        self.to_lines(writer, |record| Ok(to_json().act(record)?))
+    }
+}
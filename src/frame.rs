@@ -0,0 +1,229 @@
+use super::*;
+use polars::prelude::{AnyValue, Column, DataFrame, PolarsError, PolarsResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+
+pub(crate) fn polars_failure(e: PolarsError) -> Failure {
+    Failure::Custom(format!("polars: {}", e)).with_cause(e)
+}
+
+/// the act returned by `to_frame`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ToFrame;
+
+/// the act returned by `from_frame`
+pub struct FromFrame<T>(PhantomData<fn() -> T>);
+
+/// the act returned by `frame_op`
+pub struct FrameOp<F> {
+    name: String,
+    op: F,
+}
+
+/// turn records into a polars `DataFrame`, one row per record and one column
+/// per field, in name order. fields hold booleans, integers, floats or
+/// strings (`Option`s of them for nulls); a record that isn't a struct or a
+/// map, or a field mixing kinds or holding a list or nested struct, fails
+/// with `InvalidInput`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use polars::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Sale {
+///     item: String,
+///     qty: i64,
+///     price: f64,
+/// }
+///
+/// let sales = vec![
+///     Sale { item: "pen".into(), qty: 3, price: 1.5 },
+///     Sale { item: "ink".into(), qty: 1, price: 7.0 },
+///     Sale { item: "cap".into(), qty: 6, price: 0.5 },
+/// ];
+///
+/// let frame = to_frame().act(&sales).unwrap();
+/// assert_eq!(frame.shape(), (3, 3));
+/// assert_eq!(frame.column("qty").unwrap().dtype(), &DataType::Int64);
+///
+/// // a polars step in the middle of a chain
+/// let big = Reactor::<Vec<Sale>>::input(sales)
+///     .then(to_frame())
+///     .then(frame_op("at least 3", |df: DataFrame| {
+///         let mask = df.column("qty")?.as_materialized_series().gt_eq(3)?;
+///         df.filter(&mask)
+///     }))
+///     .then(from_frame::<Sale>())
+///     .run()
+///     .unwrap();
+/// assert_eq!(big.iter().map(|s| s.item.as_str()).collect::<Vec<_>>(), ["pen", "cap"]);
+///
+/// let failure = Reactor::<DataFrame>::input(frame)
+///     .then(frame_op("pick", |df: DataFrame| df.select(["nope"])))
+///     .run()
+///     .unwrap_err();
+/// assert_eq!(failure.step(), Some("pick"));
+/// ```
+pub fn to_frame() -> ToFrame {
+    ToFrame
+}
+
+/// read every row of a `DataFrame` back into a `T`, matching columns to
+/// fields by name. a row that doesn't fit `T` fails with `InvalidInput`
+/// naming the row.
+pub fn from_frame<T: DeserializeOwned>() -> FromFrame<T> {
+    FromFrame(PhantomData)
+}
+
+/// a named operation on a `DataFrame`, for polars code in a chain. the name
+/// shows in reports and pipeline stages, and a polars error fails with
+/// `Custom`, recording the name as its step.
+pub fn frame_op<F>(name: &str, op: F) -> FrameOp<F>
+where
+    F: Fn(DataFrame) -> PolarsResult<DataFrame>,
+{
+    FrameOp {
+        name: name.to_string(),
+        op,
+    }
+}
+
+/// the kind of values a column holds, widened as records are seen
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Null,
+    Bool,
+    Int,
+    Float,
+    Text,
+}
+
+fn kind_of(value: &Value) -> Option<Kind> {
+    match value {
+        Value::Null => Some(Kind::Null),
+        Value::Bool(_) => Some(Kind::Bool),
+        Value::Number(n) if n.is_i64() => Some(Kind::Int),
+        Value::Number(_) => Some(Kind::Float),
+        Value::String(_) => Some(Kind::Text),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+fn widen(seen: Kind, next: Kind) -> Option<Kind> {
+    match (seen, next) {
+        (a, b) if a == b => Some(a),
+        (Kind::Null, k) | (k, Kind::Null) => Some(k),
+        (Kind::Int, Kind::Float) | (Kind::Float, Kind::Int) => Some(Kind::Float),
+        _ => None,
+    }
+}
+
+fn column(name: &str, records: &[Map<String, Value>]) -> Out<Column> {
+    let values: Vec<&Value> = records
+        .iter()
+        .map(|r| r.get(name).unwrap_or(&Value::Null))
+        .collect();
+    let mut kind = Kind::Null;
+    for (row, value) in values.iter().enumerate() {
+        kind = kind_of(value).and_then(|k| widen(kind, k)).ok_or_else(|| {
+            Failure::InvalidInput(format!(
+                "field '{}' of record {} doesn't fit a column of the others",
+                name, row
+            ))
+        })?;
+    }
+    let name = name.into();
+    Ok(match kind {
+        Kind::Bool => Column::new(name, values.iter().map(|v| v.as_bool()).collect::<Vec<_>>()),
+        Kind::Int => Column::new(name, values.iter().map(|v| v.as_i64()).collect::<Vec<_>>()),
+        Kind::Float => Column::new(name, values.iter().map(|v| v.as_f64()).collect::<Vec<_>>()),
+        Kind::Text | Kind::Null => {
+            Column::new(name, values.iter().map(|v| v.as_str()).collect::<Vec<_>>())
+        }
+    })
+}
+
+impl<T: Serialize> Act<&[T], DataFrame> for ToFrame {
+    fn act(&self, records: &[T]) -> Out<DataFrame> {
+        let records = records
+            .iter()
+            .enumerate()
+            .map(|(row, record)| match serde_json::to_value(record) {
+                Ok(Value::Object(fields)) => Ok(fields),
+                Ok(_) => Err(Failure::InvalidInput(format!(
+                    "record {} isn't a struct or a map",
+                    row
+                ))),
+                Err(e) => {
+                    Err(Failure::InvalidInput(format!("record {}: {}", row, e)).with_cause(e))
+                }
+            })
+            .collect::<Out<Vec<_>>>()?;
+        let names: BTreeSet<&String> = records.iter().flat_map(|r| r.keys()).collect();
+        let columns = names
+            .into_iter()
+            .map(|name| column(name, &records))
+            .collect::<Out<Vec<_>>>()?;
+        DataFrame::new(records.len(), columns).map_err(polars_failure)
+    }
+}
+
+impl<T: Serialize> Act<&Vec<T>, DataFrame> for ToFrame {
+    fn act(&self, records: &Vec<T>) -> Out<DataFrame> {
+        self.act(records.as_slice())
+    }
+}
+
+impl<T: Serialize> Act<Vec<T>, DataFrame> for ToFrame {
+    fn act(&self, records: Vec<T>) -> Out<DataFrame> {
+        self.act(records.as_slice())
+    }
+}
+
+fn json_value(value: AnyValue) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(b) => Value::Bool(b),
+        AnyValue::String(s) => Value::from(s),
+        AnyValue::StringOwned(s) => Value::from(s.as_str()),
+        AnyValue::Float32(f) => Value::from(f as f64),
+        AnyValue::Float64(f) => Value::from(f),
+        v if v.is_signed_integer() => v.extract::<i64>().map_or(Value::Null, Value::from),
+        v if v.is_unsigned_integer() => v.extract::<u64>().map_or(Value::Null, Value::from),
+        other => Value::String(other.to_string()),
+    }
+}
+
+impl<T: DeserializeOwned> Act<DataFrame, Vec<T>> for FromFrame<T> {
+    fn act(&self, frame: DataFrame) -> Out<Vec<T>> {
+        (0..frame.height())
+            .map(|row| {
+                let mut fields = Map::new();
+                for column in frame.columns() {
+                    let value = column.get(row).map_err(polars_failure)?;
+                    fields.insert(column.name().to_string(), json_value(value));
+                }
+                serde_json::from_value(Value::Object(fields))
+                    .map_err(|e| Failure::InvalidInput(format!("row {}: {}", row, e)).with_cause(e))
+            })
+            .collect()
+    }
+}
+
+impl<F> Act<DataFrame, DataFrame> for FrameOp<F>
+where
+    F: Fn(DataFrame) -> PolarsResult<DataFrame>,
+{
+    fn act(&self, frame: DataFrame) -> Out<DataFrame> {
+        (self.op)(frame).map_err(|e| polars_failure(e).at_step(&self.name))
+    }
+
+    fn label(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+}
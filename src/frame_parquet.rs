@@ -0,0 +1,107 @@
+use super::*;
+use crate::frame::polars_failure;
+use polars::prelude::{DataFrame, ParquetReader, ParquetWriter, SerReader};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// the act returned by `from_parquet`
+pub struct FromParquet<T>(PhantomData<fn() -> T>);
+
+/// the act returned by `to_parquet`
+#[derive(Debug, Clone)]
+pub struct ToParquet {
+    path: PathBuf,
+}
+
+/// read the Parquet file at the input path, into a `Vec<T>` of records (see
+/// `from_frame`) or, as `from_parquet::<DataFrame>()`, into a polars
+/// `DataFrame`. a file that can't be opened fails with the io error, one
+/// that isn't Parquet with `Custom`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use polars::prelude::DataFrame;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Reading {
+///     sensor: String,
+///     celsius: f64,
+/// }
+///
+/// let path = std::env::temp_dir().join(format!("readings-{}.parquet", std::process::id()));
+/// let readings = vec![
+///     Reading { sensor: "roof".into(), celsius: 21.5 },
+///     Reading { sensor: "cellar".into(), celsius: 12.0 },
+/// ];
+///
+/// let written = Reactor::<Vec<Reading>>::input(readings)
+///     .then(to_parquet(&path))
+///     .run()
+///     .unwrap();
+/// assert_eq!(written, 2);
+///
+/// let back = Reactor::<&std::path::Path>::input(path.as_path())
+///     .then(from_parquet::<Reading>())
+///     .run()
+///     .unwrap();
+/// assert_eq!(back[1], Reading { sensor: "cellar".into(), celsius: 12.0 });
+///
+/// let frame: DataFrame = from_parquet::<DataFrame>().act(&path).unwrap();
+/// assert_eq!(frame.shape(), (2, 2));
+///
+/// std::fs::write(&path, "sensor,celsius\n").unwrap();
+/// assert!(from_parquet::<Reading>().act(&path).is_err());
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub fn from_parquet<T>() -> FromParquet<T> {
+    FromParquet(PhantomData)
+}
+
+/// write the records (or a polars `DataFrame`) to a Parquet file at `path`,
+/// replacing any file there. the output is the number of rows written.
+pub fn to_parquet<P: AsRef<Path>>(path: P) -> ToParquet {
+    ToParquet {
+        path: path.as_ref().to_path_buf(),
+    }
+}
+
+fn read_frame(path: &Path) -> Out<DataFrame> {
+    let file = File::open(path)?;
+    ParquetReader::new(file).finish().map_err(polars_failure)
+}
+
+impl<P: AsRef<Path>> Act<P, DataFrame> for FromParquet<DataFrame> {
+    fn act(&self, path: P) -> Out<DataFrame> {
+        read_frame(path.as_ref())
+    }
+}
+
+impl<P, T> Act<P, Vec<T>> for FromParquet<T>
+where
+    P: AsRef<Path>,
+    T: DeserializeOwned,
+{
+    fn act(&self, path: P) -> Out<Vec<T>> {
+        from_frame().act(read_frame(path.as_ref())?)
+    }
+}
+
+impl Act<DataFrame, usize> for ToParquet {
+    fn act(&self, mut frame: DataFrame) -> Out<usize> {
+        let file = File::create(&self.path)?;
+        ParquetWriter::new(file)
+            .finish(&mut frame)
+            .map_err(polars_failure)?;
+        Ok(frame.height())
+    }
+}
+
+impl<T: Serialize> Act<Vec<T>, usize> for ToParquet {
+    fn act(&self, records: Vec<T>) -> Out<usize> {
+        self.act(to_frame().act(records)?)
+    }
+}
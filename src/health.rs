@@ -0,0 +1,343 @@
+use super::*;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// overall state reported by a `Health` handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// no run has finished yet, and the staleness limit hasn't passed
+    Starting,
+    Healthy,
+    /// runs succeed, but the error rate or lag is above its threshold
+    Degraded,
+    /// the last run failed, or no run finished within the staleness limit
+    Unhealthy,
+}
+
+impl std::fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            HealthStatus::Starting => "starting",
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        };
+        f.write_str(s)
+    }
+}
+
+/// a point-in-time view of a pipeline's health, cheap to clone and send to a
+/// health-check endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthSnapshot {
+    pub status: HealthStatus,
+    pub runs: u64,
+    pub failures: u64,
+    /// failure rate over the most recent runs
+    pub error_rate: f64,
+    pub last_run_at: Option<SystemTime>,
+    pub last_run_duration: Option<Duration>,
+    pub last_error: Option<String>,
+    /// how far behind its input the pipeline last reported being
+    pub lag: Option<Duration>,
+}
+
+impl HealthSnapshot {
+    /// true unless the pipeline is unhealthy
+    pub fn is_live(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+
+    /// true once a run has completed and the pipeline isn't unhealthy
+    pub fn is_ready(&self) -> bool {
+        matches!(self.status, HealthStatus::Healthy | HealthStatus::Degraded)
+    }
+
+    /// the snapshot as a json object, for health-check responses
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::time::Duration;
+    ///
+    /// let health = Health::new();
+    /// health.record_failure("bad \"row\"\n\tat\u{1}", Duration::from_millis(5));
+    /// let json = health.snapshot().to_json();
+    /// assert!(json.starts_with("{\"status\":\"unhealthy\",\"runs\":1,\"failures\":1,\"error_rate\":1,"));
+    /// assert!(json.contains(r#""last_error":"bad \"row\"\n\tat\u0001""#));
+    /// assert!(json.contains("\"last_run_duration\":0.005,"));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let opt_secs =
+            |d: Option<Duration>| d.map_or("null".to_string(), |d| d.as_secs_f64().to_string());
+        let last_run_at = self
+            .last_run_at
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok());
+        let last_error = self
+            .last_error
+            .as_deref()
+            .map_or("null".to_string(), json_string);
+        format!(
+            "{{\"status\":\"{}\",\"runs\":{},\"failures\":{},\"error_rate\":{},\"last_run_at\":{},\"last_run_duration\":{},\"last_error\":{},\"lag\":{}}}",
+            self.status,
+            self.runs,
+            self.failures,
+            self.error_rate,
+            opt_secs(last_run_at),
+            opt_secs(self.last_run_duration),
+            last_error,
+            opt_secs(self.lag),
+        )
+    }
+}
+
+/// `s` as a json string literal, escaping quotes, backslashes and every
+/// control character
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct HealthState {
+    recent: VecDeque<bool>,
+    runs: u64,
+    failures: u64,
+    last_ok: Option<bool>,
+    last_run_at: Option<SystemTime>,
+    last_finished: Option<Instant>,
+    last_run_duration: Option<Duration>,
+    last_error: Option<String>,
+    lag: Option<Duration>,
+    created: Instant,
+}
+
+/// a shareable handle that a scheduled or streaming pipeline reports its runs
+/// to, and that the embedding process reads to answer health checks. clones
+/// share the same state and can be moved to other threads.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::time::Duration;
+///
+/// let health = Health::new().window(4).max_error_rate(0.3).max_lag(Duration::from_secs(5));
+/// assert!(!health.snapshot().is_ready());
+///
+/// for ok in [true, true, false, true] {
+///     let _ = health.track(|| if ok { Ok(()) } else { Err(Failure::Custom("boom".into())) });
+/// }
+/// let snapshot = health.snapshot();
+/// assert_eq!(snapshot.status, HealthStatus::Healthy);
+/// assert_eq!(snapshot.error_rate, 0.25);
+/// assert_eq!(snapshot.last_error.as_deref(), Some("Custom(\"boom\")"));
+///
+/// health.record_lag(Duration::from_secs(10));
+/// assert_eq!(health.snapshot().status, HealthStatus::Degraded);
+///
+/// let _ = Reactor::<i32>::input(1)
+///     .then(|_: i32| Err::<i32, _>(Failure::Custom("down".into())))
+///     .run_reported(&health);
+/// assert_eq!(health.snapshot().status, HealthStatus::Unhealthy);
+/// assert_eq!(health.snapshot().runs, 5);
+/// ```
+#[derive(Clone)]
+pub struct Health {
+    state: Arc<Mutex<HealthState>>,
+    window: usize,
+    max_error_rate: f64,
+    max_lag: Option<Duration>,
+    stale_after: Option<Duration>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Health {
+            state: Arc::new(Mutex::new(HealthState {
+                recent: VecDeque::new(),
+                runs: 0,
+                failures: 0,
+                last_ok: None,
+                last_run_at: None,
+                last_finished: None,
+                last_run_duration: None,
+                last_error: None,
+                lag: None,
+                created: Instant::now(),
+            })),
+            window: 100,
+            max_error_rate: 0.1,
+            max_lag: None,
+            stale_after: None,
+        }
+    }
+
+    /// how many recent runs the error rate is computed over (default 100)
+    pub fn window(mut self, runs: usize) -> Self {
+        self.window = runs.max(1);
+        self
+    }
+
+    /// error rate above which the pipeline is degraded (default 0.1)
+    pub fn max_error_rate(mut self, rate: f64) -> Self {
+        self.max_error_rate = rate;
+        self
+    }
+
+    /// lag above which the pipeline is degraded
+    pub fn max_lag(mut self, lag: Duration) -> Self {
+        self.max_lag = Some(lag);
+        self
+    }
+
+    /// mark the pipeline unhealthy if no run finished within this long. until
+    /// the first run finishes, this is measured from when the handle was
+    /// created, so a pipeline that never completes a run doesn't stay
+    /// `Starting` forever.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::time::Duration;
+    ///
+    /// let health = Health::new().stale_after(Duration::from_millis(20));
+    /// assert_eq!(health.snapshot().status, HealthStatus::Starting);
+    /// std::thread::sleep(Duration::from_millis(30));
+    /// assert_eq!(health.snapshot().status, HealthStatus::Unhealthy);
+    ///
+    /// health.record_success(Duration::ZERO);
+    /// assert_eq!(health.snapshot().status, HealthStatus::Healthy);
+    /// std::thread::sleep(Duration::from_millis(30));
+    /// assert!(!health.snapshot().is_live());
+    /// ```
+    pub fn stale_after(mut self, after: Duration) -> Self {
+        self.stale_after = Some(after);
+        self
+    }
+
+    /// `duration` is `None` when it wasn't measured; the last known one is then
+    /// cleared rather than kept, so it never describes a different run
+    fn record(&self, ok: bool, error: Option<String>, duration: Option<Duration>) {
+        let mut s = self.state.lock().unwrap();
+        s.runs += 1;
+        s.failures += !ok as u64;
+        s.recent.push_back(ok);
+        while s.recent.len() > self.window {
+            s.recent.pop_front();
+        }
+        s.last_ok = Some(ok);
+        s.last_run_at = Some(SystemTime::now());
+        s.last_finished = Some(Instant::now());
+        s.last_run_duration = duration;
+        if error.is_some() {
+            s.last_error = error;
+        }
+    }
+
+    pub fn record_success(&self, duration: Duration) {
+        self.record(true, None, Some(duration))
+    }
+
+    pub fn record_failure(&self, error: &str, duration: Duration) {
+        self.record(false, Some(error.to_string()), Some(duration))
+    }
+
+    /// report how far behind its input a streaming pipeline currently is
+    pub fn record_lag(&self, lag: Duration) {
+        self.state.lock().unwrap().lag = Some(lag);
+    }
+
+    /// run `f`, recording its outcome and duration
+    pub fn track<T, E, F>(&self, f: F) -> Out<T, E>
+    where
+        E: Debug,
+        F: FnOnce() -> Out<T, E>,
+    {
+        let start = Instant::now();
+        let out = f();
+        match &out {
+            Ok(_) => self.record_success(start.elapsed()),
+            Err(e) => self.record_failure(&format!("{:?}", e), start.elapsed()),
+        }
+        out
+    }
+
+    pub fn snapshot(&self) -> HealthSnapshot {
+        let s = self.state.lock().unwrap();
+        let error_rate = if s.recent.is_empty() {
+            0.0
+        } else {
+            s.recent.iter().filter(|ok| !**ok).count() as f64 / s.recent.len() as f64
+        };
+        let stale = self
+            .stale_after
+            .is_some_and(|after| s.last_finished.unwrap_or(s.created).elapsed() > after);
+        let lagging = match (self.max_lag, s.lag) {
+            (Some(max), Some(lag)) => lag > max,
+            _ => false,
+        };
+        let status = match s.last_ok {
+            None if stale => HealthStatus::Unhealthy,
+            None => HealthStatus::Starting,
+            Some(false) => HealthStatus::Unhealthy,
+            Some(true) if stale => HealthStatus::Unhealthy,
+            Some(true) if lagging || error_rate > self.max_error_rate => HealthStatus::Degraded,
+            Some(true) => HealthStatus::Healthy,
+        };
+        HealthSnapshot {
+            status,
+            runs: s.runs,
+            failures: s.failures,
+            error_rate,
+            last_run_at: s.last_run_at,
+            last_run_duration: s.last_run_duration,
+            last_error: s.last_error.clone(),
+            lag: s.lag,
+        }
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// finish the chain like `run`, reporting whether it succeeded to `health`.
+    /// stages run as they're added, so the duration isn't known here and
+    /// `last_run_duration` is left empty; wrap the whole chain in
+    /// `Health::track` when run durations matter.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::time::Duration;
+    ///
+    /// let health = Health::new();
+    /// health.record_success(Duration::from_millis(5));
+    /// let _ = Reactor::<i32>::input(1).then(|x: i32| Ok(x + 1)).run_reported(&health);
+    /// assert_eq!(health.snapshot().runs, 2);
+    /// assert_eq!(health.snapshot().last_run_duration, None);
+    /// ```
+    pub fn run_reported(self, health: &Health) -> Out<I, E> {
+        let out = self.run();
+        match &out {
+            Ok(_) => health.record(true, None, None),
+            Err(e) => health.record(false, Some(format!("{:?}", e)), None),
+        }
+        out
+    }
+}
@@ -0,0 +1,163 @@
+use super::*;
+use std::any::Any;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// the act names in a pipeline definition: separated by commas or newlines,
+/// with `#` starting a comment
+fn parse_definition(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// a pipeline of registered acts defined in a file, for long-running daemons.
+/// between runs it checks whether the file changed and, if so, rebuilds the
+/// pipeline from it. a definition that doesn't check out (unknown acts, types
+/// that don't chain) is reported through `last_error` and the previous
+/// pipeline stays in use.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let mut registry = ActRegistry::<Failure>::new();
+/// registry
+///     .register("add2", |x: i32| Ok(x + 2))
+///     .register("double", |x: i32| Ok(x * 2))
+///     .register("show", |x: i32| Ok(x.to_string()));
+///
+/// let path = std::env::temp_dir().join(format!("chain_reaction-reload-doc-{}", std::process::id()));
+/// std::fs::write(&path, "add2, show").unwrap();
+/// let mut pipeline = HotPipeline::<i32, String>::open(registry, &path).unwrap();
+/// assert_eq!(pipeline.run(1).unwrap(), "3");
+///
+/// std::fs::write(&path, "# doubled first\ndouble\nadd2\nshow").unwrap();
+/// pipeline.reload().unwrap();
+/// assert_eq!(pipeline.run(1).unwrap(), "4");
+///
+/// std::fs::write(&path, "add2, nope, show").unwrap();
+/// assert!(pipeline.reload().is_err());
+/// assert_eq!(pipeline.run(1).unwrap(), "4");
+/// assert!(pipeline.last_error().is_some());
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct HotPipeline<I, O, E = Failure> {
+    registry: ActRegistry<E>,
+    path: PathBuf,
+    stages: Vec<String>,
+    /// modification time and length of the file the stages came from
+    seen: Option<(SystemTime, u64)>,
+    check_every: Duration,
+    last_check: Instant,
+    last_error: Option<String>,
+    reloads: usize,
+    _types: PhantomData<fn(I) -> O>,
+}
+
+impl<I, O, E> HotPipeline<I, O, E>
+where
+    I: Any + Debug,
+    O: Any,
+    E: Debug + From<Failure> + 'static,
+{
+    /// load the pipeline defined in `path`, which has to be valid to start with
+    pub fn open<P: Into<PathBuf>>(registry: ActRegistry<E>, path: P) -> Out<Self, E> {
+        let mut pipeline = HotPipeline {
+            registry,
+            path: path.into(),
+            stages: Vec::new(),
+            seen: None,
+            check_every: Duration::from_millis(500),
+            last_check: Instant::now(),
+            last_error: None,
+            reloads: 0,
+            _types: PhantomData,
+        };
+        pipeline.reload()?;
+        pipeline.reloads = 0;
+        Ok(pipeline)
+    }
+
+    /// look at the file at most this often (default every 500ms)
+    pub fn check_every(mut self, interval: Duration) -> Self {
+        self.check_every = interval;
+        self
+    }
+
+    fn file_version(&self) -> Out<(SystemTime, u64), E> {
+        let meta = fs::metadata(&self.path)
+            .map_err(|e| Failure::Custom(format!("pipeline {}: {}", self.path.display(), e)))?;
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        Ok((modified, meta.len()))
+    }
+
+    /// rebuild the pipeline from the file now, whether it changed or not. on
+    /// failure the current pipeline is kept and the error is also remembered
+    /// as `last_error`.
+    pub fn reload(&mut self) -> Out<(), E> {
+        let loaded = self.file_version().and_then(|version| {
+            let text = fs::read_to_string(&self.path)
+                .map_err(|e| Failure::Custom(format!("pipeline {}: {}", self.path.display(), e)))?;
+            let stages = parse_definition(&text);
+            let names: Vec<&str> = stages.iter().map(String::as_str).collect();
+            self.registry.check_chain::<I, O>(&names)?;
+            Ok((version, stages))
+        });
+        self.last_check = Instant::now();
+        match loaded {
+            Ok((version, stages)) => {
+                self.seen = Some(version);
+                self.stages = stages;
+                self.last_error = None;
+                self.reloads += 1;
+                Ok(())
+            }
+            Err(e) => {
+                // remember the version so a broken file isn't re-read every run
+                self.seen = self.file_version().ok().or(self.seen);
+                self.last_error = Some(format!("{:?}", e));
+                Err(e)
+            }
+        }
+    }
+
+    /// reload if the file changed since it was last read; true if the
+    /// pipeline was swapped
+    pub fn check(&mut self) -> bool {
+        self.last_check = Instant::now();
+        match self.file_version() {
+            Ok(version) if Some(version) != self.seen => self.reload().is_ok(),
+            _ => false,
+        }
+    }
+
+    /// run the current pipeline on `input`, picking up changes to the file
+    /// first. a run always uses one version of the pipeline from start to end.
+    pub fn run(&mut self, input: I) -> Out<O, E> {
+        if self.last_check.elapsed() >= self.check_every {
+            self.check();
+        }
+        let names: Vec<&str> = self.stages.iter().map(String::as_str).collect();
+        self.registry.call_chain(&names, input)
+    }
+
+    /// the act names of the pipeline in use
+    pub fn stages(&self) -> &[String] {
+        &self.stages
+    }
+
+    /// why the latest attempt to reload failed, until a reload succeeds
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// how many times the pipeline was swapped since it was opened
+    pub fn reloads(&self) -> usize {
+        self.reloads
+    }
+}
@@ -0,0 +1,169 @@
+use super::*;
+
+struct CachedResponse {
+    response: Response,
+    stored: Instant,
+    /// the request headers named by the response's `vary`, with the values
+    /// they had when it was fetched
+    varied: Vec<(String, Option<String>)>,
+}
+
+impl CachedResponse {
+    /// true if this response was fetched for a request like `req`
+    fn matches(&self, req: &Request) -> bool {
+        self.varied
+            .iter()
+            .all(|(name, value)| req.header(name) == value.as_deref())
+    }
+}
+
+/// the headers `response` varies on, with their values in `req`; `None` for
+/// `vary: *`, which can't be cached
+fn varied(req: &Request, response: &Response) -> Option<Vec<(String, Option<String>)>> {
+    let mut varied = Vec::new();
+    for name in response.header("vary").unwrap_or("").split(',') {
+        let name = name.trim().to_ascii_lowercase();
+        match name.as_str() {
+            "" => {}
+            "*" => return None,
+            _ => {
+                let value = req.header(&name).map(str::to_string);
+                varied.push((name, value));
+            }
+        }
+    }
+    Some(varied)
+}
+
+/// wraps an http-fetching act (`Request -> Response`) with a response cache
+/// keyed by url. fresh entries are served without a request; once an entry is
+/// older than the ttl it is revalidated with `if-none-match` /
+/// `if-modified-since`, and a `304 Not Modified` answer keeps serving the
+/// cached response. only successful `GET` responses are stored. a response
+/// with a `vary` header is only served to requests with the same values for
+/// the headers it names, and `vary: *` responses aren't stored.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::cell::Cell;
+/// use std::time::Duration;
+///
+/// let calls = Cell::new(0);
+/// let origin = |req: Request| -> Out<Response> {
+///     calls.set(calls.get() + 1);
+///     let lang = req.header("accept-language").unwrap_or("en").to_string();
+///     if req.header("if-none-match") == Some("\"v1\"") {
+///         return Ok(Response::new(304));
+///     }
+///     Ok(Response::new(200).with_header("etag", "\"v1\"").with_header("vary", "Accept-Language").with_body(lang))
+/// };
+/// let cache = http_cached(origin, Duration::from_millis(50));
+/// let get = |lang: &str| {
+///     let req = Request::new("GET", "/greeting").with_header("Accept-Language", lang);
+///     String::from_utf8(cache.act(req).unwrap().body).unwrap()
+/// };
+///
+/// assert_eq!(get("en"), "en");
+/// assert_eq!(get("fr"), "fr");
+/// assert_eq!((get("en"), get("fr")), ("en".to_string(), "fr".to_string()));
+/// assert_eq!(calls.get(), 2);
+///
+/// // once stale, the entry is revalidated and a 304 keeps it
+/// std::thread::sleep(Duration::from_millis(60));
+/// assert_eq!(get("fr"), "fr");
+/// assert_eq!(calls.get(), 3);
+/// assert_eq!(get("fr"), "fr");
+/// assert_eq!(calls.get(), 3);
+///
+/// let posted = cache.act(Request::new("POST", "/greeting")).unwrap();
+/// assert_eq!(posted.status, 200);
+/// assert_eq!(calls.get(), 4);
+/// ```
+pub struct HttpCache<A> {
+    fetch: A,
+    ttl: Duration,
+    /// the variants cached for each url
+    entries: RefCell<HashMap<String, Vec<CachedResponse>>>,
+}
+
+impl<A> HttpCache<A> {
+    pub fn new(fetch: A, ttl: Duration) -> Self {
+        HttpCache {
+            fetch,
+            ttl,
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// drop every cached response
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    fn key(req: &Request) -> String {
+        if req.query.is_empty() {
+            req.path.clone()
+        } else {
+            format!("{}?{}", req.path, req.query)
+        }
+    }
+}
+
+impl<A, E> Act<Request, Response, E> for HttpCache<A>
+where
+    A: Act<Request, Response, E>,
+    E: Debug,
+{
+    fn act(&self, mut input: Request) -> Out<Response, E> {
+        if !input.method.eq_ignore_ascii_case("GET") {
+            return self.fetch.act(input);
+        }
+        let key = Self::key(&input);
+        let request = input.clone();
+        let cached = self
+            .entries
+            .borrow()
+            .get(&key)
+            .and_then(|variants| variants.iter().find(|v| v.matches(&request)))
+            .map(|v| (v.response.clone(), v.stored.elapsed() < self.ttl));
+        if let Some((response, fresh)) = &cached {
+            if *fresh {
+                return Ok(response.clone());
+            }
+            if let Some(etag) = response.header("etag") {
+                input = input.with_header("if-none-match", etag);
+            }
+            if let Some(modified) = response.header("last-modified") {
+                input = input.with_header("if-modified-since", modified);
+            }
+        }
+        let response = self.fetch.act(input)?;
+        let mut entries = self.entries.borrow_mut();
+        let variants = entries.entry(key).or_default();
+        if response.status == 304 {
+            if let Some(entry) = variants.iter_mut().find(|v| v.matches(&request)) {
+                entry.stored = Instant::now();
+                return Ok(entry.response.clone());
+            }
+        }
+        let no_store = response
+            .header("cache-control")
+            .is_some_and(|v| v.contains("no-store"));
+        if response.status == 200 && !no_store {
+            if let Some(varied) = varied(&request, &response) {
+                variants.retain(|v| !v.matches(&request));
+                variants.push(CachedResponse {
+                    response: response.clone(),
+                    stored: Instant::now(),
+                    varied,
+                });
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// cache the responses of an http-fetching act for `ttl`; see `HttpCache`
+pub fn http_cached<A>(fetch: A, ttl: Duration) -> HttpCache<A> {
+    HttpCache::new(fetch, ttl)
+}
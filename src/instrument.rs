@@ -0,0 +1,66 @@
+use super::*;
+
+/// an act with a name, shown in reports and pipeline stages instead of its
+/// types. see `ChainableAct::named`.
+#[derive(Clone)]
+pub struct Named<A> {
+    pub(crate) act: A,
+    pub(crate) label: String,
+}
+
+/// a `Failure` from the act records the name as its step, see `Failure::at_step`
+impl<A, I, O, E> Act<I, O, E> for Named<A>
+where
+    A: Act<I, O, E>,
+    E: Debug + 'static,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        self.act.act(input).map_err(|e| record_step(e, &self.label))
+    }
+
+    fn label(&self) -> Option<&str> {
+        Some(&self.label)
+    }
+}
+
+/// the report of a traced run: every step's name, how often it ran, how long
+/// it took and where the run failed
+pub type ExecutionReport = RunReport;
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// trace every stage from here on, finishing with `run_traced`. stages
+    /// are named with `named`, or after their types.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let parse = |s: String| s.parse::<i32>().map_err(|e| Failure::InvalidInput(e.to_string()));
+    /// let (out, report) = Reactor::<Vec<String>>::input(vec!["1".into(), "x".into()])
+    ///     .instrument()
+    ///     .for_each(parse.named("parse"))
+    ///     .then((|v: Vec<i32>| Ok(v.len())).named("count"))
+    ///     .run_traced();
+    ///
+    /// assert!(out.is_err());
+    /// assert_eq!(report.stages[0].name, "parse");
+    /// assert_eq!(report.stages[0].items, 2);
+    /// assert_eq!(report.failed_stage().unwrap().name, "parse");
+    /// assert_eq!(report.stages[1].items, 0);
+    /// ```
+    pub fn instrument(self) -> Layered<I, Reporter, E> {
+        self.layer(Reporter::new())
+    }
+}
+
+impl<I, E> Layered<I, Reporter, E>
+where
+    E: Debug,
+{
+    /// finish the chain like `run`, along with the trace of every step
+    pub fn run_traced(self) -> (Out<I, E>, ExecutionReport) {
+        self.run_with_report()
+    }
+}
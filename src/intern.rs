@@ -0,0 +1,148 @@
+use super::*;
+use std::cell::Ref;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// a handle to a string in an `Interner`: four bytes, `Copy`, and compared or
+/// hashed as an integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sym(u32);
+
+impl Sym {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+fn hash_str(s: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    s.hash(&mut h);
+    h.finish()
+}
+
+/// stores each distinct string once, back to back in one growing buffer, and
+/// hands out `Sym` handles for them. parsing stages that see the same values
+/// over and over (log levels, hostnames, field names) can keep `Sym`s instead
+/// of allocating a `String` per occurrence.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let mut interner = Interner::new();
+/// let a = interner.intern("error");
+/// let b = interner.intern("warn");
+/// assert_eq!(interner.intern("error"), a);
+/// assert_ne!(a, b);
+/// assert_eq!(interner.resolve(b), "warn");
+/// assert_eq!(interner.len(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct Interner {
+    arena: String,
+    /// where each symbol's text sits in the arena
+    spans: Vec<(usize, usize)>,
+    /// symbols by the hash of their text
+    lookup: HashMap<u64, Vec<Sym>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the symbol for `s`, if it was interned before
+    pub fn get(&self, s: &str) -> Option<Sym> {
+        self.lookup
+            .get(&hash_str(s))?
+            .iter()
+            .copied()
+            .find(|sym| self.resolve(*sym) == s)
+    }
+
+    /// the symbol for `s`, storing it on first sight
+    pub fn intern(&mut self, s: &str) -> Sym {
+        if let Some(sym) = self.get(s) {
+            return sym;
+        }
+        let sym = Sym(u32::try_from(self.spans.len()).expect("interner is full"));
+        let start = self.arena.len();
+        self.arena.push_str(s);
+        self.spans.push((start, self.arena.len()));
+        self.lookup.entry(hash_str(s)).or_default().push(sym);
+        sym
+    }
+
+    /// the text of a symbol from this interner
+    pub fn resolve(&self, sym: Sym) -> &str {
+        let (start, end) = self.spans[sym.index()];
+        &self.arena[start..end]
+    }
+
+    /// the number of distinct strings
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// bytes of string data held
+    pub fn arena_bytes(&self) -> usize {
+        self.arena.len()
+    }
+}
+
+/// an `Interner` that several stages can share; clones use the same one.
+#[derive(Debug, Clone, Default)]
+pub struct SharedInterner {
+    inner: Rc<RefCell<Interner>>,
+}
+
+impl SharedInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&self, s: &str) -> Sym {
+        self.inner.borrow_mut().intern(s)
+    }
+
+    pub fn get(&self, s: &str) -> Option<Sym> {
+        self.inner.borrow().get(s)
+    }
+
+    /// the text of a symbol; don't hold on to it across calls to `intern`
+    pub fn resolve(&self, sym: Sym) -> Ref<'_, str> {
+        Ref::map(self.inner.borrow(), |i| i.resolve(sym))
+    }
+
+    /// an act that interns its input, for use as a stage
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let levels = SharedInterner::new();
+    /// let syms = Reactor::<Vec<&str>>::input(vec!["info", "warn", "info"])
+    ///     .for_each(levels.act())
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(syms[0], syms[2]);
+    /// assert_eq!(&*levels.resolve(syms[1]), "warn");
+    /// ```
+    pub fn act<S, E>(&self) -> impl Fn(S) -> Out<Sym, E> + Clone
+    where
+        S: AsRef<str>,
+    {
+        let interner = self.clone();
+        move |s: S| Ok(interner.intern(s.as_ref()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().is_empty()
+    }
+}
@@ -0,0 +1,254 @@
+use super::*;
+
+/// a cross-cutting wrapper applied to every stage of a chain (logging, timing,
+/// retries, auth...), instead of wrapping each act by hand.
+pub trait Layer {
+    fn wrap<A, I, O, E>(&self, act: A) -> impl Act<I, O, E> + use<Self, A, I, O, E>
+    where
+        A: Act<I, O, E>,
+        E: Debug;
+}
+
+/// two layers applied together; the first one is the outermost
+impl<L1, L2> Layer for (L1, L2)
+where
+    L1: Layer,
+    L2: Layer,
+{
+    fn wrap<A, I, O, E>(&self, act: A) -> impl Act<I, O, E> + use<L1, L2, A, I, O, E>
+    where
+        A: Act<I, O, E>,
+        E: Debug,
+    {
+        self.0.wrap(self.1.wrap(act))
+    }
+}
+
+/// records how long every stage it wraps took. clones share the same record,
+/// so keep one around to read the durations after the run.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let timing = Timing::new();
+/// let out = Reactor::<i32>::input(3)
+///     .layer(timing.clone())
+///     .then(|x: i32| Ok(x + 1))
+///     .then(|x: i32| Ok(x * 2))
+///     .run();
+/// assert_eq!(out.unwrap(), 8);
+/// assert_eq!(timing.durations().len(), 2);
+/// ```
+#[derive(Clone, Default)]
+pub struct Timing {
+    durations: Rc<RefCell<Vec<Duration>>>,
+}
+
+impl Timing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the duration of every stage run so far, in order
+    pub fn durations(&self) -> Vec<Duration> {
+        self.durations.borrow().clone()
+    }
+}
+
+struct Timed<A> {
+    act: A,
+    durations: Rc<RefCell<Vec<Duration>>>,
+}
+
+impl<A, I, O, E> Act<I, O, E> for Timed<A>
+where
+    A: Act<I, O, E>,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let start = Instant::now();
+        let out = self.act.act(input);
+        self.durations.borrow_mut().push(start.elapsed());
+        out
+    }
+}
+
+impl Layer for Timing {
+    fn wrap<A, I, O, E>(&self, act: A) -> impl Act<I, O, E> + use<A, I, O, E>
+    where
+        A: Act<I, O, E>,
+        E: Debug,
+    {
+        Timed {
+            act,
+            durations: self.durations.clone(),
+        }
+    }
+}
+
+/// a reactor whose stages all go through a layer. see `Reactor::layer`.
+pub struct Layered<I, L, E = Failure> {
+    pub(crate) reactor: Reactor<I, E>,
+    pub(crate) layer: L,
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// wrap every act passed to `then` from here on in `layer`
+    pub fn layer<L: Layer>(self, layer: L) -> Layered<I, L, E> {
+        Layered {
+            reactor: self,
+            layer,
+        }
+    }
+}
+
+impl<I, L, E> Layered<I, L, E>
+where
+    L: Layer,
+    E: Debug,
+{
+    /// add another layer, inside the ones already applied
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let (outer, inner) = (Timing::new(), Timing::new());
+    /// let lens = Reactor::<Vec<&str>>::input(vec!["a", "bb", "ccc"])
+    ///     .layer(outer.clone())
+    ///     .layer(inner.clone())
+    ///     .for_each(|s: &str| Ok(s.len()))
+    ///     .into_reactor()
+    ///     .run();
+    /// assert_eq!(lens.unwrap(), [1, 2, 3]);
+    /// assert_eq!(outer.durations().len(), 3);
+    /// assert_eq!(inner.durations().len(), 3);
+    /// ```
+    pub fn layer<L2: Layer>(self, layer: L2) -> Layered<I, (L, L2), E> {
+        Layered {
+            reactor: self.reactor,
+            layer: (self.layer, layer),
+        }
+    }
+
+    pub fn then<O, T>(self, transform: T) -> Layered<O, L, E>
+    where
+        T: Act<I, O, E>,
+    {
+        let wrapped = self.layer.wrap(transform);
+        Layered {
+            reactor: self.reactor.then(wrapped),
+            layer: self.layer,
+        }
+    }
+
+    /// run `transform` on every item, through the layer
+    pub fn for_each<O, T>(self, transform: T) -> Layered<Vec<O>, L, E>
+    where
+        I: IntoIterator,
+        T: Act<I::Item, O, E>,
+    {
+        let wrapped = self.layer.wrap(transform);
+        Layered {
+            reactor: self
+                .reactor
+                .and_then(|i| i.into_iter().map(|item| wrapped.act(item)).collect()),
+            layer: self.layer,
+        }
+    }
+
+    /// drop the layer and continue with a plain reactor
+    pub fn into_reactor(self) -> Reactor<I, E> {
+        self.reactor
+    }
+
+    pub fn run(self) -> Out<I, E> {
+        self.reactor.run()
+    }
+}
+
+/// a pipeline whose stages all go through a layer. see `Pipeline::with_layer`.
+pub struct LayeredPipeline<I, L, O = I, E = Failure> {
+    pipeline: Pipeline<I, O, E>,
+    layer: L,
+}
+
+impl<I, O, E> Pipeline<I, O, E>
+where
+    I: 'static,
+    O: 'static,
+    E: Debug + 'static,
+{
+    /// wrap every stage added from here on in `layer`
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let timing = Timing::new();
+    /// let p = Pipeline::<i32>::new("p")
+    ///     .stage("plain", |x: i32| Ok(x + 1))
+    ///     .with_layer(timing.clone())
+    ///     .stage("double", |x: i32| Ok(x * 2))
+    ///     .then(|x: i32| Ok(x.to_string()))
+    ///     .into_pipeline();
+    /// assert_eq!(p.stages(), ["plain", "double", "i32->String"]);
+    /// assert_eq!(p.run(1).unwrap(), "4");
+    /// assert_eq!(timing.durations().len(), 2);
+    /// ```
+    pub fn with_layer<L: Layer>(self, layer: L) -> LayeredPipeline<I, L, O, E> {
+        LayeredPipeline {
+            pipeline: self,
+            layer,
+        }
+    }
+}
+
+impl<I, L, O, E> LayeredPipeline<I, L, O, E>
+where
+    I: 'static,
+    L: Layer + 'static,
+    O: 'static,
+    E: Debug + 'static,
+{
+    /// add another layer, inside the ones already applied
+    pub fn layer<L2: Layer>(self, layer: L2) -> LayeredPipeline<I, (L, L2), O, E> {
+        LayeredPipeline {
+            pipeline: self.pipeline,
+            layer: (self.layer, layer),
+        }
+    }
+
+    /// append a named stage, through the layer. the layer sees the act under
+    /// `name`, as if it had been given it with `named`.
+    pub fn stage<O2, T>(self, name: &str, act: T) -> LayeredPipeline<I, L, O2, E>
+    where
+        O2: 'static,
+        T: Act<O, O2, E> + 'static,
+    {
+        let wrapped = self.layer.wrap(Named {
+            act,
+            label: name.to_string(),
+        });
+        LayeredPipeline {
+            pipeline: self.pipeline.stage(name, wrapped),
+            layer: self.layer,
+        }
+    }
+
+    /// append a stage named like `Pipeline::then` would, through the layer
+    pub fn then<O2, T>(self, act: T) -> LayeredPipeline<I, L, O2, E>
+    where
+        O2: 'static,
+        T: Act<O, O2, E> + 'static,
+    {
+        let name = stage_name::<O, O2, E, T>(&act);
+        self.stage(&name, act)
+    }
+
+    /// drop the layer and continue with a plain pipeline
+    pub fn into_pipeline(self) -> Pipeline<I, O, E> {
+        self.pipeline
+    }
+}
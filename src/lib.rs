@@ -1,13 +1,171 @@
 #![allow(unused_imports,unused_variables,dead_code, unused_braces, unused_import_braces)]
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BinaryHeap, HashMap};
 use std::fmt::{Debug};
 use std::marker::PhantomData;
 use std::mem;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
+#[cfg(feature = "affinity")]
+mod affinity;
+#[cfg(feature = "async")]
+mod async_act;
+#[cfg(feature = "auth")]
+mod auth;
+mod blackboard;
+mod branch;
+mod checkpoint;
+#[cfg(feature = "cli")]
+mod cli;
+mod collections;
+mod context;
+mod debug;
+mod dedup;
+mod describe;
+mod details;
+mod diff;
+mod distributed;
+mod either;
+#[cfg(feature = "serde")]
+mod formats;
+#[cfg(feature = "polars")]
+mod frame;
+#[cfg(feature = "parquet")]
+mod frame_parquet;
+mod health;
+mod hot_reload;
+mod http_cache;
+mod instrument;
+mod intern;
+mod layer;
+mod memo;
+mod memory;
+mod messages;
+mod multipart;
+mod lines;
+mod offload;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod partial;
+mod pipeline;
+#[cfg(feature = "plugins")]
+mod plugin;
+mod policy;
+mod priority;
+mod profile;
+mod quality;
+mod quota;
+mod queue;
+mod recover;
+mod redis_queue;
+mod registry;
+#[cfg(feature = "repl")]
+mod repl;
+mod replay;
+mod report;
+mod resilience;
+mod resume;
+#[cfg(feature = "serde")]
+mod schema;
+mod scope;
+#[cfg(feature = "tower")]
+mod service;
+mod shared_cache;
+mod sink;
+mod snapshot;
+mod speculative;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+mod timeout;
+#[cfg(feature = "units")]
+mod units;
+mod validate;
+mod web;
+#[cfg(feature = "axum")]
+mod web_axum;
+mod window;
+mod wire;
+#[cfg(feature = "serde")]
+mod wire_serde;
+#[cfg(feature = "affinity")]
+pub use affinity::*;
+#[cfg(feature = "async")]
+pub use async_act::*;
+#[cfg(feature = "auth")]
+pub use auth::*;
+pub use blackboard::*;
+pub use branch::*;
+pub use checkpoint::*;
+#[cfg(feature = "cli")]
+pub use cli::*;
+pub use collections::*;
+pub use context::*;
+pub use debug::*;
+pub use dedup::*;
+pub use describe::{short_type_name, Shape};
+pub use details::*;
+pub use diff::*;
+pub use distributed::*;
+#[cfg(feature = "serde")]
+pub use formats::*;
+#[cfg(feature = "polars")]
+pub use frame::*;
+#[cfg(feature = "parquet")]
+pub use frame_parquet::*;
+pub use health::*;
+pub use hot_reload::*;
+pub use http_cache::*;
+pub use instrument::*;
+pub use intern::*;
+pub use layer::*;
+pub use memo::*;
+pub use memory::*;
+pub use messages::*;
+pub use multipart::*;
+pub use offload::*;
+#[cfg(feature = "parallel")]
+pub use parallel::*;
+pub use partial::*;
+pub use pipeline::*;
+#[cfg(feature = "plugins")]
+pub use plugin::*;
+pub use policy::*;
+pub use priority::*;
+pub use profile::*;
+pub use quality::*;
+pub use quota::*;
+pub use queue::*;
+pub use recover::*;
+pub use redis_queue::*;
+pub use registry::*;
+#[cfg(feature = "repl")]
+pub use repl::*;
+pub use replay::*;
+pub use report::*;
+pub use resilience::*;
+pub use resume::*;
+#[cfg(feature = "serde")]
+pub use schema::*;
+pub use scope::*;
+#[cfg(feature = "tower")]
+pub use service::*;
+pub use shared_cache::*;
+pub use sink::*;
+pub use snapshot::*;
+#[cfg(feature = "telemetry")]
+pub use telemetry::*;
+pub use timeout::*;
+#[cfg(feature = "units")]
+pub use units::*;
+pub use validate::*;
+pub use web::*;
+#[cfg(feature = "axum")]
+pub use web_axum::*;
+pub use window::*;
+pub use wire::*;
+
 
 
 
@@ -77,6 +235,12 @@ where
     E:  Debug,
 {
     fn act(&self, input: I) -> Out<O, E>;
+
+    /// the name given to this act with `named`, used by reports and pipelines
+    fn label(&self) -> Option<&str> {
+        None
+    }
+
     fn run(&self, input: I) -> O {
         match self.act(input) {
             Ok(output) => output,
@@ -102,6 +266,41 @@ where
             _marker: PhantomData,
         }
     }
+
+    /// give this act a name, shown instead of its types in reports
+    fn named(self, label: &str) -> Named<Self> {
+        Named {
+            act: self,
+            label: label.to_string(),
+        }
+    }
+
+    /// fail with `Failure::Timeout` when a run takes longer than `limit`.
+    /// see `Timeout`.
+    fn with_timeout(self, limit: Duration) -> Timeout<Self> {
+        Timeout::new(self, limit)
+    }
+
+    /// remember the outputs for inputs seen before. see `Cached`.
+    fn cached(self) -> Cached<Self, I, O> {
+        Cached::new(self, MemoCache::new())
+    }
+
+    /// remember outputs in `cache`, shared with whatever else uses it
+    fn cached_in(self, cache: &MemoCache<I, O>) -> Cached<Self, I, O> {
+        Cached::new(self, cache.clone())
+    }
+
+    /// erase this act's type, so acts and pipelines of the same shape can be
+    /// stored together
+    fn boxed(self) -> BoxedAct<I, O, E>
+    where
+        Self: 'static,
+    {
+        BoxedAct {
+            act: Box::new(self),
+        }
+    }
 }
 
 pub struct Chain<A, B, I, O1, O2, E>
@@ -138,7 +337,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Either<L, R> {
     Left(L),
     Right(R),
@@ -158,13 +357,51 @@ where
         Self { input: Ok(input) }
     }
 
-    pub fn then<O, T>(&mut self, transform: T) -> Reactor<O, E>
+    pub fn then<O, T>(self, transform: T) -> Reactor<O, E>
     where
         T: Act<I, O, E>,
     {
-        let input = mem::replace(&mut self.input, Err(unsafe { std::mem::zeroed() }));
         Reactor {
-            input: input.and_then(|i| transform.act(i)),
+            input: self.input.and_then(|i| transform.act(i)),
+        }
+    }
+
+    /// run `transform` only when `enabled`, otherwise pass the input through.
+    /// for stages switched on and off by configuration.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let debug = false;
+    /// let out = Reactor::<i32>::input(2)
+    ///     .then_if(true, |x: i32| Ok(x * 10))
+    ///     .then_if(debug, |x: i32| Ok(x + 1))
+    ///     .run();
+    /// assert_eq!(out.unwrap(), 20);
+    /// ```
+    pub fn then_if<T>(self, enabled: bool, transform: T) -> Reactor<I, E>
+    where
+        T: Act<I, I, E>,
+    {
+        self.then_some(enabled.then_some(transform))
+    }
+
+    /// run `transform` if there is one, otherwise pass the input through
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let enrich: Option<fn(String) -> Out<String>> = None;
+    /// let out = Reactor::<String>::input("log".to_string()).then_some(enrich).run();
+    /// assert_eq!(out.unwrap(), "log");
+    /// ```
+    pub fn then_some<T>(self, transform: Option<T>) -> Reactor<I, E>
+    where
+        T: Act<I, I, E>,
+    {
+        match transform {
+            Some(transform) => self.then(transform),
+            None => self,
         }
     }
 
@@ -172,7 +409,7 @@ where
     
 
     pub fn if_else<O1, O2, C, T1, T2>(
-        &mut self,
+        self,
         condition: C,
         true_transform: T1,
         false_transform: T2,
@@ -182,9 +419,8 @@ where
         T1: Act<I, O1, E>,
         T2: Act<I, O2, E>,
     {
-        let input = mem::replace(&mut self.input, Err(unsafe { std::mem::zeroed() }));
         Reactor {
-            input: input.and_then(|i| {
+            input: self.input.and_then(|i| {
                 if condition(&i) {
                     true_transform.act(i).map(Either::Left)
                 } else {
@@ -194,14 +430,13 @@ where
         }
     }
 
-    pub fn for_each<O, T>(&mut self, transform: T) -> Reactor<Vec<O>, E>
+    pub fn for_each<O, T>(self, transform: T) -> Reactor<Vec<O>, E>
     where
         I: IntoIterator,
         T: Act<I::Item, O, E> + Clone,
     {
-        let input = mem::replace(&mut self.input, Err(unsafe { std::mem::zeroed() }));
         Reactor {
-            input: input.and_then(|i| {
+            input: self.input.and_then(|i| {
                 i.into_iter()
                     .map(|item| transform.act(item))
                     .collect::<Result<Vec<_>, _>>()
@@ -209,46 +444,43 @@ where
         }
     }
 
-    pub fn map<O, F>(&mut self, f: F) -> Reactor<O, E>
+    pub fn map<O, F>(self, f: F) -> Reactor<O, E>
     where
         F: FnOnce(I) -> O,
     {
-        let input = mem::replace(&mut self.input, Err(unsafe { std::mem::zeroed() }));
         Reactor {
-            input: input.map(f),
+            input: self.input.map(f),
         }
     }
 
-    pub fn and_then<O, F>(&mut self, f: F) -> Reactor<O, E>
+    pub fn and_then<O, F>(self, f: F) -> Reactor<O, E>
     where
         F: FnOnce(I) -> Result<O, E>,
     {
-        let input = mem::replace(&mut self.input, Err(unsafe { std::mem::zeroed() }));
         Reactor {
-            input: input.and_then(f),
+            input: self.input.and_then(f),
         }
     }
 
-    pub fn merge<O, F>(&mut self, f: F) -> Reactor<O, E>
+    pub fn merge<O, F>(self, f: F) -> Reactor<O, E>
     where
         I: IntoIterator,
         I::Item: Clone,
         F: Fn(I::Item, I::Item) -> O,
     {
-        let input = mem::replace(&mut self.input, Err(unsafe { std::mem::zeroed() }));
         Reactor {
-            input: input.and_then(|i| {
+            input: self.input.map(|i| {
                 let mut iter = i.into_iter();
                 match (iter.next(), iter.next()) {
-                    (Some(a), Some(b)) => Ok(f(a, b)),
+                    (Some(a), Some(b)) => f(a, b),
                     _ => panic!("Merge operation requires at least two items"),
                 }
             }),
         }
     }
 
-    pub fn run(&mut self) -> Out<I, E> {
-        mem::replace(&mut self.input, Err(unsafe { std::mem::zeroed() }))
+    pub fn run(self) -> Out<I, E> {
+        self.input
     }
 }
  
@@ -256,7 +488,18 @@ where
 pub enum Failure {
     InvalidInput(String),
     ArithmeticError(String),
+    Unauthorized(String),
     Custom(String),
+    /// a failure annotated with the stage it happened in and the value that entered it
+    Snapshot(Box<StageSnapshot>),
+    /// a stage went over its time or memory quota
+    QuotaExceeded(Box<QuotaViolation>),
+    /// a failure with machine-readable details attached
+    Detailed(Box<FailureDetails>),
+    /// a stage ran past its time limit and was abandoned
+    Timeout(Box<TimedOut>),
+    /// the chain was cancelled through a `CancellationToken`
+    Cancelled(String),
 }
 
 impl std::fmt::Display for Failure {
@@ -264,11 +507,78 @@ impl std::fmt::Display for Failure {
         match self {
             Failure::InvalidInput(s) => write!(f, "Invalid input: {}", s),
             Failure::ArithmeticError(s) => write!(f, "Arithmetic error: {}", s),
+            Failure::Unauthorized(s) => write!(f, "Unauthorized: {}", s),
             Failure::Custom(s) => write!(f, "Custom error: {}", s),
+            Failure::Snapshot(s) => write!(f, "{} (in stage '{}' with input {})", s.source, s.stage, s.input),
+            Failure::QuotaExceeded(q) => write!(f, "Quota exceeded: {}", q),
+            Failure::Detailed(d) => write!(f, "{}", d),
+            Failure::Timeout(t) => write!(f, "Timed out: {}", t),
+            Failure::Cancelled(s) => write!(f, "Cancelled: {}", s),
         }
     }
 }
 
-impl std::error::Error for Failure {}
+impl Failure {
+    /// the failure underneath any snapshots
+    pub fn root(&self) -> &Failure {
+        match self {
+            Failure::Snapshot(s) => s.source.root(),
+            Failure::Detailed(d) => d.source.root(),
+            f => f,
+        }
+    }
+}
+
+impl std::error::Error for Failure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Failure::Snapshot(s) => Some(&s.source),
+            Failure::Detailed(d) => match &d.cause {
+                Some(cause) => Some(cause.as_ref()),
+                None => d.source.source(),
+            },
+            _ => None,
+        }
+    }
+}
+
+// the conversions below keep the original error as the cause, so they produce
+// `Failure::Detailed`; match on `root()` for the kind of failure.
+
+impl From<std::io::Error> for Failure {
+    fn from(e: std::io::Error) -> Self {
+        Failure::Custom(e.to_string()).with_cause(e)
+    }
+}
+
+impl From<std::num::ParseIntError> for Failure {
+    fn from(e: std::num::ParseIntError) -> Self {
+        Failure::InvalidInput(e.to_string()).with_cause(e)
+    }
+}
+
+impl From<std::num::ParseFloatError> for Failure {
+    fn from(e: std::num::ParseFloatError) -> Self {
+        Failure::InvalidInput(e.to_string()).with_cause(e)
+    }
+}
+
+impl From<std::str::ParseBoolError> for Failure {
+    fn from(e: std::str::ParseBoolError) -> Self {
+        Failure::InvalidInput(e.to_string()).with_cause(e)
+    }
+}
+
+impl From<std::str::Utf8Error> for Failure {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Failure::InvalidInput(e.to_string()).with_cause(e)
+    }
+}
+
+impl From<std::string::FromUtf8Error> for Failure {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Failure::InvalidInput(e.to_string()).with_cause(e)
+    }
+}
 
 
@@ -0,0 +1,200 @@
+use super::*;
+use std::io::{BufRead, ErrorKind, Write};
+
+/// the next record of `lines`: `None` at the end, after an error that leaves
+/// the reader unusable, or once `stop` is set; blank lines are skipped. a line
+/// that isn't valid utf-8 is a failure of that line only.
+fn next_record<E, T, P>(
+    lines: &mut impl Iterator<Item = (usize, std::io::Result<String>)>,
+    stopped: &mut bool,
+    parse: &P,
+) -> Option<(usize, Out<T, E>)>
+where
+    E: From<Failure>,
+    P: Fn(&str) -> Out<T, E>,
+{
+    if *stopped {
+        return None;
+    }
+    for (n, line) in lines {
+        let n = n + 1;
+        match line {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => return Some((n, parse(&line))),
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                return Some((
+                    n,
+                    Err(Failure::InvalidInput(e.to_string()).with_cause(e).into()),
+                ))
+            }
+            // anything else (a closed pipe, a directory) fails every read from here on
+            Err(e) => {
+                *stopped = true;
+                return Some((n, Err(Failure::from(e).into())));
+            }
+        }
+    }
+    None
+}
+
+impl<T, E> Reactor<Partial<T, E>, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// read newline-delimited records (e.g. JSON Lines) from `reader`, parsing
+    /// each non-empty line with `parse`. lines that can't be decoded or parsed
+    /// are collected as failures tagged with their 1-based line number, so one
+    /// bad record doesn't stop the rest of the file; an error that stops the
+    /// reader itself is recorded the same way and ends the input.
+    ///
+    /// the parser is a plain function, so any format works; for JSON Lines with
+    /// the `serde` feature, `from_jsonl` and `to_jsonl` do this for you. this
+    /// reads the whole input before the chain goes on; `process_lines` handles
+    /// one record at a time.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::io::{BufReader, Read};
+    ///
+    /// let input = "1\n\ntwo\n3\n";
+    /// let parsed = Reactor::<Partial<i32>>::from_lines(input.as_bytes(), |l| Ok(l.parse::<i32>()?))
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(parsed.items, [(1, 1), (4, 3)]);
+    /// assert_eq!(parsed.failures[0].0, 3);
+    ///
+    /// // a reader that keeps failing is reported once, not read forever
+    /// struct Broken;
+    /// impl Read for Broken {
+    ///     fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+    ///         Err(std::io::Error::other("is a directory"))
+    ///     }
+    /// }
+    /// let parsed = Reactor::<Partial<i32>>::from_lines(BufReader::new(Broken), |l| Ok(l.parse::<i32>()?))
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(parsed.failures.len(), 1);
+    /// ```
+    pub fn from_lines<R, P>(reader: R, parse: P) -> Self
+    where
+        R: BufRead,
+        P: Fn(&str) -> Out<T, E>,
+    {
+        let mut out = Partial::new();
+        let mut lines = reader.lines().enumerate();
+        let mut stopped = false;
+        while let Some((n, record)) = next_record(&mut lines, &mut stopped, &parse) {
+            match record {
+                Ok(record) => out.items.push((n, record)),
+                Err(e) => out.failures.push((n, e)),
+            }
+        }
+        Reactor { input: Ok(out) }
+    }
+
+    /// write every surviving record to `writer` as one line, rendered by `format`.
+    /// records that fail to render join the failures. the result is the full list
+    /// of failures so the caller can report them; an I/O error on the writer
+    /// fails the whole chain.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let mut out = Vec::new();
+    /// let failures = Reactor::<Partial<i32>>::from_lines("1\nx\n3\n".as_bytes(), |l| Ok(l.parse::<i32>()?))
+    ///     .then_each(|n: i32| Ok(n * 10))
+    ///     .to_lines(&mut out, |n: &i32| Ok(format!("{{\"n\":{}}}", n)))
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(String::from_utf8(out).unwrap(), "{\"n\":10}\n{\"n\":30}\n");
+    /// assert_eq!(failures.iter().map(|(n, _)| *n).collect::<Vec<_>>(), [2]);
+    /// ```
+    pub fn to_lines<W, F>(self, mut writer: W, format: F) -> Reactor<Vec<(usize, E)>, E>
+    where
+        W: Write,
+        F: Fn(&T) -> Out<String, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|p| {
+                let mut failures = p.failures;
+                for (n, record) in p.items {
+                    match format(&record) {
+                        Ok(line) => writeln!(writer, "{}", line).map_err(|e| {
+                            Failure::Custom(format!("failed writing record {}: {}", n, e))
+                        })?,
+                        Err(e) => failures.push((n, e)),
+                    }
+                }
+                writer
+                    .flush()
+                    .map_err(|e| Failure::Custom(format!("failed flushing output: {}", e)))?;
+                failures.sort_by_key(|(n, _)| *n);
+                Ok(failures)
+            }),
+        }
+    }
+}
+
+impl<E> Reactor<Vec<(usize, E)>, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// stream newline-delimited records from `reader` to `writer` one at a
+    /// time: each line is parsed, run through `transform` and written with
+    /// `format` before the next is read, so inputs of any size go through in
+    /// constant memory. the result is the failures, tagged with their line
+    /// numbers, as with `from_lines` and `to_lines`.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let mut out = Vec::new();
+    /// let failures = Reactor::<Vec<(usize, Failure)>>::process_lines(
+    ///     "4\nfour\n9\n".as_bytes(),
+    ///     &mut out,
+    ///     |l: &str| Ok(l.parse::<i32>()?),
+    ///     |n: i32| Ok(n * n),
+    ///     |n: &i32| Ok(n.to_string()),
+    /// )
+    /// .run()
+    /// .unwrap();
+    /// assert_eq!(String::from_utf8(out).unwrap(), "16\n81\n");
+    /// assert_eq!(failures[0].0, 2);
+    /// ```
+    pub fn process_lines<R, W, T, O, P, A, F>(
+        reader: R,
+        mut writer: W,
+        parse: P,
+        transform: A,
+        format: F,
+    ) -> Self
+    where
+        R: BufRead,
+        W: Write,
+        P: Fn(&str) -> Out<T, E>,
+        A: Act<T, O, E>,
+        F: Fn(&O) -> Out<String, E>,
+    {
+        let run = || -> Out<Vec<(usize, E)>, E> {
+            let mut failures = Vec::new();
+            let mut lines = reader.lines().enumerate();
+            let mut stopped = false;
+            while let Some((n, record)) = next_record(&mut lines, &mut stopped, &parse) {
+                match record
+                    .and_then(|record| transform.act(record))
+                    .and_then(|o| format(&o))
+                {
+                    Ok(line) => writeln!(writer, "{}", line).map_err(|e| {
+                        Failure::Custom(format!("failed writing record {}: {}", n, e))
+                    })?,
+                    Err(e) => failures.push((n, e)),
+                }
+            }
+            writer
+                .flush()
+                .map_err(|e| Failure::Custom(format!("failed flushing output: {}", e)))?;
+            Ok(failures)
+        };
+        Reactor { input: run() }
+    }
+}
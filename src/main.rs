@@ -1,5 +1,5 @@
  
- use std::{fs::DirEntry, path::{Path, PathBuf}};
+ use std::{fs::DirEntry, path::Path};
 
 use chain_reaction::*;
  // functions can do anything, as long as they return a Result<T, E>
@@ -42,12 +42,55 @@ use chain_reaction::*;
  }
 
  pub fn append(y: Vec<i32>) -> impl Fn(Vec<i32>) -> Out<Vec<i32>> {
-     move |x| Ok(x.into_iter().chain(y.clone().into_iter()).collect())
+     move |x| Ok(x.into_iter().chain(y.clone()).collect())
  }
 
  
+ #[cfg(any(feature = "repl", feature = "cli"))]
+ fn registry() -> ActRegistry {
+     let mut registry = ActRegistry::new();
+     registry
+         .register("add2", add(2))
+         .register("square", square())
+         .register("double", double())
+         .register("half", divide(2))
+         .register("to_string", to_string());
+     registry
+ }
+
+ #[cfg(feature = "repl")]
+ fn repl() {
+     let stdin = std::io::stdin();
+     if let Err(e) = Repl::new(registry()).run(stdin.lock(), std::io::stdout()) {
+         eprintln!("{}", e);
+     }
+ }
+
+ #[cfg(feature = "cli")]
+ fn cli(args: &[String]) {
+     let mut cli = Cli::new(registry());
+     cli.pipeline("example", &["add2", "square", "double", "to_string"]);
+     if let Err(e) = cli.run(args, std::io::stdout()) {
+         eprintln!("{}", e);
+         std::process::exit(1);
+     }
+ }
+
  fn main() {
  
+ #[cfg(feature = "repl")]
+     if std::env::args().nth(1).as_deref() == Some("repl") {
+         return repl();
+     }
+
+ #[cfg(feature = "cli")]
+     {
+         let args: Vec<String> = std::env::args().skip(1).collect();
+         if matches!(args.first().map(String::as_str), Some("run" | "list" | "graph" | "bench" | "help")) {
+             return cli(&args);
+         }
+     }
+
  // we can chain them together like this:
  // 5 -> add(2) -> square() -> to_string() -> double()
  // in a type safe and composable way
@@ -70,9 +113,9 @@ use chain_reaction::*;
         println!("{:?}", result);
 
         //now lets use chain_eractor to extract data from a folder of files
-        let data = Reactor::input(Path::new("."))
-        .then(|x: &Path| x.read_dir())
-        .for_each(|x: Result<DirEntry, std::io::Error>| Ok(x.unwrap())  )
+        let data = Reactor::<&Path>::input(Path::new("."))
+        .then(|x: &Path| Ok(x.read_dir()?))
+        .try_collect::<DirEntry, _>()
         .run();
 
         println!("{:?}", data);
@@ -0,0 +1,314 @@
+use super::*;
+use std::any::{Any, TypeId};
+use std::hash::Hash;
+
+struct Entry<O> {
+    output: O,
+    stored: Instant,
+    /// when the entry was last read or written, for evicting the least
+    /// recently used one
+    used: u64,
+}
+
+struct Store<I, O> {
+    entries: HashMap<I, Entry<O>>,
+    capacity: Option<usize>,
+    ttl: Option<Duration>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// an in-memory map from inputs to the outputs computed for them, used by
+/// `Cached`. clones share the same entries, so one cache can serve several
+/// acts or pipelines.
+pub struct MemoCache<I, O> {
+    store: Rc<RefCell<Store<I, O>>>,
+}
+
+impl<I, O> MemoCache<I, O> {
+    /// an unbounded cache whose entries never expire
+    pub fn new() -> Self {
+        MemoCache {
+            store: Rc::new(RefCell::new(Store {
+                entries: HashMap::new(),
+                capacity: None,
+                ttl: None,
+                tick: 0,
+                hits: 0,
+                misses: 0,
+            })),
+        }
+    }
+
+    /// keep at most `entries` outputs, evicting the least recently used
+    pub fn capacity(self, entries: usize) -> Self {
+        self.store.borrow_mut().capacity = Some(entries);
+        self
+    }
+
+    /// recompute outputs stored longer than `ttl` ago
+    pub fn ttl(self, ttl: Duration) -> Self {
+        self.store.borrow_mut().ttl = Some(ttl);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.borrow().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.borrow().entries.is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.store.borrow_mut().entries.clear();
+    }
+
+    /// how many lookups were answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.store.borrow().hits
+    }
+
+    /// how many lookups had to run the act
+    pub fn misses(&self) -> u64 {
+        self.store.borrow().misses
+    }
+}
+
+impl<I, O> MemoCache<I, O>
+where
+    I: Hash + Eq,
+    O: Clone,
+{
+    fn get(&self, input: &I) -> Option<O> {
+        let mut store = self.store.borrow_mut();
+        store.tick += 1;
+        let (tick, ttl) = (store.tick, store.ttl);
+        let fresh = match store.entries.get_mut(input) {
+            Some(entry) if ttl.is_none_or(|ttl| entry.stored.elapsed() <= ttl) => {
+                entry.used = tick;
+                Some(entry.output.clone())
+            }
+            _ => None,
+        };
+        match fresh {
+            Some(_) => store.hits += 1,
+            None => store.misses += 1,
+        }
+        fresh
+    }
+
+    fn insert(&self, input: I, output: O) {
+        let mut store = self.store.borrow_mut();
+        if store.capacity == Some(0) {
+            return;
+        }
+        let full = store
+            .capacity
+            .is_some_and(|capacity| store.entries.len() >= capacity);
+        if full && !store.entries.contains_key(&input) {
+            let oldest = store
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(_, entry)| entry.used);
+            store.entries.retain(|_, entry| Some(entry.used) != oldest);
+        }
+        let used = store.tick;
+        store.entries.insert(
+            input,
+            Entry {
+                output,
+                stored: Instant::now(),
+                used,
+            },
+        );
+    }
+}
+
+impl<I, O> Clone for MemoCache<I, O> {
+    fn clone(&self) -> Self {
+        MemoCache {
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<I, O> Default for MemoCache<I, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// an act that remembers its outputs, so inputs seen before skip the work.
+/// only successful outputs are kept. see `ChainableAct::cached`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::cell::Cell;
+///
+/// let calls = Cell::new(0);
+/// let lookup = (|id: u32| {
+///     calls.set(calls.get() + 1);
+///     Ok(format!("user-{}", id))
+/// })
+/// .cached()
+/// .capacity(100);
+///
+/// let names = Reactor::<Vec<u32>>::input(vec![1, 2, 1, 1, 2])
+///     .for_each(lookup.clone())
+///     .run()
+///     .unwrap();
+/// assert_eq!(names[2], "user-1");
+/// assert_eq!(calls.get(), 2);
+/// assert_eq!(lookup.cache().hits(), 3);
+/// ```
+pub struct Cached<A, I, O> {
+    act: A,
+    cache: MemoCache<I, O>,
+}
+
+impl<A, I, O> Cached<A, I, O> {
+    pub(crate) fn new(act: A, cache: MemoCache<I, O>) -> Self {
+        Cached { act, cache }
+    }
+
+    /// keep at most `entries` outputs, evicting the least recently used
+    pub fn capacity(self, entries: usize) -> Self {
+        Cached {
+            act: self.act,
+            cache: self.cache.capacity(entries),
+        }
+    }
+
+    /// recompute outputs stored longer than `ttl` ago
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Cached {
+            act: self.act,
+            cache: self.cache.ttl(ttl),
+        }
+    }
+
+    pub fn cache(&self) -> &MemoCache<I, O> {
+        &self.cache
+    }
+}
+
+impl<A, I, O> Clone for Cached<A, I, O>
+where
+    A: Clone,
+{
+    fn clone(&self) -> Self {
+        Cached {
+            act: self.act.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<A, I, O, E> Act<I, O, E> for Cached<A, I, O>
+where
+    A: Act<I, O, E>,
+    I: Hash + Eq + Clone,
+    O: Clone,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        if let Some(output) = self.cache.get(&input) {
+            return Ok(output);
+        }
+        let output = self.act.act(input.clone())?;
+        self.cache.insert(input, output.clone());
+        Ok(output)
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.act.label()
+    }
+}
+
+/// hands out caches that last for one `Reactor::cache_scope`. see there.
+///
+/// caches are named, not picked by the act's type, so two values of one act
+/// type only share outputs when they are cached under the same name.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// struct Mul(i32);
+///
+/// impl Act<i32, i32> for Mul {
+///     fn act(&self, x: i32) -> Out<i32> {
+///         Ok(x * self.0)
+///     }
+/// }
+///
+/// let scope = CacheScope::default();
+/// let double = scope.cached("double", Mul(2));
+/// let triple = scope.cached("triple", Mul(3));
+/// assert_eq!(double.act(5).unwrap(), 10);
+/// assert_eq!(triple.act(5).unwrap(), 15);
+///
+/// // the same name is the same cache
+/// let again = scope.cached("double", Mul(2));
+/// assert_eq!(again.act(5).unwrap(), 10);
+/// assert_eq!(again.cache().hits(), 1);
+/// ```
+#[derive(Default)]
+pub struct CacheScope {
+    caches: RefCell<HashMap<(String, TypeId), Box<dyn Any>>>,
+}
+
+impl CacheScope {
+    /// cache `act` in this scope under `name`. acts cached under the same
+    /// name (and with the same input and output types) share one cache.
+    pub fn cached<A, I, O>(&self, name: &str, act: A) -> Cached<A, I, O>
+    where
+        I: 'static,
+        O: 'static,
+    {
+        let mut caches = self.caches.borrow_mut();
+        let cache = caches
+            .entry((name.to_string(), TypeId::of::<MemoCache<I, O>>()))
+            .or_insert_with(|| Box::new(MemoCache::<I, O>::new()))
+            .downcast_ref::<MemoCache<I, O>>()
+            .expect("keyed by the cache's type")
+            .clone();
+        Cached::new(act, cache)
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// run `f` on this reactor with a `CacheScope`, so the acts it caches
+    /// keep their outputs across every item and stage within `f`, and drop
+    /// them when it returns.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::cell::Cell;
+    ///
+    /// let calls = Cell::new(0);
+    /// let slow_len = |s: String| {
+    ///     calls.set(calls.get() + 1);
+    ///     Ok(s.len())
+    /// };
+    /// let out = Reactor::<Vec<String>>::input(vec!["ab".into(), "abc".into(), "ab".into()])
+    ///     .cache_scope(|r, scope| {
+    ///         r.for_each(scope.cached("len", slow_len))
+    ///             .then(|lens: Vec<usize>| Ok(lens.iter().sum::<usize>()))
+    ///     })
+    ///     .run();
+    /// assert_eq!(out.unwrap(), 7);
+    /// assert_eq!(calls.get(), 2);
+    /// ```
+    pub fn cache_scope<O, F>(self, f: F) -> Reactor<O, E>
+    where
+        F: FnOnce(Reactor<I, E>, &CacheScope) -> Reactor<O, E>,
+    {
+        f(self, &CacheScope::default())
+    }
+}
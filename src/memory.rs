@@ -0,0 +1,196 @@
+use super::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::any::type_name;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// bytes allocated minus bytes freed by this thread. memory freed by
+    /// another thread than the one that allocated it makes this drift, so
+    /// only differences are meaningful.
+    static THREAD_CURRENT: Cell<isize> = const { Cell::new(0) };
+    /// the highest `THREAD_CURRENT` since the innermost `measure_peak` began
+    static THREAD_PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// count `bytes` (negative when freed) against the process and this thread
+fn count(bytes: isize) {
+    if bytes >= 0 {
+        CURRENT.fetch_add(bytes as usize, AtomicOrdering::Relaxed);
+    } else {
+        CURRENT.fetch_sub(bytes.unsigned_abs(), AtomicOrdering::Relaxed);
+    }
+    // the thread locals are const-initialised without destructors, so they
+    // never allocate; during thread teardown they may be gone already
+    let _ = THREAD_CURRENT.try_with(|current| {
+        let now = current.get() + bytes;
+        current.set(now);
+        let _ = THREAD_PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+    });
+}
+
+/// a global allocator that counts live heap bytes, so `MemoryTracker` can
+/// attribute allocations to stages. install it in the binary:
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// #[global_allocator]
+/// static ALLOC: TrackingAllocator = TrackingAllocator::system();
+///
+/// fn main() {
+///     let memory = MemoryTracker::new();
+///     let expand = memory.stage("expand", |n: usize| Ok(vec![0u64; n]));
+///     Reactor::<usize>::input(1000).then(expand).run().unwrap();
+///
+///     let usage = &memory.usage()[0];
+///     assert_eq!(usage.name, "expand");
+///     assert!(usage.peak >= 8000);
+/// }
+/// ```
+///
+/// stages are measured on the thread that runs them: allocations made by other
+/// threads meanwhile, including threads the stage spawns itself, aren't
+/// attributed to it. `allocated_bytes` is the process-wide total.
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+}
+
+impl TrackingAllocator<System> {
+    pub const fn system() -> Self {
+        TrackingAllocator { inner: System }
+    }
+}
+
+impl<A> TrackingAllocator<A> {
+    /// count the allocations made through `inner`
+    pub const fn new(inner: A) -> Self {
+        TrackingAllocator { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            count(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        count(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = self.inner.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            count(new_size as isize - layout.size() as isize);
+        }
+        new
+    }
+}
+
+/// live heap bytes counted by `TrackingAllocator`; always 0 if it isn't installed
+pub fn allocated_bytes() -> usize {
+    CURRENT.load(AtomicOrdering::Relaxed)
+}
+
+/// the change in heap bytes made by the current thread
+fn thread_allocated() -> isize {
+    THREAD_CURRENT.with(|c| c.get())
+}
+
+/// run `f`, returning the most heap it had allocated at once on this thread
+/// above what was live when it started. nested calls each see their own peak.
+pub(crate) fn measure_peak<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = thread_allocated();
+    // measure from here, restoring an enclosing measurement's peak after
+    let outer_peak = THREAD_PEAK.with(|p| p.replace(before));
+    let out = f();
+    let peak = THREAD_PEAK.with(|p| p.replace(p.get().max(outer_peak)));
+    (out, (peak - before).max(0) as usize)
+}
+
+/// the heap usage of one run of a stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageMemory {
+    pub name: String,
+    /// bytes still allocated when the stage returned, minus those freed; this
+    /// includes the stage's output and excludes its consumed input
+    pub delta: isize,
+    /// the most bytes the stage had allocated at once, above what was live
+    /// when it started
+    pub peak: usize,
+}
+
+/// records how much heap each tracked stage uses, as counted by
+/// `TrackingAllocator`. clones share the same record.
+#[derive(Clone, Default)]
+pub struct MemoryTracker {
+    usage: Rc<RefCell<Vec<StageMemory>>>,
+}
+
+impl MemoryTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// track the heap usage of `act` under `name`
+    pub fn stage<A>(&self, name: &str, act: A) -> MemoryTracked<A> {
+        MemoryTracked {
+            act,
+            name: name.to_string(),
+            usage: self.usage.clone(),
+        }
+    }
+
+    /// every tracked stage run so far, in the order they finished
+    pub fn usage(&self) -> Vec<StageMemory> {
+        self.usage.borrow().clone()
+    }
+}
+
+/// an act whose heap usage is recorded by a `MemoryTracker`. see
+/// `MemoryTracker::stage`.
+pub struct MemoryTracked<A> {
+    act: A,
+    name: String,
+    usage: Rc<RefCell<Vec<StageMemory>>>,
+}
+
+impl<A, I, O, E> Act<I, O, E> for MemoryTracked<A>
+where
+    A: Act<I, O, E>,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let before = thread_allocated();
+        let (out, peak) = measure_peak(|| self.act.act(input));
+        self.usage.borrow_mut().push(StageMemory {
+            name: self.name.clone(),
+            delta: thread_allocated() - before,
+            peak,
+        });
+        out
+    }
+}
+
+/// tracks every stage, naming each after its input and output types
+impl Layer for MemoryTracker {
+    fn wrap<A, I, O, E>(&self, act: A) -> impl Act<I, O, E> + use<A, I, O, E>
+    where
+        A: Act<I, O, E>,
+        E: Debug,
+    {
+        let name = format!(
+            "{}->{}",
+            short_type_name(type_name::<I>()),
+            short_type_name(type_name::<O>())
+        );
+        self.stage(&name, act)
+    }
+}
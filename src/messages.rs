@@ -0,0 +1,218 @@
+use super::*;
+
+/// message templates by key, for rendering failures in the user's language.
+/// templates refer to a failure's arguments as `{name}`; see
+/// `Failure::message_key` and `Failure::message_args`.
+pub trait MessageCatalog {
+    fn template(&self, key: &str) -> Option<&str>;
+}
+
+/// the messages `Display` uses, for keys a catalog doesn't cover
+fn english(key: &str) -> &'static str {
+    match key {
+        "invalid_input" => "Invalid input: {detail}",
+        "arithmetic_error" => "Arithmetic error: {detail}",
+        "unauthorized" => "Unauthorized: {detail}",
+        "custom" => "Custom error: {detail}",
+        "snapshot" => "{source} (in stage '{stage}' with input {input})",
+        "quota_time" => "Quota exceeded: stage '{stage}' took {used}, over its budget of {limit}",
+        "quota_memory" => {
+            "Quota exceeded: stage '{stage}' allocated {used} bytes, over its cap of {limit}"
+        }
+        "detailed" => "{source}{fields}",
+        "timeout" => "Timed out: stage '{stage}' ran past its limit of {limit}",
+        "cancelled" => "Cancelled: {detail}",
+        _ => "{detail}",
+    }
+}
+
+/// a catalog of templates for one language.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let german = Messages::parse(
+///     "# failures in german\n\
+///      invalid_input = Ungültige Eingabe: {detail}\n\
+///      snapshot = {source} (in Schritt '{stage}')",
+/// );
+/// let failure = Failure::InvalidInput("kein Datum".into());
+/// assert_eq!(failure.localize(&german), "Ungültige Eingabe: kein Datum");
+///
+/// // keys the catalog lacks fall back to the english messages
+/// let failure = Failure::Custom("boom".into());
+/// assert_eq!(failure.localize(&german), failure.to_string());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Messages {
+    templates: HashMap<String, String>,
+}
+
+impl Messages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// set the template for `key`
+    pub fn add(mut self, key: &str, template: &str) -> Self {
+        self.templates.insert(key.to_string(), template.to_string());
+        self
+    }
+
+    /// read `key = template` lines; blank lines and lines starting with `#`
+    /// are skipped
+    pub fn parse(text: &str) -> Self {
+        let mut messages = Messages::new();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, template)) = line.split_once('=') {
+                messages = messages.add(key.trim(), template.trim());
+            }
+        }
+        messages
+    }
+}
+
+impl MessageCatalog for Messages {
+    fn template(&self, key: &str) -> Option<&str> {
+        self.templates.get(key).map(String::as_str)
+    }
+}
+
+impl MessageCatalog for HashMap<String, String> {
+    fn template(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+/// fill in `{name}`s from `args`, leaving unknown ones as they are
+fn render(template: &str, args: &[(String, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let arg = after.find('}').and_then(|end| {
+            let name = &after[..end];
+            args.iter().find(|(k, _)| k == name).map(|(_, v)| (v, end))
+        });
+        match arg {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+impl Failure {
+    /// a stable name for the kind of failure, for looking up its message
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            Failure::InvalidInput(_) => "invalid_input",
+            Failure::ArithmeticError(_) => "arithmetic_error",
+            Failure::Unauthorized(_) => "unauthorized",
+            Failure::Custom(_) => "custom",
+            Failure::Snapshot(_) => "snapshot",
+            Failure::QuotaExceeded(q) => match q.resource {
+                QuotaResource::Time { .. } => "quota_time",
+                QuotaResource::Memory { .. } => "quota_memory",
+            },
+            Failure::Detailed(_) => "detailed",
+            Failure::Timeout(_) => "timeout",
+            Failure::Cancelled(_) => "cancelled",
+        }
+    }
+
+    /// the values a message for this failure can refer to. a wrapped failure
+    /// is passed as `source`, rendered in english; `localize` renders it with
+    /// the catalog instead.
+    pub fn message_args(&self) -> Vec<(String, String)> {
+        self.args_with(&|source: &Failure| source.to_string())
+    }
+
+    fn args_with(&self, source: &dyn Fn(&Failure) -> String) -> Vec<(String, String)> {
+        let arg = |k: &str, v: String| (k.to_string(), v);
+        match self {
+            Failure::InvalidInput(s)
+            | Failure::ArithmeticError(s)
+            | Failure::Unauthorized(s)
+            | Failure::Custom(s)
+            | Failure::Cancelled(s) => vec![arg("detail", s.clone())],
+            Failure::Snapshot(s) => vec![
+                arg("source", source(&s.source)),
+                arg("stage", s.stage.clone()),
+                arg("input", s.input.clone()),
+            ],
+            Failure::QuotaExceeded(q) => {
+                let (used, limit) = match q.resource {
+                    QuotaResource::Time { limit, used } => {
+                        (format!("{:?}", used), format!("{:?}", limit))
+                    }
+                    QuotaResource::Memory { limit, used } => (used.to_string(), limit.to_string()),
+                };
+                vec![
+                    arg("stage", q.stage.clone()),
+                    arg("used", used),
+                    arg("limit", limit),
+                ]
+            }
+            Failure::Timeout(t) => vec![
+                arg("stage", t.stage.clone()),
+                arg("limit", format!("{:?}", t.limit)),
+            ],
+            Failure::Detailed(d) => {
+                let fields: Vec<_> = d
+                    .fields
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect();
+                let mut args = vec![
+                    arg("source", source(&d.source)),
+                    arg(
+                        "fields",
+                        if fields.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" [{}]", fields.join(", "))
+                        },
+                    ),
+                ];
+                args.extend(d.fields.iter().cloned());
+                args
+            }
+        }
+    }
+
+    /// render this failure with the templates in `catalog`, falling back to
+    /// the english message for keys it doesn't have. fields attached with
+    /// `with_field` can be used by name in the messages of the failures they
+    /// wrap.
+    pub fn localize<C: MessageCatalog + ?Sized>(&self, catalog: &C) -> String {
+        self.localize_with(catalog, &[])
+    }
+
+    fn localize_with<C: MessageCatalog + ?Sized>(
+        &self,
+        catalog: &C,
+        fields: &[(String, String)],
+    ) -> String {
+        let mut inherited = fields.to_vec();
+        if let Failure::Detailed(d) = self {
+            inherited.extend(d.fields.iter().cloned());
+        }
+        let mut args =
+            self.args_with(&|source: &Failure| source.localize_with(catalog, &inherited));
+        args.extend(fields.iter().cloned());
+        let key = self.message_key();
+        render(catalog.template(key).unwrap_or_else(|| english(key)), &args)
+    }
+}
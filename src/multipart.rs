@@ -0,0 +1,422 @@
+use super::*;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+/// where the contents of a part ended up.
+#[derive(Debug)]
+pub enum PartData {
+    Memory(Vec<u8>),
+    /// spilled to a temporary file, which the caller now owns
+    File {
+        path: PathBuf,
+        size: u64,
+    },
+}
+
+/// one part of a multipart body, with the metadata from its headers.
+#[derive(Debug)]
+pub struct Part {
+    /// the form field name from `content-disposition`
+    pub name: Option<String>,
+    /// the uploaded file name from `content-disposition`
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub data: PartData,
+}
+
+impl Part {
+    pub fn size(&self) -> u64 {
+        match &self.data {
+            PartData::Memory(bytes) => bytes.len() as u64,
+            PartData::File { size, .. } => *size,
+        }
+    }
+
+    /// the contents, if they were kept in memory
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.data {
+            PartData::Memory(bytes) => Some(bytes),
+            PartData::File { .. } => None,
+        }
+    }
+
+    /// the contents as text, if they were kept in memory and are utf-8
+    pub fn text(&self) -> Option<&str> {
+        self.bytes().and_then(|b| std::str::from_utf8(b).ok())
+    }
+}
+
+/// limits for multipart parsing.
+#[derive(Debug, Clone)]
+pub struct MultipartOptions {
+    /// parts larger than this are streamed to a file in `temp_dir`
+    pub memory_limit: usize,
+    pub temp_dir: PathBuf,
+    /// the largest header block accepted for a single part
+    pub max_header_size: usize,
+}
+
+impl Default for MultipartOptions {
+    fn default() -> Self {
+        MultipartOptions {
+            memory_limit: 64 * 1024,
+            temp_dir: std::env::temp_dir(),
+            max_header_size: 16 * 1024,
+        }
+    }
+}
+
+fn malformed(msg: &str) -> Failure {
+    Failure::InvalidInput(format!("malformed multipart body: {}", msg))
+}
+
+fn io_failure(e: std::io::Error) -> Failure {
+    Failure::Custom(format!("multipart i/o error: {}", e))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+struct Buffered<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> Buffered<R> {
+    /// read more input, returning false at the end of it
+    fn fill(&mut self) -> Out<bool> {
+        let mut chunk = [0u8; 8192];
+        let n = self.reader.read(&mut chunk).map_err(io_failure)?;
+        self.buf.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+
+    fn ensure(&mut self, n: usize) -> Out<()> {
+        while self.buf.len() < n {
+            if !self.fill()? {
+                return Err(malformed("unexpected end of input"));
+            }
+        }
+        Ok(())
+    }
+}
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// a new file in `dir` that nobody else had: `create_new` refuses to follow a
+/// symlink or reuse a file planted under the name, so a taken name is skipped
+fn create_temp(dir: &Path) -> Out<(File, PathBuf)> {
+    loop {
+        let path = dir.join(format!(
+            "chain_reaction-multipart-{}-{}",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, AtomicOrdering::Relaxed)
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(io_failure(e)),
+        }
+    }
+}
+
+/// collects a part's contents in memory, moving to a temp file once they
+/// outgrow the memory limit
+struct PartSink<'a> {
+    options: &'a MultipartOptions,
+    memory: Vec<u8>,
+    file: Option<(File, PathBuf)>,
+    size: u64,
+}
+
+impl PartSink<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Out<()> {
+        self.size += bytes.len() as u64;
+        if self.file.is_none() && self.memory.len() + bytes.len() > self.options.memory_limit {
+            let (mut file, path) = create_temp(&self.options.temp_dir)?;
+            file.write_all(&mem::take(&mut self.memory))
+                .map_err(io_failure)?;
+            self.file = Some((file, path));
+        }
+        match &mut self.file {
+            Some((file, _)) => file.write_all(bytes).map_err(io_failure),
+            None => {
+                self.memory.extend_from_slice(bytes);
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(mut self) -> Out<PartData> {
+        match self.file.take() {
+            Some((file, path)) => {
+                if let Err(e) = file.sync_all() {
+                    let _ = fs::remove_file(&path);
+                    return Err(io_failure(e));
+                }
+                Ok(PartData::File {
+                    path,
+                    size: self.size,
+                })
+            }
+            None => Ok(PartData::Memory(mem::take(&mut self.memory))),
+        }
+    }
+}
+
+/// a part that didn't finish doesn't leave its temp file behind
+impl Drop for PartSink<'_> {
+    fn drop(&mut self) {
+        if let Some((_, path)) = self.file.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn header_param(value: &str, param: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|p| {
+        let (k, v) = p.trim().split_once('=')?;
+        k.trim()
+            .eq_ignore_ascii_case(param)
+            .then(|| v.trim().trim_matches('"').to_string())
+    })
+}
+
+/// the boundary declared by a `multipart/*` content type
+pub fn boundary_of(content_type: &str) -> Option<String> {
+    let (mime, _) = content_type.split_once(';')?;
+    if !mime.trim().to_ascii_lowercase().starts_with("multipart/") {
+        return None;
+    }
+    header_param(content_type, "boundary")
+}
+
+/// parse a multipart body from `reader` without holding it in memory as a
+/// whole: only the current chunk and parts under the memory limit are kept,
+/// larger parts are written to temporary files as they stream in. if the body
+/// turns out to be malformed, the temporary files written for it are removed.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let body = "--xyz\r\n\
+///     content-disposition: form-data; name=\"title\"\r\n\r\n\
+///     hello\r\n\
+///     --xyz\r\n\
+///     content-disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+///     content-type: text/plain\r\n\r\n\
+///     0123456789abcdef\r\n\
+///     --xyz--\r\n";
+/// let options = MultipartOptions {
+///     memory_limit: 8,
+///     ..MultipartOptions::default()
+/// };
+/// let parts = parse_multipart(body.as_bytes(), "xyz", &options).unwrap();
+/// assert_eq!(parts[0].name.as_deref(), Some("title"));
+/// assert_eq!(parts[0].text(), Some("hello"));
+/// assert_eq!(parts[1].filename.as_deref(), Some("a.txt"));
+/// assert_eq!(parts[1].size(), 16);
+/// // the upload went over the memory limit, so it was spilled to a file
+/// let PartData::File { path, .. } = &parts[1].data else { panic!() };
+/// assert_eq!(std::fs::read_to_string(path).unwrap(), "0123456789abcdef");
+/// std::fs::remove_file(path).unwrap();
+///
+/// // cut off in the middle of the second part, with both parts spilled:
+/// // nothing is left on disk
+/// let dir = std::env::temp_dir().join("chain_reaction-multipart-doc");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let options = MultipartOptions {
+///     memory_limit: 2,
+///     temp_dir: dir.clone(),
+///     ..options
+/// };
+/// assert!(parse_multipart(&body.as_bytes()[..body.len() - 20], "xyz", &options).is_err());
+/// assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+///
+/// assert!(parse_multipart("no boundary here".as_bytes(), "xyz", &options).is_err());
+///
+/// // temp file names planted in advance as symlinks are skipped, not followed
+/// # #[cfg(unix)] {
+/// let victim = dir.join("victim");
+/// std::fs::write(&victim, "keep").unwrap();
+/// for n in 0..100 {
+///     let name = format!("chain_reaction-multipart-{}-{}", std::process::id(), n);
+///     std::os::unix::fs::symlink(&victim, dir.join(name)).unwrap();
+/// }
+/// let parts = parse_multipart(body.as_bytes(), "xyz", &options).unwrap();
+/// assert_eq!(std::fs::read_to_string(&victim).unwrap(), "keep");
+/// let PartData::File { path, .. } = &parts[1].data else { panic!() };
+/// assert_eq!(std::fs::read_to_string(path).unwrap(), "0123456789abcdef");
+/// # }
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn parse_multipart<R: Read>(
+    reader: R,
+    boundary: &str,
+    options: &MultipartOptions,
+) -> Out<Vec<Part>> {
+    let mut parts = Vec::new();
+    match parse_parts(reader, boundary, options, &mut parts) {
+        Ok(()) => Ok(parts),
+        Err(e) => {
+            for part in parts {
+                if let PartData::File { path, .. } = part.data {
+                    let _ = fs::remove_file(path);
+                }
+            }
+            Err(e)
+        }
+    }
+}
+
+fn parse_parts<R: Read>(
+    reader: R,
+    boundary: &str,
+    options: &MultipartOptions,
+    parts: &mut Vec<Part>,
+) -> Out<()> {
+    let dash_boundary = format!("--{}", boundary).into_bytes();
+    let delimiter = [b"\r\n".as_slice(), &dash_boundary].concat();
+    let mut input = Buffered {
+        reader,
+        buf: Vec::new(),
+    };
+
+    // skip the preamble
+    loop {
+        if let Some(i) = find(&input.buf, &dash_boundary) {
+            input.buf.drain(..i + dash_boundary.len());
+            break;
+        }
+        let keep = dash_boundary.len();
+        if input.buf.len() > keep {
+            input.buf.drain(..input.buf.len() - keep);
+        }
+        if !input.fill()? {
+            return Err(malformed("no opening boundary"));
+        }
+    }
+
+    loop {
+        input.ensure(2)?;
+        if input.buf.starts_with(b"--") {
+            return Ok(());
+        }
+        if !input.buf.starts_with(b"\r\n") {
+            return Err(malformed("boundary not followed by a line break"));
+        }
+        input.buf.drain(..2);
+
+        let mut headers = Vec::new();
+        loop {
+            if input.buf.starts_with(b"\r\n") {
+                input.buf.drain(..2);
+                break;
+            }
+            if let Some(i) = find(&input.buf, b"\r\n\r\n") {
+                let block = String::from_utf8_lossy(&input.buf[..i]).into_owned();
+                for line in block.split("\r\n") {
+                    let (name, value) = line
+                        .split_once(':')
+                        .ok_or_else(|| malformed("invalid part header"))?;
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+                input.buf.drain(..i + 4);
+                break;
+            }
+            if input.buf.len() > options.max_header_size {
+                return Err(malformed("part headers too large"));
+            }
+            if !input.fill()? {
+                return Err(malformed("unexpected end of part headers"));
+            }
+        }
+
+        let mut sink = PartSink {
+            options,
+            memory: Vec::new(),
+            file: None,
+            size: 0,
+        };
+        loop {
+            if let Some(i) = find(&input.buf, &delimiter) {
+                sink.write(&input.buf[..i])?;
+                input.buf.drain(..i + delimiter.len());
+                break;
+            }
+            // hold back enough bytes to catch a delimiter split across reads
+            let keep = delimiter.len() - 1;
+            if input.buf.len() > keep {
+                let n = input.buf.len() - keep;
+                sink.write(&input.buf[..n])?;
+                input.buf.drain(..n);
+            }
+            if !input.fill()? {
+                return Err(malformed("unexpected end of part"));
+            }
+        }
+
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+        };
+        let disposition = header("content-disposition");
+        parts.push(Part {
+            name: disposition.as_deref().and_then(|d| header_param(d, "name")),
+            filename: disposition
+                .as_deref()
+                .and_then(|d| header_param(d, "filename")),
+            content_type: header("content-type"),
+            data: sink.finish()?,
+            headers,
+        });
+    }
+}
+
+/// split a multipart request body into its parts, spilling large ones to disk.
+/// a `Request` holds its whole body already; servers that can hand over the
+/// body as a reader should use `multipart_stream` instead, so uploads never
+/// sit in memory as a whole.
+pub fn multipart(options: MultipartOptions) -> impl Fn(Request) -> Out<Vec<Part>> {
+    move |req| {
+        let boundary = req
+            .header("content-type")
+            .and_then(boundary_of)
+            .ok_or_else(|| Failure::InvalidInput("not a multipart request".to_string()))?;
+        parse_multipart(req.body.as_slice(), &boundary, &options)
+    }
+}
+
+/// like `multipart`, for a body that is still being read: the act takes the
+/// request's content type and a reader over its body, e.g. the connection, and
+/// streams large parts straight to disk.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let body = "--b\r\ncontent-disposition: form-data; name=\"n\"\r\n\r\n42\r\n--b--";
+/// let parts = Reactor::input(("multipart/form-data; boundary=b".to_string(), body.as_bytes()))
+///     .then(multipart_stream(MultipartOptions::default()))
+///     .run()
+///     .unwrap();
+/// assert_eq!(parts[0].text(), Some("42"));
+/// ```
+pub fn multipart_stream<R: Read>(
+    options: MultipartOptions,
+) -> impl Fn((String, R)) -> Out<Vec<Part>> {
+    move |(content_type, body)| {
+        let boundary = boundary_of(&content_type)
+            .ok_or_else(|| Failure::InvalidInput("not a multipart request".to_string()))?;
+        parse_multipart(body, &boundary, &options)
+    }
+}
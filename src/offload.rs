@@ -0,0 +1,97 @@
+use super::*;
+
+/// a backend that processes whole batches at once, typically on a gpu or
+/// other accelerator driven by the caller's own code (wgpu, cuda bindings...).
+/// closures taking and returning a `Vec` implement it and are always available.
+pub trait Offload<T, O, E = Failure>
+where
+    E: Debug,
+{
+    /// false when the device is missing or busy; batches then run on the cpu
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// process a batch, returning exactly one output per item, in order
+    fn run_batch(&self, batch: Vec<T>) -> Out<Vec<O>, E>;
+}
+
+impl<F, T, O, E> Offload<T, O, E> for F
+where
+    F: Fn(Vec<T>) -> Out<Vec<O>, E>,
+    E: Debug,
+{
+    fn run_batch(&self, batch: Vec<T>) -> Out<Vec<O>, E> {
+        self(batch)
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// like `for_each`, but the items go to `backend` in batches of
+    /// `batch_size`. whenever the backend reports itself unavailable, that
+    /// batch runs item by item through `fallback` on the cpu instead.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// struct Device;
+    ///
+    /// impl Offload<f32, f32> for Device {
+    ///     fn is_available(&self) -> bool {
+    ///         false // no accelerator in this environment
+    ///     }
+    ///
+    ///     fn run_batch(&self, batch: Vec<f32>) -> Out<Vec<f32>> {
+    ///         unreachable!("would upload the batch and run a kernel")
+    ///     }
+    /// }
+    ///
+    /// let out = Reactor::<Vec<f32>>::input(vec![1.0, 2.0, 3.0])
+    ///     .for_each_offloaded(&Device, 2, |x: f32| Ok(x * 2.0))
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(out, vec![2.0, 4.0, 6.0]);
+    /// ```
+    pub fn for_each_offloaded<O, B, T>(
+        self,
+        backend: &B,
+        batch_size: usize,
+        fallback: T,
+    ) -> Reactor<Vec<O>, E>
+    where
+        I: IntoIterator,
+        B: Offload<I::Item, O, E>,
+        T: Act<I::Item, O, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let mut items = i.into_iter().peekable();
+                let mut out = Vec::new();
+                while items.peek().is_some() {
+                    let batch: Vec<_> = items.by_ref().take(batch_size.max(1)).collect();
+                    if !backend.is_available() {
+                        for item in batch {
+                            out.push(fallback.act(item)?);
+                        }
+                        continue;
+                    }
+                    let len = batch.len();
+                    let results = backend.run_batch(batch)?;
+                    if results.len() != len {
+                        return Err(Failure::Custom(format!(
+                            "offload backend returned {} results for a batch of {}",
+                            results.len(),
+                            len
+                        ))
+                        .into());
+                    }
+                    out.extend(results);
+                }
+                Ok(out)
+            }),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use super::*;
+use std::sync::Mutex;
+use std::thread;
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// like `for_each`, but the items are shared out between one thread per
+    /// available core. the outputs keep the input order. the first failure
+    /// cancels the other threads, which stop after the item they are on.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let squares = Reactor::<Vec<u64>>::input((1..=1000).collect())
+    ///     .par_for_each(|x: u64| Ok(x * x))
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(squares[999], 1_000_000);
+    ///
+    /// let out = Reactor::<Vec<u64>>::input((1..=1000).collect())
+    ///     .par_for_each(|x: u64| match x {
+    ///         500 => Err(Failure::InvalidInput("500".into())),
+    ///         x => Ok(x),
+    ///     })
+    ///     .run();
+    /// assert!(out.is_err());
+    /// ```
+    pub fn par_for_each<O, T>(self, transform: T) -> Reactor<Vec<O>, E>
+    where
+        I: IntoIterator,
+        I::IntoIter: Send,
+        I::Item: Send,
+        O: Send,
+        E: Send,
+        T: Act<I::Item, O, E> + Sync,
+    {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        self.par_for_each_with(threads, transform)
+    }
+
+    /// like `par_for_each`, on `threads` threads
+    pub fn par_for_each_with<O, T>(self, threads: usize, transform: T) -> Reactor<Vec<O>, E>
+    where
+        I: IntoIterator,
+        I::IntoIter: Send,
+        I::Item: Send,
+        O: Send,
+        E: Send,
+        T: Act<I::Item, O, E> + Sync,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let items = Mutex::new(i.into_iter().enumerate());
+                let (items, transform) = (&items, &transform);
+                let mut out: Vec<(usize, O)> = task_scope(|scope| {
+                    let workers: Vec<_> = (0..threads.max(1))
+                        .map(|_| {
+                            scope.spawn(move || {
+                                let mut done = Vec::new();
+                                while !is_cancelled() {
+                                    let next = items.lock().expect("item lock poisoned").next();
+                                    let Some((n, item)) = next else {
+                                        break;
+                                    };
+                                    done.push((n, transform.act(item)?));
+                                }
+                                Ok(done)
+                            })
+                        })
+                        .collect();
+                    let mut out = Vec::new();
+                    for worker in workers {
+                        out.extend(worker.join()?);
+                    }
+                    Ok(out)
+                })?;
+                out.sort_unstable_by_key(|(n, _)| *n);
+                Ok(out.into_iter().map(|(_, o)| o).collect())
+            }),
+        }
+    }
+}
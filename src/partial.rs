@@ -0,0 +1,121 @@
+use super::*;
+
+/// the result of running a stage in partial-success mode: items that made it
+/// through, and the failures of the ones that didn't. both sides are tagged with
+/// the position the item came from (the line number for line sources, the
+/// index for collections), so failures can be reported against the input.
+#[derive(Debug)]
+pub struct Partial<T, E = Failure> {
+    pub items: Vec<(usize, T)>,
+    pub failures: Vec<(usize, E)>,
+}
+
+impl<T, E> Partial<T, E> {
+    pub fn new() -> Self {
+        Partial {
+            items: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// true when no item failed
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// the successful values, without their positions
+    pub fn values(self) -> Vec<T> {
+        self.items.into_iter().map(|(_, v)| v).collect()
+    }
+}
+
+impl<T, E> Default for Partial<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// like `for_each`, but a failing item doesn't stop the chain; it's recorded
+    /// in the returned `Partial` along with its index and the rest keep going.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let out = Reactor::<Vec<i32>>::input(vec![4, 0, 2])
+    ///     .for_each_partial(|n: i32| match n {
+    ///         0 => Err(Failure::ArithmeticError("division by zero".into())),
+    ///         n => Ok(100 / n),
+    ///     })
+    ///     .then_each(|n: i32| Ok(n + 1))
+    ///     .run()
+    ///     .unwrap();
+    /// assert!(!out.is_complete());
+    /// assert_eq!(out.items, [(0, 26), (2, 51)]);
+    /// assert_eq!(out.failures[0].0, 1);
+    /// ```
+    pub fn for_each_partial<O, T>(self, transform: T) -> Reactor<Partial<O, E>, E>
+    where
+        I: IntoIterator,
+        T: Act<I::Item, O, E>,
+    {
+        Reactor {
+            input: self.input.map(|i| {
+                let mut out = Partial::new();
+                for (n, item) in i.into_iter().enumerate() {
+                    match transform.act(item) {
+                        Ok(o) => out.items.push((n, o)),
+                        Err(e) => out.failures.push((n, e)),
+                    }
+                }
+                out
+            }),
+        }
+    }
+}
+
+impl<T, E> Reactor<Partial<T, E>, E>
+where
+    E: Debug,
+{
+    /// run `transform` on every surviving item, moving the ones that fail over to
+    /// the failures while keeping their original positions.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let out = Reactor::<Vec<&str>>::input(vec!["1", "-2", "x"])
+    ///     .for_each_partial(|s: &str| Ok(s.parse::<i32>()?))
+    ///     .then_each(|n: i32| match n {
+    ///         n if n < 0 => Err(Failure::InvalidInput(format!("{} is negative", n))),
+    ///         n => Ok(n),
+    ///     })
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(out.values(), [1]);
+    /// ```
+    pub fn then_each<O, A>(self, transform: A) -> Reactor<Partial<O, E>, E>
+    where
+        A: Act<T, O, E>,
+    {
+        Reactor {
+            input: self.input.map(|p| {
+                let mut out = Partial {
+                    items: Vec::with_capacity(p.items.len()),
+                    failures: p.failures,
+                };
+                for (n, item) in p.items {
+                    match transform.act(item) {
+                        Ok(o) => out.items.push((n, o)),
+                        Err(e) => out.failures.push((n, e)),
+                    }
+                }
+                out.failures.sort_by_key(|(n, _)| *n);
+                out
+            }),
+        }
+    }
+}
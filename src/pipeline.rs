@@ -0,0 +1,366 @@
+use super::*;
+use crate::describe::{write_arrows, write_tree};
+use crate::resilience::thread_retries;
+use std::any::type_name;
+
+/// told about every stage that ran: its qualified name, how long it took and
+/// whether it succeeded
+type Observer<'a> = dyn FnMut(&str, Duration, bool) + 'a;
+
+type Run<I, O, E> = Box<dyn Fn(I, &mut Observer) -> Out<O, E>>;
+
+/// the name `Pipeline::then` gives a stage: its label, or else its types
+pub(crate) fn stage_name<I, O, E, T>(act: &T) -> String
+where
+    T: Act<I, O, E>,
+    E: Debug,
+{
+    match act.label() {
+        Some(label) => label.to_string(),
+        None => format!(
+            "{}->{}",
+            short_type_name(type_name::<I>()),
+            short_type_name(type_name::<O>())
+        ),
+    }
+}
+
+/// a named chain of acts built without an input, to be run on as many inputs
+/// as needed. a pipeline is itself an act, so it can be used as a stage of a
+/// reactor or another pipeline; nested with `nest`, its stages show up in
+/// reports as `child/stage`.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let clean = Pipeline::<String>::new("clean")
+///     .stage("trim", |s: String| Ok(s.trim().to_string()))
+///     .stage("lower", |s: String| Ok(s.to_lowercase()));
+/// let words = Pipeline::<String>::new("words")
+///     .nest(clean)
+///     .stage("count", |s: String| Ok(s.split_whitespace().count()));
+///
+/// assert_eq!(words.stages(), ["clean/trim", "clean/lower", "count"]);
+/// assert_eq!(words.run("  Hello World ".to_string()).unwrap(), 2);
+///
+/// let (out, report) = words.run_with_report("a b c".to_string());
+/// assert_eq!(out.unwrap(), 3);
+/// assert_eq!(report.stages[1].name, "clean/lower");
+/// ```
+pub struct Pipeline<I, O = I, E = Failure> {
+    name: String,
+    stages: Vec<String>,
+    /// the output type of each stage, for `Debug` and `Display`
+    types: Vec<&'static str>,
+    run: Run<I, O, E>,
+}
+
+impl<I, E> Pipeline<I, I, E>
+where
+    I: 'static,
+    E: Debug + 'static,
+{
+    /// an empty pipeline that passes its input through
+    pub fn new(name: &str) -> Self {
+        Pipeline {
+            name: name.to_string(),
+            stages: Vec::new(),
+            types: Vec::new(),
+            run: Box::new(|input, _| Ok(input)),
+        }
+    }
+}
+
+impl<I, O, E> Pipeline<I, O, E>
+where
+    I: 'static,
+    O: 'static,
+    E: Debug + 'static,
+{
+    /// append a named stage
+    pub fn stage<O2, T>(self, name: &str, act: T) -> Pipeline<I, O2, E>
+    where
+        O2: 'static,
+        T: Act<O, O2, E> + 'static,
+    {
+        let prev = self.run;
+        let stage = name.to_string();
+        let mut stages = self.stages;
+        stages.push(stage.clone());
+        let mut types = self.types;
+        types.push(type_name::<O2>());
+        Pipeline {
+            name: self.name,
+            stages,
+            types,
+            run: Box::new(move |input, observe| {
+                let o = prev(input, observe)?;
+                let start = Instant::now();
+                let out = act.act(o).map_err(|e| record_step(e, &stage));
+                observe(&stage, start.elapsed(), out.is_ok());
+                out
+            }),
+        }
+    }
+
+    /// append a stage named after its label, or else its input and output types
+    pub fn then<O2, T>(self, act: T) -> Pipeline<I, O2, E>
+    where
+        O2: 'static,
+        T: Act<O, O2, E> + 'static,
+    {
+        let name = stage_name(&act);
+        self.stage(&name, act)
+    }
+
+    /// append a named stage only when `enabled`
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let enrich = false;
+    /// let p = Pipeline::<i32>::new("p")
+    ///     .stage("double", |x: i32| Ok(x * 2))
+    ///     .stage_if(enrich, "enrich", |x: i32| Ok(x + 1));
+    /// assert_eq!(p.stages(), ["double"]);
+    /// assert_eq!(p.run(4).unwrap(), 8);
+    /// ```
+    pub fn stage_if<T>(self, enabled: bool, name: &str, act: T) -> Self
+    where
+        T: Act<O, O, E> + 'static,
+    {
+        self.stage_some(name, enabled.then_some(act))
+    }
+
+    /// append a named stage if there is one
+    pub fn stage_some<T>(self, name: &str, act: Option<T>) -> Self
+    where
+        T: Act<O, O, E> + 'static,
+    {
+        match act {
+            Some(act) => self.stage(name, act),
+            None => self,
+        }
+    }
+
+    /// append a stage named after its types only when `enabled`
+    pub fn then_if<T>(self, enabled: bool, act: T) -> Self
+    where
+        T: Act<O, O, E> + 'static,
+    {
+        self.then_some(enabled.then_some(act))
+    }
+
+    /// append a stage named after its types if there is one
+    pub fn then_some<T>(self, act: Option<T>) -> Self
+    where
+        T: Act<O, O, E> + 'static,
+    {
+        match act {
+            Some(act) => self.then(act),
+            None => self,
+        }
+    }
+
+    /// append another pipeline's stages, named `child/stage` after it
+    pub fn nest<O2>(self, child: Pipeline<O, O2, E>) -> Pipeline<I, O2, E>
+    where
+        O2: 'static,
+    {
+        let prev = self.run;
+        let child_name = child.name;
+        let child_run = child.run;
+        let mut stages = self.stages;
+        stages.extend(child.stages.iter().map(|s| format!("{}/{}", child_name, s)));
+        let mut types = self.types;
+        types.extend(child.types);
+        Pipeline {
+            name: self.name,
+            stages,
+            types,
+            run: Box::new(move |input, observe| {
+                let o = prev(input, observe)?;
+                child_run(o, &mut |stage: &str, took: Duration, ok: bool| {
+                    observe(&format!("{}/{}", child_name, stage), took, ok)
+                })
+            }),
+        }
+    }
+
+    /// append another pipeline's stages as if they had been added to this one,
+    /// keeping their names. for stitching independently owned segments
+    /// (ingest, transform, publish) into one pipeline.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let ingest = Pipeline::<String>::new("ingest")
+    ///     .stage("parse", |s: String| s.parse::<i64>().map_err(|e| Failure::InvalidInput(e.to_string())));
+    /// let publish = Pipeline::<i64>::new("publish").stage("render", |n: i64| Ok(format!("n={}", n)));
+    ///
+    /// let job = ingest.concat(publish);
+    /// assert_eq!(job.stages(), ["parse", "render"]);
+    /// assert_eq!(job.run("42".to_string()).unwrap(), "n=42");
+    /// ```
+    pub fn concat<O2>(self, next: Pipeline<O, O2, E>) -> Pipeline<I, O2, E>
+    where
+        O2: 'static,
+    {
+        let prev = self.run;
+        let next_run = next.run;
+        let mut stages = self.stages;
+        stages.extend(next.stages);
+        let mut types = self.types;
+        types.extend(next.types);
+        Pipeline {
+            name: self.name,
+            stages,
+            types,
+            run: Box::new(move |input, observe| next_run(prev(input, observe)?, observe)),
+        }
+    }
+
+    pub fn run(&self, input: I) -> Out<O, E> {
+        (self.run)(input, &mut |_: &str, _: Duration, _: bool| {})
+    }
+
+    /// run like `run`, along with a report of every stage
+    pub fn run_with_report(&self, input: I) -> (Out<O, E>, RunReport) {
+        let mut report = RunReport {
+            stages: self
+                .stages
+                .iter()
+                .map(|name| StageReport {
+                    name: name.clone(),
+                    items: 0,
+                    failures: 0,
+                    duration: Duration::ZERO,
+                    retries: 0,
+                })
+                .collect(),
+            failed_at: None,
+        };
+        // stages run once each and in order, so the n-th call is stage n
+        let mut next = 0;
+        let mut retries = thread_retries();
+        let out = (self.run)(input, &mut |_: &str, took: Duration, ok: bool| {
+            let stage = &mut report.stages[next];
+            stage.items += 1;
+            stage.failures += !ok as u64;
+            stage.duration += took;
+            stage.retries += thread_retries() - retries;
+            retries = thread_retries();
+            if !ok && report.failed_at.is_none() {
+                report.failed_at = Some(next);
+            }
+            next += 1;
+        });
+        (out, report)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the qualified names of the stages, in order
+    pub fn stages(&self) -> &[String] {
+        &self.stages
+    }
+}
+
+impl<I, O, E> Pipeline<I, O, E> {
+    /// the stages with their input and output types
+    fn stage_infos(&self) -> Vec<StageInfo> {
+        let inputs = std::iter::once(type_name::<I>()).chain(self.types.iter().copied());
+        self.stages
+            .iter()
+            .zip(inputs.zip(&self.types))
+            .enumerate()
+            .map(|(index, (name, (input_type, output_type)))| StageInfo {
+                index,
+                name: name.clone(),
+                input_type,
+                output_type,
+            })
+            .collect()
+    }
+}
+
+/// the pipeline as an arrow diagram: `String -> trim -> String -> count -> usize`
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let p = Pipeline::<String>::new("words")
+///     .stage("trim", |s: String| Ok(s.trim().to_string()))
+///     .stage("count", |s: String| Ok(s.split_whitespace().count()));
+/// assert_eq!(p.to_string(), "String -> trim -> String -> count -> usize");
+/// assert_eq!(
+///     format!("{:?}", p),
+///     "Pipeline words (String)\n  ├─ 0. trim: String -> String\n  └─ 1. count: String -> usize\n"
+/// );
+/// ```
+impl<I, O, E> std::fmt::Display for Pipeline<I, O, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write_arrows(f, type_name::<I>(), self.stage_infos().iter())
+    }
+}
+
+/// one line per stage with its types
+impl<I, O, E> Debug for Pipeline<I, O, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write_tree(
+            f,
+            &format!("Pipeline {}", self.name),
+            type_name::<I>(),
+            self.stage_infos().iter(),
+            |_| "  ",
+        )
+    }
+}
+
+impl<I, O, E> Act<I, O, E> for Pipeline<I, O, E>
+where
+    I: 'static,
+    O: 'static,
+    E: Debug + 'static,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        self.run(input)
+    }
+}
+
+/// an act of any type behind a box. see `ChainableAct::boxed`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::collections::HashMap;
+///
+/// let mut routes: HashMap<&str, BoxedAct<i32, String>> = HashMap::new();
+/// routes.insert("hex", (|x: i32| Ok(format!("{:x}", x))).boxed());
+/// routes.insert(
+///     "padded",
+///     Pipeline::<i32>::new("padded")
+///         .stage("double", |x: i32| Ok(x * 2))
+///         .stage("pad", |x: i32| Ok(format!("{:04}", x)))
+///         .boxed(),
+/// );
+/// assert_eq!(routes["hex"].act(255).unwrap(), "ff");
+/// assert_eq!(routes["padded"].act(21).unwrap(), "0042");
+/// ```
+pub struct BoxedAct<I, O, E = Failure> {
+    pub(crate) act: Box<dyn Act<I, O, E>>,
+}
+
+impl<I, O, E> Act<I, O, E> for BoxedAct<I, O, E>
+where
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        self.act.act(input)
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.act.label()
+    }
+}
@@ -0,0 +1,124 @@
+use super::*;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+
+#[cfg(unix)]
+mod sys {
+    use super::*;
+
+    pub const RTLD_NOW: c_int = 2;
+
+    extern "C" {
+        pub fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+        pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        pub fn dlerror() -> *mut c_char;
+    }
+}
+
+/// the version of this crate, which plugins have to match
+pub const PLUGIN_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// the function a plugin exports to register its acts; see `export_plugin!`
+pub type PluginRegister = fn(&mut ActRegistry);
+
+/// the version of this crate a plugin was built against; see `export_plugin!`
+pub type PluginVersion = fn() -> &'static str;
+
+/// export a registration function from a plugin, i.e. a `cdylib` crate
+/// depending on this one. the host loads it with `ActRegistry::load_plugin`.
+///
+/// ```rust,ignore
+/// use chain_reaction::*;
+///
+/// fn register(registry: &mut ActRegistry) {
+///     registry.register("shout", |s: String| Ok(s.to_uppercase()));
+/// }
+///
+/// export_plugin!(register);
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($register:path) => {
+        #[no_mangle]
+        pub fn chain_reaction_plugin_version() -> &'static str {
+            $crate::PLUGIN_VERSION
+        }
+
+        #[no_mangle]
+        pub fn chain_reaction_register(registry: &mut $crate::ActRegistry) {
+            $register(registry)
+        }
+    };
+}
+
+#[cfg(unix)]
+fn last_dl_error() -> String {
+    let e = unsafe { sys::dlerror() };
+    if e.is_null() {
+        "unknown error".to_string()
+    } else {
+        unsafe { CStr::from_ptr(e) }.to_string_lossy().into_owned()
+    }
+}
+
+impl ActRegistry<Failure> {
+    /// load a shared library exporting a registration function (see
+    /// `export_plugin!`) and let it register its acts here. acts are passed
+    /// across as rust values, so the plugin has to be built with the same
+    /// compiler and the same version of this crate as the host; plugins built
+    /// against another version are refused. a loaded library stays loaded
+    /// for the rest of the process, since its acts may be anywhere by then.
+    /// only supported on unix.
+    ///
+    /// ```rust,no_run
+    /// use chain_reaction::*;
+    ///
+    /// let mut registry = ActRegistry::new();
+    /// registry.load_plugin("target/release/libshout.so").unwrap();
+    /// let loud: String = registry.call("shout", "hi".to_string()).unwrap();
+    /// ```
+    pub fn load_plugin<P: AsRef<Path>>(&mut self, path: P) -> Out<&mut Self> {
+        let path = path.as_ref();
+        let failure = |e: String| Failure::Custom(format!("plugin {}: {}", path.display(), e));
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let name = CString::new(path.as_os_str().as_bytes())
+                .map_err(|_| failure("path contains a nul byte".to_string()))?;
+            let handle = unsafe { sys::dlopen(name.as_ptr(), sys::RTLD_NOW) };
+            if handle.is_null() {
+                return Err(failure(last_dl_error()));
+            }
+            let symbol = |name: &CStr| {
+                let f = unsafe { sys::dlsym(handle, name.as_ptr()) };
+                if f.is_null() {
+                    Err(failure(format!(
+                        "doesn't export {}; use export_plugin!",
+                        name.to_string_lossy()
+                    )))
+                } else {
+                    Ok(f)
+                }
+            };
+            let version = symbol(c"chain_reaction_plugin_version")?;
+            let version = unsafe { std::mem::transmute::<*mut c_void, PluginVersion>(version) }();
+            if version != PLUGIN_VERSION {
+                return Err(failure(format!(
+                    "built against chain_reaction {}, but this is {}",
+                    version, PLUGIN_VERSION
+                )));
+            }
+            let register = symbol(c"chain_reaction_register")?;
+            let register = unsafe { std::mem::transmute::<*mut c_void, PluginRegister>(register) };
+            register(self);
+            Ok(self)
+        }
+        #[cfg(not(unix))]
+        {
+            Err(failure(
+                "loading plugins is only supported on unix".to_string(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use super::*;
+use std::fs;
+use std::path::Path;
+
+/// the resilience settings of one stage. the defaults leave a stage alone: no
+/// retries, timeout, rate limit or circuit breaker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagePolicy {
+    pub retry: RetryPolicy,
+    /// fail the stage if it took longer than this. checked when the stage
+    /// returns, like `Quota::time_limit`.
+    pub timeout: Option<Duration>,
+    /// at most this many calls per interval
+    pub rate_limit: Option<(u32, Duration)>,
+    /// consecutive failures (after retries) that open the circuit, and for how long
+    pub circuit_breaker: Option<(u32, Duration)>,
+}
+
+impl Default for StagePolicy {
+    fn default() -> Self {
+        StagePolicy {
+            retry: RetryPolicy::new(0),
+            timeout: None,
+            rate_limit: None,
+            circuit_breaker: None,
+        }
+    }
+}
+
+/// the act built by `StagePolicy::apply`: a circuit breaker around retries
+/// around the rate-limited, timed stage.
+pub type PolicyAct<A> = CircuitBreaker<Retry<RateLimit<Quota<A>>>>;
+
+impl StagePolicy {
+    /// wrap `act`, named `stage` in failures, in the wrappers this policy asks
+    /// for. settings that are off use wrappers that never trigger.
+    pub fn apply<A>(&self, stage: &str, act: A) -> PolicyAct<A> {
+        let mut timed = quota(stage, act);
+        if let Some(timeout) = self.timeout {
+            timed = timed.time_limit(timeout);
+        }
+        let (limit, per) = self
+            .rate_limit
+            .unwrap_or((u32::MAX, Duration::from_secs(1)));
+        let (threshold, open_for) = self.circuit_breaker.unwrap_or((u32::MAX, Duration::ZERO));
+        circuit_breaker(
+            retry_with(rate_limit(timed, limit, per), self.retry),
+            threshold,
+            open_for,
+        )
+    }
+}
+
+/// `250ms`, `2s` or `5m`
+fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (number, unit) = s.split_at(s.find(|c: char| c.is_alphabetic())?);
+    let number: u64 = number.trim().parse().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(number)),
+        "s" => Some(Duration::from_secs(number)),
+        "m" => Some(Duration::from_secs(number * 60)),
+        _ => None,
+    }
+}
+
+/// `10/1s`: a count per duration
+fn parse_per(s: &str) -> Option<(u32, Duration)> {
+    let (count, per) = s.split_once('/')?;
+    Some((count.trim().parse().ok()?, parse_duration(per)?))
+}
+
+/// resilience policies for stages by name, so operators can tune retries,
+/// timeouts, rate limits and circuit breakers in a file instead of the code.
+/// stages without a policy of their own get the `[*]` one, if any.
+///
+/// ```text
+/// [*]
+/// timeout = 5s
+///
+/// [fetch]
+/// retries = 3
+/// backoff = 200ms
+/// max_delay = 5s
+/// rate_limit = 10/1s
+/// circuit_breaker = 5/30s
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResiliencePolicies {
+    default: Option<StagePolicy>,
+    stages: HashMap<String, StagePolicy>,
+}
+
+impl ResiliencePolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// set the policy of the stage named `stage`, or of every other stage for `*`
+    pub fn stage(mut self, stage: &str, policy: StagePolicy) -> Self {
+        if stage == "*" {
+            self.default = Some(policy);
+        } else {
+            self.stages.insert(stage.to_string(), policy);
+        }
+        self
+    }
+
+    /// the policy for the stage named `stage`
+    pub fn get(&self, stage: &str) -> StagePolicy {
+        self.stages
+            .get(stage)
+            .or(self.default.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// read policies in the format shown above
+    pub fn parse(text: &str) -> Out<Self> {
+        let mut policies = ResiliencePolicies::new();
+        let mut current: Option<(String, StagePolicy)> = None;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid =
+                |what: &str| Failure::InvalidInput(format!("line {}: {}: '{}'", n + 1, what, line));
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if let Some((stage, policy)) = current.take() {
+                    policies = policies.stage(&stage, policy);
+                }
+                current = Some((name.trim().to_string(), StagePolicy::default()));
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `key = value`"))?;
+            let (_, policy) = current
+                .as_mut()
+                .ok_or_else(|| invalid("setting outside of a [stage] section"))?;
+            let value = value.trim();
+            match key.trim() {
+                "retries" => {
+                    policy.retry.max_retries =
+                        value.parse().map_err(|_| invalid("bad retry count"))?
+                }
+                "backoff" => {
+                    policy.retry.backoff =
+                        parse_duration(value).ok_or_else(|| invalid("bad duration"))?
+                }
+                "max_delay" => {
+                    policy.retry.max_delay =
+                        parse_duration(value).ok_or_else(|| invalid("bad duration"))?
+                }
+                "timeout" => {
+                    policy.timeout =
+                        Some(parse_duration(value).ok_or_else(|| invalid("bad duration"))?)
+                }
+                "rate_limit" => {
+                    policy.rate_limit = Some(
+                        parse_per(value)
+                            .filter(|(limit, _)| *limit > 0)
+                            .ok_or_else(|| invalid("expected calls/duration"))?,
+                    )
+                }
+                "circuit_breaker" => {
+                    policy.circuit_breaker = Some(
+                        parse_per(value).ok_or_else(|| invalid("expected failures/duration"))?,
+                    )
+                }
+                _ => return Err(invalid("unknown setting")),
+            }
+        }
+        if let Some((stage, policy)) = current {
+            policies = policies.stage(&stage, policy);
+        }
+        Ok(policies)
+    }
+
+    /// read policies from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Out<Self> {
+        let text = fs::read_to_string(path.as_ref())
+            .map_err(|e| Failure::Custom(format!("policies {}: {}", path.as_ref().display(), e)))?;
+        Self::parse(&text)
+    }
+}
+
+/// a pipeline whose stages get their resilience policy by name. see
+/// `Pipeline::with_policies`.
+pub struct PolicyPipeline<I, O = I, E = Failure> {
+    pipeline: Pipeline<I, O, E>,
+    policies: ResiliencePolicies,
+}
+
+impl<I, O, E> Pipeline<I, O, E>
+where
+    I: 'static,
+    O: 'static,
+    E: Debug + 'static,
+{
+    /// wrap every stage added by name from here on according to its policy in
+    /// `policies`. retrying needs the stage's input again, so those inputs
+    /// have to be `Clone`.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    ///
+    /// let policies = ResiliencePolicies::parse("[flaky]\nretries = 2\nbackoff = 1ms").unwrap();
+    /// let calls = Rc::new(Cell::new(0));
+    /// let counter = calls.clone();
+    ///
+    /// let p = Pipeline::<i32>::new("p")
+    ///     .with_policies(policies)
+    ///     .stage("flaky", move |x: i32| {
+    ///         let calls = &counter;
+    ///         calls.set(calls.get() + 1);
+    ///         if calls.get() < 3 { Err(Failure::Custom("busy".into())) } else { Ok(x) }
+    ///     })
+    ///     .into_pipeline();
+    /// assert_eq!(p.run(7).unwrap(), 7);
+    /// assert_eq!(calls.get(), 3);
+    /// ```
+    pub fn with_policies(self, policies: ResiliencePolicies) -> PolicyPipeline<I, O, E> {
+        PolicyPipeline {
+            pipeline: self,
+            policies,
+        }
+    }
+}
+
+impl<I, O, E> PolicyPipeline<I, O, E>
+where
+    I: 'static,
+    O: Clone + 'static,
+    E: Debug + From<Failure> + 'static,
+{
+    /// append a named stage, wrapped according to its policy
+    pub fn stage<O2, T>(self, name: &str, act: T) -> PolicyPipeline<I, O2, E>
+    where
+        O2: 'static,
+        T: Act<O, O2, E> + 'static,
+    {
+        let act = self.policies.get(name).apply(name, act);
+        PolicyPipeline {
+            pipeline: self.pipeline.stage(name, act),
+            policies: self.policies,
+        }
+    }
+
+    /// stop applying policies and continue with a plain pipeline
+    pub fn into_pipeline(self) -> Pipeline<I, O, E> {
+        self.pipeline
+    }
+}
@@ -0,0 +1,140 @@
+use super::*;
+use std::any::Any;
+
+/// higher runs first. runs of equal priority go in submission order.
+pub type Priority = i32;
+
+/// a queued run that can advance one stage at a time
+trait ScheduledRun {
+    /// run the next stage; true once there are none left
+    fn step(&mut self) -> bool;
+    /// hand the result to the run's callback
+    fn complete(self: Box<Self>);
+}
+
+struct Run<O, E, F> {
+    runner: DebugRunner<O, E>,
+    on_done: F,
+}
+
+impl<O, E, F> ScheduledRun for Run<O, E, F>
+where
+    O: Any,
+    E: Debug,
+    F: FnOnce(Out<O, E>),
+{
+    fn step(&mut self) -> bool {
+        matches!(self.runner.step(), Step::Done | Step::Failed(_))
+    }
+
+    fn complete(self: Box<Self>) {
+        (self.on_done)(self.runner.finish())
+    }
+}
+
+struct Queued<'a> {
+    priority: Priority,
+    seq: u64,
+    run: Box<dyn ScheduledRun + 'a>,
+}
+
+impl PartialEq for Queued<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued<'_> {}
+
+impl PartialOrd for Queued<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// interleaves queued pipeline runs stage by stage, always advancing the
+/// highest-priority run. the boundaries between stages are preemption points:
+/// an urgent run submitted while a bulk run is in progress goes next, and the
+/// bulk run picks up where it left off once nothing more urgent is waiting.
+/// runs are `DebugRunner`s, since those can be advanced one stage at a time.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::cell::RefCell;
+///
+/// let order = RefCell::new(Vec::new());
+/// let mut scheduler = PriorityScheduler::new();
+/// let backfill = DebugRunner::<i32>::input(1)
+///     .stage("load", |x: i32| Ok(x))
+///     .stage("transform", |x: i32| Ok(x + 1));
+/// scheduler.submit(0, backfill, |out| order.borrow_mut().push(("backfill", out.unwrap())));
+///
+/// scheduler.run_stage();
+/// // an urgent run arrives while the backfill is half done
+/// let urgent = DebugRunner::<i32>::input(10).stage("alert", |x: i32| Ok(x * 10));
+/// scheduler.submit(10, urgent, |out| order.borrow_mut().push(("urgent", out.unwrap())));
+///
+/// scheduler.run_all();
+/// assert_eq!(*order.borrow(), vec![("urgent", 100), ("backfill", 2)]);
+/// ```
+#[derive(Default)]
+pub struct PriorityScheduler<'a> {
+    queue: BinaryHeap<Queued<'a>>,
+    seq: u64,
+}
+
+impl<'a> PriorityScheduler<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// queue `runner`; `on_done` gets its result once its last stage has run
+    pub fn submit<O, E, F>(&mut self, priority: Priority, runner: DebugRunner<O, E>, on_done: F)
+    where
+        O: Any,
+        E: Debug + 'a,
+        F: FnOnce(Out<O, E>) + 'a,
+    {
+        self.seq += 1;
+        self.queue.push(Queued {
+            priority,
+            seq: self.seq,
+            run: Box::new(Run { runner, on_done }),
+        });
+    }
+
+    /// runs queued and not finished yet
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// run one stage of the most urgent run, returning false if nothing is queued
+    pub fn run_stage(&mut self) -> bool {
+        let Some(mut next) = self.queue.pop() else {
+            return false;
+        };
+        if next.run.step() {
+            next.run.complete();
+        } else {
+            self.queue.push(next);
+        }
+        true
+    }
+
+    /// run stages until every queued run is finished
+    pub fn run_all(&mut self) {
+        while self.run_stage() {}
+    }
+}
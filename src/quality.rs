@@ -0,0 +1,210 @@
+use super::*;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::ops::RangeBounds;
+
+type Rule<T> = Box<dyn Fn(&[T]) -> Vec<bool>>;
+
+/// a declarative set of data-quality rules evaluated over a whole collection.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// struct Row { id: u32, age: i32, email: Option<String> }
+///
+/// let checks = Checks::new()
+///     .not_null("email present", |r: &Row| r.email.as_ref())
+///     .in_range("age", |r: &Row| r.age, 0..=130)
+///     .unique("id", |r: &Row| r.id)
+///     .max_failure_rate(0.5);
+///
+/// let rows = vec![
+///     Row { id: 1, age: 30, email: Some("a@b.c".to_string()) },
+///     Row { id: 2, age: 200, email: None },
+/// ];
+/// let (rows, report) = Reactor::<Vec<Row>>::input(rows).check_quality(checks).run().unwrap();
+/// assert_eq!(report.failed(), 2);
+/// ```
+pub struct Checks<T> {
+    rules: Vec<(String, Rule<T>)>,
+    max_failure_rate: f64,
+}
+
+impl<T: 'static> Checks<T> {
+    /// an empty rule set that tolerates no failures
+    pub fn new() -> Self {
+        Checks {
+            rules: Vec::new(),
+            max_failure_rate: 0.0,
+        }
+    }
+
+    /// the fraction (0.0 - 1.0) of records any single rule may fail before the
+    /// stage fails the chain
+    pub fn max_failure_rate(mut self, rate: f64) -> Self {
+        self.max_failure_rate = rate;
+        self
+    }
+
+    /// a rule that checks each record on its own
+    pub fn rule<F>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        self.rules.push((
+            name.to_string(),
+            Box::new(move |rows: &[T]| rows.iter().map(&check).collect()),
+        ));
+        self
+    }
+
+    /// the field returned by `field` must be present
+    pub fn not_null<V, F>(self, name: &str, field: F) -> Self
+    where
+        V: ?Sized,
+        F: Fn(&T) -> Option<&V> + 'static,
+    {
+        self.rule(name, move |r| field(r).is_some())
+    }
+
+    /// the field returned by `field` must lie within `range`
+    pub fn in_range<V, F, R>(self, name: &str, field: F, range: R) -> Self
+    where
+        V: PartialOrd,
+        F: Fn(&T) -> V + 'static,
+        R: RangeBounds<V> + 'static,
+    {
+        self.rule(name, move |r| range.contains(&field(r)))
+    }
+
+    /// the field returned by `field` must match `format`. absent values pass;
+    /// combine with `not_null` to require them.
+    pub fn format<F, M>(self, name: &str, field: F, format: M) -> Self
+    where
+        F: Fn(&T) -> Option<&str> + 'static,
+        M: Fn(&str) -> bool + 'static,
+    {
+        self.rule(name, move |r| field(r).is_none_or(&format))
+    }
+
+    /// the key returned by `key` must not repeat; every occurrence after the
+    /// first counts as a failure
+    pub fn unique<K, F>(mut self, name: &str, key: F) -> Self
+    where
+        K: Hash + Eq,
+        F: Fn(&T) -> K + 'static,
+    {
+        self.rules.push((
+            name.to_string(),
+            Box::new(move |rows: &[T]| {
+                let mut seen = HashSet::new();
+                rows.iter().map(|r| seen.insert(key(r))).collect()
+            }),
+        ));
+        self
+    }
+
+    /// evaluate every rule against `rows`
+    pub fn evaluate(&self, rows: &[T]) -> QualityReport {
+        let rules = self
+            .rules
+            .iter()
+            .map(|(name, rule)| {
+                let results = rule(rows);
+                let passed = results.iter().filter(|ok| **ok).count();
+                RuleOutcome {
+                    name: name.clone(),
+                    passed,
+                    failed: results.len() - passed,
+                }
+            })
+            .collect();
+        QualityReport {
+            records: rows.len(),
+            rules,
+        }
+    }
+}
+
+impl<T: 'static> Default for Checks<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// pass/fail counts for a single rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleOutcome {
+    pub name: String,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl RuleOutcome {
+    pub fn failure_rate(&self) -> f64 {
+        let total = self.passed + self.failed;
+        if total == 0 {
+            0.0
+        } else {
+            self.failed as f64 / total as f64
+        }
+    }
+}
+
+/// the outcome of a `Checks` run over a collection
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityReport {
+    pub records: usize,
+    pub rules: Vec<RuleOutcome>,
+}
+
+impl QualityReport {
+    /// total number of rule violations across all rules
+    pub fn failed(&self) -> usize {
+        self.rules.iter().map(|r| r.failed).sum()
+    }
+
+    /// the rules whose failure rate is above `rate`
+    pub fn exceeding(&self, rate: f64) -> impl Iterator<Item = &RuleOutcome> {
+        self.rules.iter().filter(move |r| r.failure_rate() > rate)
+    }
+}
+
+impl std::fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} records", self.records)?;
+        for r in &self.rules {
+            write!(f, "; {}: {} passed, {} failed", r.name, r.passed, r.failed)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// evaluate `checks` over the collection and pass it on together with the
+    /// resulting `QualityReport`. fails the chain if any rule's failure rate is
+    /// above the configured threshold.
+    pub fn check_quality<T>(self, checks: Checks<T>) -> Reactor<(Vec<T>, QualityReport), E>
+    where
+        I: IntoIterator<Item = T>,
+        T: 'static,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let rows: Vec<T> = i.into_iter().collect();
+                let report = checks.evaluate(&rows);
+                if report.exceeding(checks.max_failure_rate).next().is_some() {
+                    return Err(Failure::InvalidInput(format!(
+                        "data quality checks failed: {}",
+                        report
+                    ))
+                    .into());
+                }
+                Ok((rows, report))
+            }),
+        }
+    }
+}
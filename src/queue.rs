@@ -0,0 +1,341 @@
+use super::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// a unit of work pulled from a `JobQueue`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    pub id: String,
+    pub payload: String,
+    /// how many times the job was handed out before, and failed
+    pub attempts: u32,
+}
+
+/// a source of jobs that are acknowledged once processed. implementations
+/// must be safe to share between the consumer's threads.
+pub trait JobQueue: Sync {
+    /// claim the next job, or `None` if the queue is empty right now
+    fn pull(&self) -> Out<Option<Job>>;
+    /// the job succeeded; remove it
+    fn ack(&self, job: &Job) -> Out<()>;
+    /// the job failed; requeue it or give up on it
+    fn nack(&self, job: &Job, error: &str) -> Out<()>;
+}
+
+fn io_failure(what: &str, e: std::io::Error) -> Failure {
+    Failure::Custom(format!("job queue: failed to {}: {}", what, e))
+}
+
+static JOB_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// a queue kept in a directory, usable by several processes at once. jobs are
+/// files in `pending/`; pulling one moves it to `running/`, which only one
+/// consumer can do. acked jobs are deleted, and jobs that failed
+/// `max_attempts` times end up in `failed/` next to a `.error` file.
+pub struct FileQueue {
+    dir: PathBuf,
+    max_attempts: u32,
+}
+
+impl FileQueue {
+    /// open (creating if needed) the queue in `dir`
+    pub fn open<P: Into<PathBuf>>(dir: P) -> Out<Self> {
+        let dir = dir.into();
+        for sub in ["pending", "running", "failed"] {
+            fs::create_dir_all(dir.join(sub))
+                .map_err(|e| io_failure("create the queue directory", e))?;
+        }
+        Ok(FileQueue {
+            dir,
+            max_attempts: 3,
+        })
+    }
+
+    /// give up on a job after it failed this many times (default 3)
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    fn path(&self, state: &str, id: &str) -> PathBuf {
+        self.dir.join(state).join(format!("{}.job", id))
+    }
+
+    fn write(&self, state: &str, id: &str, attempts: u32, payload: &str) -> Out<()> {
+        let tmp = self.dir.join(format!("{}.tmp", id));
+        fs::write(&tmp, format!("{}\n{}", attempts, payload))
+            .and_then(|_| fs::rename(&tmp, self.path(state, id)))
+            .map_err(|e| io_failure("write a job", e))
+    }
+
+    /// add a job, returning its id. ids sort in the order jobs were pushed.
+    pub fn push(&self, payload: &str) -> Out<String> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let id = format!(
+            "{:020}-{}-{}",
+            nanos,
+            std::process::id(),
+            JOB_COUNTER.fetch_add(1, AtomicOrdering::Relaxed)
+        );
+        self.write("pending", &id, 0, payload)?;
+        Ok(id)
+    }
+
+    /// the number of jobs waiting to be pulled
+    pub fn pending(&self) -> Out<usize> {
+        Ok(Self::job_ids(&self.dir.join("pending"))?.len())
+    }
+
+    /// move jobs left in `running/` by a consumer that died back to `pending/`.
+    /// only call this while no consumer is running.
+    pub fn recover(&self) -> Out<usize> {
+        let ids = Self::job_ids(&self.dir.join("running"))?;
+        for id in &ids {
+            fs::rename(self.path("running", id), self.path("pending", id))
+                .map_err(|e| io_failure("requeue a job", e))?;
+        }
+        Ok(ids.len())
+    }
+
+    fn job_ids(dir: &Path) -> Out<Vec<String>> {
+        let mut ids: Vec<String> = fs::read_dir(dir)
+            .map_err(|e| io_failure("list jobs", e))?
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                name.strip_suffix(".job").map(str::to_string)
+            })
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+}
+
+impl JobQueue for FileQueue {
+    fn pull(&self) -> Out<Option<Job>> {
+        for id in Self::job_ids(&self.dir.join("pending"))? {
+            let running = self.path("running", &id);
+            // losing the rename means another consumer claimed the job
+            if fs::rename(self.path("pending", &id), &running).is_err() {
+                continue;
+            }
+            let text = fs::read_to_string(&running).map_err(|e| io_failure("read a job", e))?;
+            let (attempts, payload) = text.split_once('\n').unwrap_or(("0", &text));
+            return Ok(Some(Job {
+                id,
+                payload: payload.to_string(),
+                attempts: attempts.parse().unwrap_or(0),
+            }));
+        }
+        Ok(None)
+    }
+
+    fn ack(&self, job: &Job) -> Out<()> {
+        fs::remove_file(self.path("running", &job.id)).map_err(|e| io_failure("ack a job", e))
+    }
+
+    fn nack(&self, job: &Job, error: &str) -> Out<()> {
+        let attempts = job.attempts + 1;
+        if attempts < self.max_attempts {
+            self.write("pending", &job.id, attempts, &job.payload)?;
+        } else {
+            self.write("failed", &job.id, attempts, &job.payload)?;
+            fs::write(
+                self.dir.join("failed").join(format!("{}.error", job.id)),
+                error,
+            )
+            .map_err(|e| io_failure("record a job's error", e))?;
+        }
+        fs::remove_file(self.path("running", &job.id)).map_err(|e| io_failure("nack a job", e))
+    }
+}
+
+/// what a `JobConsumer` did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConsumerStats {
+    pub acked: u64,
+    pub nacked: u64,
+}
+
+/// runs a pipeline once per job pulled from a queue, on up to `concurrency`
+/// threads, acking jobs whose pipeline succeeded and nacking the rest.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let dir = std::env::temp_dir().join(format!("chain_reaction-queue-doc-{}", std::process::id()));
+/// let queue = FileQueue::open(&dir).unwrap();
+/// for n in ["1", "2", "x"] {
+///     queue.push(n).unwrap();
+/// }
+///
+/// let stats = JobConsumer::new(queue)
+///     .concurrency(2)
+///     .stop_when_empty()
+///     .run(|job: &Job| {
+///         Reactor::input(job.payload.clone())
+///             .then(|s: String| s.parse::<i32>().map_err(|e| Failure::InvalidInput(e.to_string())))
+///             .run()
+///     })
+///     .unwrap();
+/// assert_eq!(stats.acked, 2);
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct JobConsumer<Q> {
+    queue: Q,
+    concurrency: usize,
+    poll_interval: Duration,
+    stop_when_empty: bool,
+    stop: Arc<AtomicBool>,
+    cores: Vec<usize>,
+}
+
+impl<Q: JobQueue> JobConsumer<Q> {
+    pub fn new(queue: Q) -> Self {
+        JobConsumer {
+            queue,
+            concurrency: 1,
+            poll_interval: Duration::from_millis(500),
+            stop_when_empty: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            cores: Vec::new(),
+        }
+    }
+
+    /// process up to this many jobs at once
+    pub fn concurrency(mut self, threads: usize) -> Self {
+        self.concurrency = threads.max(1);
+        self
+    }
+
+    /// how long to wait before asking an empty queue again
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// return once the queue is empty instead of waiting for more jobs
+    pub fn stop_when_empty(mut self) -> Self {
+        self.stop_when_empty = true;
+        self
+    }
+
+    /// pin the consumer's threads to these cpu cores, round-robin
+    #[cfg(feature = "affinity")]
+    pub fn pin_threads(mut self, cores: &[usize]) -> Self {
+        self.cores = cores.to_vec();
+        self
+    }
+
+    /// a flag that makes `run` return after the jobs in progress, when set
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn queue(&self) -> &Q {
+        &self.queue
+    }
+
+    /// consume jobs until stopped. an error from the queue itself stops every
+    /// thread once its job in progress is done, and the first one is returned;
+    /// a failing job is only nacked.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// // a queue that breaks after handing out a few jobs
+    /// struct Broken(std::sync::atomic::AtomicU32);
+    /// impl JobQueue for Broken {
+    ///     fn pull(&self) -> Out<Option<Job>> {
+    ///         match self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+    ///             n if n < 3 => Ok(Some(Job { id: n.to_string(), payload: String::new(), attempts: 0 })),
+    ///             _ => Err(Failure::Custom("connection lost".into())),
+    ///         }
+    ///     }
+    ///     fn ack(&self, _: &Job) -> Out<()> {
+    ///         Ok(())
+    ///     }
+    ///     fn nack(&self, _: &Job, _: &str) -> Out<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// // without stop_when_empty, the other threads would otherwise poll forever
+    /// let consumer = JobConsumer::new(Broken(0.into())).concurrency(4);
+    /// let failure = consumer.run(|_: &Job| Ok::<_, Failure>(())).unwrap_err();
+    /// assert_eq!(failure.to_string(), "Custom error: connection lost");
+    /// ```
+    pub fn run<T, E, F>(&self, handle: F) -> Out<ConsumerStats>
+    where
+        E: Debug,
+        F: Fn(&Job) -> Out<T, E> + Sync,
+    {
+        let acked = AtomicU64::new(0);
+        let nacked = AtomicU64::new(0);
+        let failed = AtomicBool::new(false);
+        let first_error = Mutex::new(None);
+        let (acked, nacked, handle) = (&acked, &nacked, &handle);
+        let (failed, first_error) = (&failed, &first_error);
+        thread::scope(|scope| {
+            let threads: Vec<_> = (0..self.concurrency)
+                .map(|n| {
+                    scope.spawn(move || {
+                        let consume = || -> Out<()> {
+                            #[cfg(feature = "affinity")]
+                            if !self.cores.is_empty() {
+                                crate::affinity::pin_current_thread(&[
+                                    self.cores[n % self.cores.len()]
+                                ])?;
+                            }
+                            while !self.stop.load(AtomicOrdering::Relaxed)
+                                && !failed.load(AtomicOrdering::Relaxed)
+                            {
+                                let Some(job) = self.queue.pull()? else {
+                                    if self.stop_when_empty {
+                                        return Ok(());
+                                    }
+                                    thread::sleep(self.poll_interval);
+                                    continue;
+                                };
+                                match handle(&job) {
+                                    Ok(_) => {
+                                        self.queue.ack(&job)?;
+                                        acked.fetch_add(1, AtomicOrdering::Relaxed);
+                                    }
+                                    Err(e) => {
+                                        self.queue.nack(&job, &format!("{:?}", e))?;
+                                        nacked.fetch_add(1, AtomicOrdering::Relaxed);
+                                    }
+                                }
+                            }
+                            Ok(())
+                        };
+                        if let Err(e) = consume() {
+                            // the first thread to fail keeps its error and stops the rest
+                            let mut first = first_error.lock().unwrap();
+                            first.get_or_insert(e);
+                            failed.store(true, AtomicOrdering::Relaxed);
+                        }
+                    })
+                })
+                .collect();
+            for t in threads {
+                t.join().expect("job consumer thread panicked");
+            }
+            if let Some(e) = first_error.lock().unwrap().take() {
+                return Err(e);
+            }
+            Ok(ConsumerStats {
+                acked: acked.load(AtomicOrdering::Relaxed),
+                nacked: nacked.load(AtomicOrdering::Relaxed),
+            })
+        })
+    }
+}
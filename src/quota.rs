@@ -0,0 +1,148 @@
+use super::*;
+use crate::memory::measure_peak;
+
+/// which limit a stage went over, and by how much.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotaResource {
+    Time {
+        limit: Duration,
+        used: Duration,
+    },
+    /// heap bytes, as counted by `TrackingAllocator`
+    Memory {
+        limit: usize,
+        used: usize,
+    },
+}
+
+/// the details of `Failure::QuotaExceeded`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaViolation {
+    pub stage: String,
+    pub resource: QuotaResource,
+}
+
+impl std::fmt::Display for QuotaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.resource {
+            QuotaResource::Time { limit, used } => write!(
+                f,
+                "stage '{}' took {:?}, over its budget of {:?}",
+                self.stage, used, limit
+            ),
+            QuotaResource::Memory { limit, used } => write!(
+                f,
+                "stage '{}' allocated {} bytes, over its cap of {}",
+                self.stage, used, limit
+            ),
+        }
+    }
+}
+
+/// limits on the wall-clock time and heap a stage may use; going over either
+/// fails the stage with `Failure::QuotaExceeded` and discards its output.
+///
+/// both limits are checked after the stage returns: a stage that runs long
+/// or allocates too much still runs to the end, and only its output is
+/// refused. to stop waiting on a stage once its time is up, use
+/// `ChainableAct::with_timeout` instead.
+///
+/// the memory cap is the peak heap the stage allocated on its own thread, as
+/// counted by `TrackingAllocator`; without it installed nothing is counted
+/// and the cap never trips.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// #[global_allocator]
+/// static ALLOC: TrackingAllocator = TrackingAllocator::system();
+///
+/// fn main() {
+///     let expand = quota("expand", |n: usize| Ok(vec![0u8; n].len())).memory_limit(64 * 1024);
+///     match expand.act(1 << 20) {
+///         Err(Failure::QuotaExceeded(q)) => assert_eq!(q.stage, "expand"),
+///         other => panic!("unexpected {:?}", other),
+///     }
+///
+///     // another thread's allocations, even large ones, aren't the stage's
+///     let elsewhere = quota("elsewhere", |n: usize| -> Out<usize> {
+///         Ok(std::thread::spawn(move || vec![0u8; n].len()).join().unwrap())
+///     })
+///     .memory_limit(64 * 1024);
+///     assert_eq!(elsewhere.act(1 << 20).unwrap(), 1 << 20);
+/// }
+/// ```
+pub struct Quota<A> {
+    act: A,
+    stage: String,
+    time: Option<Duration>,
+    memory: Option<usize>,
+}
+
+impl<A> Quota<A> {
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.time = Some(limit);
+        self
+    }
+
+    /// cap the heap the stage may allocate above what was live when it started
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory = Some(bytes);
+        self
+    }
+
+    fn violation(&self, resource: QuotaResource) -> Failure {
+        Failure::QuotaExceeded(Box::new(QuotaViolation {
+            stage: self.stage.clone(),
+            resource,
+        }))
+    }
+}
+
+impl<A, I, O, E> Act<I, O, E> for Quota<A>
+where
+    A: Act<I, O, E>,
+    E: Debug + From<Failure>,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let start = Instant::now();
+        let (out, peak) = measure_peak(|| self.act.act(input));
+        let used = start.elapsed();
+        if let Some(limit) = self.time.filter(|limit| used > *limit) {
+            return Err(self.violation(QuotaResource::Time { limit, used }).into());
+        }
+        if let Some(limit) = self.memory.filter(|limit| peak > *limit) {
+            return Err(self
+                .violation(QuotaResource::Memory { limit, used: peak })
+                .into());
+        }
+        out
+    }
+}
+
+/// put `act`, named `stage` in violations, under quotas set with
+/// `time_limit` and `memory_limit`
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::time::Duration;
+///
+/// let slow = quota("slow", |x: u64| {
+///     std::thread::sleep(Duration::from_millis(20));
+///     Ok(x)
+/// })
+/// .time_limit(Duration::from_millis(5));
+///
+/// match Reactor::input(1).then(slow).run() {
+///     Err(Failure::QuotaExceeded(q)) => assert_eq!(q.stage, "slow"),
+///     other => panic!("unexpected {:?}", other),
+/// }
+/// ```
+pub fn quota<A>(stage: &str, act: A) -> Quota<A> {
+    Quota {
+        act,
+        stage: stage.to_string(),
+        time: None,
+        memory: None,
+    }
+}
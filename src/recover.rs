@@ -0,0 +1,102 @@
+use super::*;
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// recover from an earlier failure: `f` gets the error and may return a
+    /// value to carry on with, or fail again
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let out = Reactor::<i32>::input(0)
+    ///     .then(|x: i32| if x == 0 { Err(Failure::ArithmeticError("division by zero".into())) } else { Ok(10 / x) })
+    ///     .or_else(|e| match e {
+    ///         Failure::ArithmeticError(_) => Ok(i32::MAX),
+    ///         e => Err(e),
+    ///     })
+    ///     .then(|x: i32| Ok(x.to_string()))
+    ///     .run();
+    /// assert_eq!(out.unwrap(), i32::MAX.to_string());
+    /// ```
+    pub fn or_else<F>(self, f: F) -> Reactor<I, E>
+    where
+        F: FnOnce(E) -> Out<I, E>,
+    {
+        Reactor {
+            input: self.input.or_else(f),
+        }
+    }
+
+    /// carry on with `default` if anything before failed
+    pub fn fallback(self, default: I) -> Reactor<I, E> {
+        self.or_else(|_| Ok(default))
+    }
+
+    /// inspect or convert an earlier failure, e.g. to log it or to move to
+    /// another error type. values pass through untouched.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Status {
+    ///     BadRequest,
+    ///     Internal,
+    /// }
+    ///
+    /// let out = Reactor::<&str>::input("x")
+    ///     .then(|s: &str| s.parse::<i32>().map_err(|e| Failure::InvalidInput(e.to_string())))
+    ///     .catch(|e| match e {
+    ///         Failure::InvalidInput(_) => Status::BadRequest,
+    ///         _ => Status::Internal,
+    ///     })
+    ///     .run();
+    /// assert_eq!(out, Err(Status::BadRequest));
+    /// ```
+    pub fn catch<E2, F>(self, handler: F) -> Reactor<I, E2>
+    where
+        E2: Debug,
+        F: FnOnce(E) -> E2,
+    {
+        Reactor {
+            input: self.input.map_err(handler),
+        }
+    }
+
+    /// like `then`, but a failing `transform` is tried again, up to
+    /// `max_retries` more times, right away
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::cell::Cell;
+    ///
+    /// let attempts = Cell::new(0);
+    /// let flaky = |x: i32| {
+    ///     attempts.set(attempts.get() + 1);
+    ///     if attempts.get() < 3 { Err(Failure::Custom("busy".into())) } else { Ok(x) }
+    /// };
+    /// let out = Reactor::<i32>::input(5).then_retry(2, flaky).run();
+    /// assert_eq!(out.unwrap(), 5);
+    /// ```
+    pub fn then_retry<O, T>(self, max_retries: u32, transform: T) -> Reactor<O, E>
+    where
+        I: Clone,
+        T: Act<I, O, E>,
+    {
+        self.then_retry_with_backoff(
+            RetryPolicy::new(max_retries).backoff(Duration::ZERO),
+            transform,
+        )
+    }
+
+    /// like `then_retry`, waiting between attempts as `policy` says
+    pub fn then_retry_with_backoff<O, T>(self, policy: RetryPolicy, transform: T) -> Reactor<O, E>
+    where
+        I: Clone,
+        T: Act<I, O, E>,
+    {
+        self.then(retry_with(transform, policy))
+    }
+}
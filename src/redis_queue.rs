@@ -0,0 +1,283 @@
+use super::*;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn redis_failure(what: &str, e: impl std::fmt::Display) -> Failure {
+    Failure::Custom(format!("redis queue: failed to {}: {}", what, e))
+}
+
+/// a reply to a redis command, as far as the queue needs them
+enum Reply {
+    Nil,
+    Status,
+    Int(i64),
+    Bulk(String),
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn command(&mut self, args: &[&str]) -> Out<Reply> {
+        let mut request = format!("*{}\r\n", args.len());
+        for arg in args {
+            request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        self.writer
+            .write_all(request.as_bytes())
+            .map_err(|e| redis_failure("send a command", e))?;
+        self.reply()
+    }
+
+    fn line(&mut self) -> Out<String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err(redis_failure("read a reply", "connection closed")),
+            Ok(_) => Ok(line.trim_end_matches("\r\n").to_string()),
+            Err(e) => Err(redis_failure("read a reply", e)),
+        }
+    }
+
+    fn reply(&mut self) -> Out<Reply> {
+        let line = self.line()?;
+        let (kind, rest) = line.split_at(line.len().min(1));
+        let number = |s: &str| {
+            s.parse::<i64>()
+                .map_err(|_| redis_failure("read a reply", format!("bad reply '{}'", line)))
+        };
+        match kind {
+            "+" => Ok(Reply::Status),
+            "-" => Err(Failure::Custom(format!("redis queue: {}", rest))),
+            ":" => Ok(Reply::Int(number(rest)?)),
+            "$" => match number(rest)? {
+                n if n < 0 => Ok(Reply::Nil),
+                n => {
+                    // the value, then its trailing \r\n
+                    let mut value = vec![0; n as usize + 2];
+                    self.reader
+                        .read_exact(&mut value)
+                        .map_err(|e| redis_failure("read a reply", e))?;
+                    value.truncate(n as usize);
+                    String::from_utf8(value)
+                        .map(Reply::Bulk)
+                        .map_err(|e| redis_failure("read a reply", e))
+                }
+            },
+            _ => Err(redis_failure(
+                "read a reply",
+                format!("unexpected reply '{}'", line),
+            )),
+        }
+    }
+}
+
+static JOB_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// a queue kept in redis lists, usable by several processes at once. jobs wait
+/// in `<name>:pending`; pulling one moves it atomically to `<name>:running`
+/// (`RPOPLPUSH`), so only one consumer gets it. acked jobs are removed, and
+/// jobs that failed `max_attempts` times end up in `<name>:failed` along with
+/// their error.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// # use std::collections::{HashMap, VecDeque};
+/// # use std::io::{BufRead, BufReader, Read, Write};
+/// # use std::net::TcpListener;
+/// #
+/// # // just enough of a redis server for the queue
+/// # let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let addr = listener.local_addr().unwrap();
+/// # std::thread::spawn(move || {
+/// #     let mut lists: HashMap<String, VecDeque<String>> = HashMap::new();
+/// #     for stream in listener.incoming() {
+/// #         let mut writer = stream.unwrap();
+/// #         let mut reader = BufReader::new(writer.try_clone().unwrap());
+/// #         let mut line = String::new();
+/// #         while reader.read_line(&mut line).unwrap() > 0 {
+/// #             let args: Vec<String> = (0..line[1..].trim().parse().unwrap())
+/// #                 .map(|_| {
+/// #                     line.clear();
+/// #                     reader.read_line(&mut line).unwrap();
+/// #                     let mut arg = vec![0; line[1..].trim().parse::<usize>().unwrap() + 2];
+/// #                     reader.read_exact(&mut arg).unwrap();
+/// #                     String::from_utf8(arg[..arg.len() - 2].to_vec()).unwrap()
+/// #                 })
+/// #                 .collect();
+/// #             line.clear();
+/// #             let list = lists.entry(args[1].clone()).or_default();
+/// #             let reply = match args[0].as_str() {
+/// #                 "LPUSH" => { list.push_front(args[2].clone()); format!(":{}\r\n", list.len()) }
+/// #                 "LLEN" => format!(":{}\r\n", list.len()),
+/// #                 "LREM" => match list.iter().position(|v| *v == args[3]) {
+/// #                     Some(i) => { list.remove(i); ":1\r\n".to_string() }
+/// #                     None => ":0\r\n".to_string(),
+/// #                 },
+/// #                 _ => match list.pop_back() {
+/// #                     Some(v) => {
+/// #                         lists.entry(args[2].clone()).or_default().push_front(v.clone());
+/// #                         format!("${}\r\n{}\r\n", v.len(), v)
+/// #                     }
+/// #                     None => "$-1\r\n".to_string(),
+/// #                 },
+/// #             };
+/// #             writer.write_all(reply.as_bytes()).unwrap();
+/// #         }
+/// #     }
+/// # });
+///
+/// let queue = RedisQueue::connect(addr, "orders").unwrap().max_attempts(2);
+/// for n in ["1", "2", "x"] {
+///     queue.push(n).unwrap();
+/// }
+/// assert_eq!(queue.pending().unwrap(), 3);
+///
+/// let stats = JobConsumer::new(queue)
+///     .stop_when_empty()
+///     .run(|job: &Job| job.payload.parse::<i32>().map_err(|e| Failure::InvalidInput(e.to_string())))
+///     .unwrap();
+/// // "x" was tried twice, then given up on
+/// assert_eq!((stats.acked, stats.nacked), (2, 2));
+/// ```
+pub struct RedisQueue {
+    connection: Mutex<Connection>,
+    name: String,
+    max_attempts: u32,
+}
+
+impl RedisQueue {
+    /// connect to the redis server at `addr` and use the lists named after
+    /// `name`
+    pub fn connect<A: ToSocketAddrs>(addr: A, name: &str) -> Out<Self> {
+        let writer = TcpStream::connect(addr).map_err(|e| redis_failure("connect", e))?;
+        let reader = BufReader::new(
+            writer
+                .try_clone()
+                .map_err(|e| redis_failure("connect", e))?,
+        );
+        Ok(RedisQueue {
+            connection: Mutex::new(Connection { reader, writer }),
+            name: name.to_string(),
+            max_attempts: 3,
+        })
+    }
+
+    /// give up on a job after it failed this many times (default 3)
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    fn key(&self, list: &str) -> String {
+        format!("{}:{}", self.name, list)
+    }
+
+    fn command(&self, args: &[&str]) -> Out<Reply> {
+        self.connection
+            .lock()
+            .expect("a redis command panicked")
+            .command(args)
+    }
+
+    fn count(&self, args: &[&str]) -> Out<i64> {
+        match self.command(args)? {
+            Reply::Int(n) => Ok(n),
+            _ => Err(redis_failure(args[0], "expected a number")),
+        }
+    }
+
+    /// a job as stored in the lists: id, attempts and escaped payload
+    fn entry(id: &str, attempts: u32, payload: &str) -> String {
+        format!("{}\t{}\t{}", id, attempts, escape(payload))
+    }
+
+    /// add a job, returning its id
+    pub fn push(&self, payload: &str) -> Out<String> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let id = format!(
+            "{:020}-{}-{}",
+            nanos,
+            std::process::id(),
+            JOB_COUNTER.fetch_add(1, AtomicOrdering::Relaxed)
+        );
+        let entry = Self::entry(&id, 0, payload);
+        self.count(&["LPUSH", &self.key("pending"), &entry])?;
+        Ok(id)
+    }
+
+    /// the number of jobs waiting to be pulled
+    pub fn pending(&self) -> Out<usize> {
+        Ok(self.count(&["LLEN", &self.key("pending")])? as usize)
+    }
+
+    /// move jobs left running by a consumer that died back to pending. only
+    /// call this while no consumer is running.
+    pub fn recover(&self) -> Out<usize> {
+        let (running, pending) = (self.key("running"), self.key("pending"));
+        let mut moved = 0;
+        while let Reply::Bulk(_) = self.command(&["RPOPLPUSH", &running, &pending])? {
+            moved += 1;
+        }
+        Ok(moved)
+    }
+
+    /// take `job` off the running list
+    fn finish(&self, job: &Job) -> Out<()> {
+        let entry = Self::entry(&job.id, job.attempts, &job.payload);
+        self.count(&["LREM", &self.key("running"), "1", &entry])?;
+        Ok(())
+    }
+}
+
+impl JobQueue for RedisQueue {
+    fn pull(&self) -> Out<Option<Job>> {
+        let reply = self.command(&["RPOPLPUSH", &self.key("pending"), &self.key("running")])?;
+        let entry = match reply {
+            Reply::Bulk(entry) => entry,
+            Reply::Nil => return Ok(None),
+            Reply::Status | Reply::Int(_) => {
+                return Err(redis_failure("pull a job", "expected a job or nil"))
+            }
+        };
+        let mut fields = entry.splitn(3, '\t');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(id), Some(attempts), Some(payload)) => Ok(Some(Job {
+                id: id.to_string(),
+                payload: unescape(payload),
+                attempts: attempts.parse().unwrap_or(0),
+            })),
+            _ => Err(redis_failure(
+                "pull a job",
+                format!("malformed job '{}'", entry),
+            )),
+        }
+    }
+
+    fn ack(&self, job: &Job) -> Out<()> {
+        self.finish(job)
+    }
+
+    fn nack(&self, job: &Job, error: &str) -> Out<()> {
+        let attempts = job.attempts + 1;
+        if attempts < self.max_attempts {
+            let entry = Self::entry(&job.id, attempts, &job.payload);
+            self.count(&["LPUSH", &self.key("pending"), &entry])?;
+        } else {
+            let entry = format!(
+                "{}\t{}",
+                Self::entry(&job.id, attempts, &job.payload),
+                escape(error)
+            );
+            self.count(&["LPUSH", &self.key("failed"), &entry])?;
+        }
+        self.finish(job)
+    }
+}
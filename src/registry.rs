@@ -0,0 +1,207 @@
+use super::*;
+use crate::debug::{Stage, Value};
+use std::any::{type_name, Any, TypeId};
+use std::collections::BTreeMap;
+
+/// an act stored in an `ActRegistry`, along with its input and output types.
+pub struct RegisteredAct<E = Failure> {
+    pub name: String,
+    pub input_type: &'static str,
+    pub output_type: &'static str,
+    input_id: TypeId,
+    output_id: TypeId,
+    run: Stage<E>,
+}
+
+impl<E> RegisteredAct<E> {
+    /// true if this act's output can be fed into `next`
+    pub fn feeds(&self, next: &RegisteredAct<E>) -> bool {
+        self.output_id == next.input_id
+    }
+
+    pub(crate) fn input_id(&self) -> TypeId {
+        self.input_id
+    }
+
+    pub(crate) fn accepts(&self, value: &dyn Value) -> bool {
+        value.as_any().type_id() == self.input_id
+    }
+
+    pub(crate) fn run(&self, value: Box<dyn Value>) -> Out<Box<dyn Value>, E> {
+        (self.run)(value)
+    }
+}
+
+/// acts registered under names, so they can be looked up and chained at
+/// runtime (from the repl, the cli, or configuration) instead of in code.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let mut registry = ActRegistry::<Failure>::new();
+/// registry
+///     .register("double", |x: i32| Ok(x * 2))
+///     .register("show", |x: i32| Ok(format!("<{}>", x)))
+///     .register("len", |s: String| Ok(s.len()));
+///
+/// assert_eq!(registry.call::<i32, i32>("double", 4).unwrap(), 8);
+/// assert_eq!(registry.call_chain::<i32, usize>(&["double", "double", "show", "len"], 25).unwrap(), 5);
+/// assert!(registry.get("double").unwrap().feeds(registry.get("show").unwrap()));
+/// assert!(!registry.get("show").unwrap().feeds(registry.get("double").unwrap()));
+/// assert_eq!(registry.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), ["double", "len", "show"]);
+///
+/// let message = |r: Out<()>| r.unwrap_err().to_string();
+/// assert_eq!(
+///     message(registry.check_chain::<i32, usize>(&["double", "len"])),
+///     "Invalid input: act 'len' takes alloc::string::String, but receives i32"
+/// );
+/// assert_eq!(message(registry.check_chain::<i32, i32>(&["triple"])), "Invalid input: no act registered as 'triple'");
+/// assert_eq!(message(registry.check_chain::<i32, i32>(&["show"])), "Invalid input: chain produces alloc::string::String, not i32");
+/// assert!(registry.check_chain::<i32, i32>(&[]).is_ok());
+/// ```
+pub struct ActRegistry<E = Failure> {
+    acts: BTreeMap<String, RegisteredAct<E>>,
+}
+
+impl<E> ActRegistry<E>
+where
+    E: Debug + 'static,
+{
+    pub fn new() -> Self {
+        ActRegistry {
+            acts: BTreeMap::new(),
+        }
+    }
+
+    /// register `act` under `name`, replacing any act already registered there
+    pub fn register<I, O, A>(&mut self, name: &str, act: A) -> &mut Self
+    where
+        I: Any + Debug,
+        O: Any + Debug,
+        A: Act<I, O, E> + 'static,
+    {
+        let run: Stage<E> = Box::new(move |value: Box<dyn Value>| {
+            let input = value
+                .into_any()
+                .downcast::<I>()
+                .expect("registered act received a value of the wrong type");
+            act.act(*input).map(|o| Box::new(o) as Box<dyn Value>)
+        });
+        self.acts.insert(
+            name.to_string(),
+            RegisteredAct {
+                name: name.to_string(),
+                input_type: type_name::<I>(),
+                output_type: type_name::<O>(),
+                input_id: TypeId::of::<I>(),
+                output_id: TypeId::of::<O>(),
+                run,
+            },
+        );
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredAct<E>> {
+        self.acts.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.acts.contains_key(name)
+    }
+
+    /// every registered act, ordered by name
+    pub fn iter(&self) -> impl Iterator<Item = &RegisteredAct<E>> {
+        self.acts.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.acts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.acts.is_empty()
+    }
+}
+
+impl<E> ActRegistry<E>
+where
+    E: Debug + From<Failure> + 'static,
+{
+    /// run the act registered as `name` on `input`
+    pub fn call<I, O>(&self, name: &str, input: I) -> Out<O, E>
+    where
+        I: Any + Debug,
+        O: Any,
+    {
+        self.call_chain(&[name], input)
+    }
+
+    /// check that the acts registered under `names` exist and chain from `I`
+    /// to `O`, without running anything
+    pub fn check_chain<I, O>(&self, names: &[&str]) -> Out<(), E>
+    where
+        I: Any,
+        O: Any,
+    {
+        self.resolve_chain::<I, O>(names).map(|_| ())
+    }
+
+    fn resolve_chain<I, O>(&self, names: &[&str]) -> Out<Vec<&RegisteredAct<E>>, E>
+    where
+        I: Any,
+        O: Any,
+    {
+        let mut acts = Vec::with_capacity(names.len());
+        let mut current = (TypeId::of::<I>(), type_name::<I>());
+        for name in names {
+            let act = self
+                .get(name)
+                .ok_or_else(|| Failure::InvalidInput(format!("no act registered as '{}'", name)))?;
+            if act.input_id != current.0 {
+                return Err(Failure::InvalidInput(format!(
+                    "act '{}' takes {}, but receives {}",
+                    name, act.input_type, current.1
+                ))
+                .into());
+            }
+            current = (act.output_id, act.output_type);
+            acts.push(act);
+        }
+        if current.0 != TypeId::of::<O>() {
+            return Err(Failure::InvalidInput(format!(
+                "chain produces {}, not {}",
+                current.1,
+                type_name::<O>()
+            ))
+            .into());
+        }
+        Ok(acts)
+    }
+
+    /// run the acts registered under `names` one after another, like a chain
+    /// of `then`s. unknown names and mismatched types fail before anything runs.
+    pub fn call_chain<I, O>(&self, names: &[&str], input: I) -> Out<O, E>
+    where
+        I: Any + Debug,
+        O: Any,
+    {
+        let acts = self.resolve_chain::<I, O>(names)?;
+        let mut value: Box<dyn Value> = Box::new(input);
+        for act in acts {
+            value = act.run(value)?;
+        }
+        Ok(*value
+            .into_any()
+            .downcast::<O>()
+            .expect("type checked above"))
+    }
+}
+
+impl<E> Default for ActRegistry<E>
+where
+    E: Debug + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,253 @@
+use super::*;
+use crate::debug::Value;
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+type Parser = Box<dyn Fn(&str) -> Result<Box<dyn Value>, String>>;
+
+/// an interactive session for chaining registered acts against a sample input,
+/// printing the intermediate value after every stage that's appended.
+///
+/// commands:
+/// - `input <type> <text>`: start over from a sample value of a registered input type
+/// - `then <act>` (or just `<act>`): append a registered act and run it
+/// - `undo`: drop the last stage
+/// - `show`: print the current chain and value
+/// - `list`: list the registered acts and input types
+/// - `quit`
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let mut registry = ActRegistry::<Failure>::new();
+/// registry
+///     .register("double", |x: i32| Ok(x * 2))
+///     .register("checked", |x: i32| if x > 10 { Err(Failure::InvalidInput("too big".into())) } else { Ok(x) })
+///     .register("upper", |s: String| Ok(s.to_uppercase()));
+/// let mut repl = Repl::new(registry);
+///
+/// assert_eq!(repl.eval("double").0, "no input yet; use `input <type> <text>`");
+/// assert_eq!(repl.eval("input i32 3").0, "input = 3");
+/// assert_eq!(repl.eval("double").0, "[1] double = 6");
+/// assert_eq!(repl.eval("then double").0, "[2] double = 12");
+/// assert!(repl.eval("checked").0.starts_with("'checked' failed: "));
+/// assert!(repl.eval("upper").0.starts_with("'upper' takes alloc::string::String"));
+/// assert_eq!(repl.eval("show").0, "i32 3 -> double -> double\n= 12");
+/// assert_eq!(repl.eval("undo").0, "[1] = 6");
+/// assert_eq!(repl.eval("input u8 1").0, "unknown input type 'u8'; see `list`");
+/// assert!(repl.eval("input i32 x").0.starts_with("can't parse 'x' as i32"));
+/// assert_eq!(repl.eval("quit"), (String::new(), false));
+///
+/// // a typo or an act of the wrong type leaves the value alone, with no replay
+/// let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+/// let counter = calls.clone();
+/// let mut registry = ActRegistry::<Failure>::new();
+/// registry
+///     .register("double", move |x: i32| { counter.set(counter.get() + 1); Ok(x * 2) })
+///     .register("upper", |s: String| Ok(s.to_uppercase()));
+/// let mut repl = Repl::new(registry);
+/// repl.eval("input i32 3");
+/// repl.eval("double");
+/// assert_eq!(repl.eval("dubble").0, "no act registered as 'dubble'");
+/// assert!(repl.eval("upper").0.starts_with("'upper' takes"));
+/// assert_eq!(repl.eval("show").0, "i32 3 -> double\n= 6");
+/// assert_eq!(calls.get(), 1);
+///
+/// let mut out = Vec::new();
+/// let script = std::io::Cursor::new("input String hi\nupper\nquit\nupper\n");
+/// repl.run(script, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "> input = \"hi\"\n> [1] upper = \"HI\"\n> ");
+/// ```
+pub struct Repl<E = Failure> {
+    registry: ActRegistry<E>,
+    parsers: BTreeMap<String, Parser>,
+    input: Option<(String, String)>,
+    stages: Vec<String>,
+    value: Option<Box<dyn Value>>,
+}
+
+impl<E> Repl<E>
+where
+    E: Debug + 'static,
+{
+    /// a session over `registry`, accepting `i32`, `i64`, `f64` and `String` inputs
+    pub fn new(registry: ActRegistry<E>) -> Self {
+        let mut repl = Repl {
+            registry,
+            parsers: BTreeMap::new(),
+            input: None,
+            stages: Vec::new(),
+            value: None,
+        };
+        repl.input_type::<i32>("i32")
+            .input_type::<i64>("i64")
+            .input_type::<f64>("f64")
+            .input_type::<String>("String");
+        repl
+    }
+
+    /// accept sample inputs of type `T`, parsed from text, under `name`
+    pub fn input_type<T>(&mut self, name: &str) -> &mut Self
+    where
+        T: FromStr + Debug + 'static,
+        T::Err: std::fmt::Display,
+    {
+        self.parsers.insert(
+            name.to_string(),
+            Box::new(|s: &str| {
+                s.parse::<T>()
+                    .map(|v| Box::new(v) as Box<dyn Value>)
+                    .map_err(|e| format!("can't parse '{}' as {}: {}", s, type_name::<T>(), e))
+            }),
+        );
+        self
+    }
+
+    /// rebuild the current value by running the sample input through `stages`
+    fn replay(&self, stages: &[String]) -> Result<Box<dyn Value>, String> {
+        let (kind, text) = self
+            .input
+            .as_ref()
+            .ok_or("no input yet; use `input <type> <text>`")?;
+        let mut value = (self.parsers[kind])(text)?;
+        for name in stages {
+            value = self.apply(name, value)?;
+        }
+        Ok(value)
+    }
+
+    /// the act registered as `name`, if it takes `value`
+    fn check(&self, name: &str, value: &dyn Value) -> Result<&RegisteredAct<E>, String> {
+        let act = self
+            .registry
+            .get(name)
+            .ok_or_else(|| format!("no act registered as '{}'", name))?;
+        if !act.accepts(value) {
+            return Err(format!(
+                "'{}' takes {}, but the current value is {:?}",
+                name, act.input_type, value
+            ));
+        }
+        Ok(act)
+    }
+
+    fn apply(&self, name: &str, value: Box<dyn Value>) -> Result<Box<dyn Value>, String> {
+        self.check(name, &*value)?
+            .run(value)
+            .map_err(|e| format!("'{}' failed: {:?}", name, e))
+    }
+
+    /// handle one command line, returning what to print and whether to keep going
+    pub fn eval(&mut self, line: &str) -> (String, bool) {
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let out = match cmd {
+            "" => String::new(),
+            "quit" | "exit" => return (String::new(), false),
+            "help" => {
+                "commands: input <type> <text>, then <act>, undo, show, list, quit".to_string()
+            }
+            "list" => {
+                let mut out = String::from("acts:\n");
+                for act in self.registry.iter() {
+                    out += &format!(
+                        "  {}: {} -> {}\n",
+                        act.name, act.input_type, act.output_type
+                    );
+                }
+                out += "input types: ";
+                out += &self.parsers.keys().cloned().collect::<Vec<_>>().join(", ");
+                out
+            }
+            "input" => {
+                let (kind, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                match self.parsers.get(kind) {
+                    None => format!("unknown input type '{}'; see `list`", kind),
+                    Some(parse) => match parse(text) {
+                        Ok(v) => {
+                            let out = format!("input = {:?}", v);
+                            self.input = Some((kind.to_string(), text.to_string()));
+                            self.stages.clear();
+                            self.value = Some(v);
+                            out
+                        }
+                        Err(e) => e,
+                    },
+                }
+            }
+            "undo" => {
+                let mut stages = self.stages.clone();
+                if stages.pop().is_none() {
+                    "nothing to undo".to_string()
+                } else {
+                    match self.replay(&stages) {
+                        Ok(v) => {
+                            let out = format!("[{}] = {:?}", stages.len(), v);
+                            self.stages = stages;
+                            self.value = Some(v);
+                            out
+                        }
+                        Err(e) => e,
+                    }
+                }
+            }
+            "show" => match &self.value {
+                None => "no input yet".to_string(),
+                Some(v) => {
+                    let input = self.input.as_ref().map(|(k, t)| format!("{} {}", k, t));
+                    let mut chain = vec![input.unwrap_or_default()];
+                    chain.extend(self.stages.iter().cloned());
+                    format!("{}\n= {:?}", chain.join(" -> "), v)
+                }
+            },
+            _ => {
+                let name = if cmd == "then" { rest } else { line };
+                let checked = match &self.value {
+                    None => Err("no input yet; use `input <type> <text>`".to_string()),
+                    Some(v) => self.check(name, &**v).map(|_| ()),
+                };
+                match checked {
+                    Err(e) => e,
+                    // the value is consumed by the act, so rebuild it if the act fails
+                    Ok(()) => {
+                        let v = self.value.take().expect("checked above");
+                        match self.apply(name, v) {
+                            Ok(v) => {
+                                self.stages.push(name.to_string());
+                                let out = format!("[{}] {} = {:?}", self.stages.len(), name, v);
+                                self.value = Some(v);
+                                out
+                            }
+                            Err(e) => {
+                                self.value = self.replay(&self.stages).ok();
+                                e
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        (out, true)
+    }
+
+    /// read commands from `input` until it ends or `quit`, writing results to `output`
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> std::io::Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            let (out, go_on) = self.eval(&line?);
+            if !out.is_empty() {
+                writeln!(output, "{}", out)?;
+            }
+            if !go_on {
+                break;
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
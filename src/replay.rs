@@ -0,0 +1,342 @@
+use super::*;
+use crate::debug::Value;
+use std::any::{type_name, Any, TypeId};
+
+/// an intermediate value that can be captured and handed out again
+trait Captured: Value {
+    fn clone_captured(&self) -> Box<dyn Captured>;
+    fn into_value(self: Box<Self>) -> Box<dyn Value>;
+}
+
+impl<T: Any + Debug + Clone> Captured for T {
+    fn clone_captured(&self) -> Box<dyn Captured> {
+        Box::new(self.clone())
+    }
+
+    fn into_value(self: Box<Self>) -> Box<dyn Value> {
+        self
+    }
+}
+
+type RecordedStage<E> = Box<dyn Fn(Box<dyn Captured>) -> Out<Box<dyn Captured>, E>>;
+
+fn recorded_stage<I, O, E, T>(act: T) -> RecordedStage<E>
+where
+    I: Any,
+    O: Any + Debug + Clone,
+    E: Debug,
+    T: Act<I, O, E> + 'static,
+{
+    Box::new(move |value: Box<dyn Captured>| {
+        let input = value
+            .into_value()
+            .into_any()
+            .downcast::<I>()
+            .expect("recorded stage received a value of the wrong type");
+        act.act(*input).map(|o| Box::new(o) as Box<dyn Captured>)
+    })
+}
+
+/// a run that keeps a copy of the value entering every stage, so it can be
+/// re-executed from any stage after the fact, optionally with a stage swapped
+/// for a fixed version. stages run as they're added, like on a `Reactor`;
+/// once one fails, the rest are recorded but not run until a replay.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let mut run = Recording::<i32>::input(4)
+///     .stage("half", |x: i32| Ok(x / 2))
+///     .stage("invert", |x: i32| {
+///         if x == 2 {
+///             Err(Failure::ArithmeticError("pole at 2".to_string()))
+///         } else {
+///             Ok(100 / (x - 2))
+///         }
+///     });
+/// assert_eq!(run.failed_stage().unwrap().name, "invert");
+///
+/// run.swap_stage(1, |x: i32| Ok(100 / (x + 2))).unwrap();
+/// assert_eq!(run.replay_from(1).output(), Some(25));
+/// ```
+pub struct Recording<O, E = Failure> {
+    /// each stage with the ids of its input and output types
+    stages: Vec<(StageInfo, [TypeId; 2], RecordedStage<E>)>,
+    /// the value entering each stage that has run, followed by the output
+    values: Vec<Box<dyn Captured>>,
+    error: Option<E>,
+    _marker: PhantomData<O>,
+}
+
+impl<I, E> Recording<I, E>
+where
+    I: Any + Debug + Clone,
+{
+    pub fn input(input: I) -> Self {
+        Recording {
+            stages: Vec::new(),
+            values: vec![Box::new(input)],
+            error: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<O, E> Recording<O, E>
+where
+    O: Any + Debug + Clone,
+    E: Debug + 'static,
+{
+    /// append a named stage and run it if every stage before it succeeded
+    pub fn stage<O2, T>(self, name: &str, act: T) -> Recording<O2, E>
+    where
+        O2: Any + Debug + Clone,
+        T: Act<O, O2, E> + 'static,
+    {
+        let info = StageInfo {
+            index: self.stages.len(),
+            name: name.to_string(),
+            input_type: type_name::<O>(),
+            output_type: type_name::<O2>(),
+        };
+        let mut run = Recording {
+            stages: self.stages,
+            values: self.values,
+            error: self.error,
+            _marker: PhantomData,
+        };
+        let types = [TypeId::of::<O>(), TypeId::of::<O2>()];
+        run.stages.push((info, types, recorded_stage(act)));
+        if run.error.is_none() {
+            run.run_from(run.stages.len() - 1);
+        }
+        run
+    }
+
+    /// replace stage `index` with `act`, which must have the same input and
+    /// output types. nothing runs until `replay_from`.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let mut run = Recording::<i32>::input(4).stage("half", |x: i32| Ok(x / 2));
+    /// let wrong = run.swap_stage(0, |x: i64| Ok(x / 2)).unwrap_err();
+    /// assert!(matches!(wrong, Failure::InvalidInput(_)));
+    /// assert!(run.swap_stage(1, |x: i32| Ok(x)).is_err());
+    /// assert!(run.swap_stage(0, |x: i32| Ok(x / 4)).is_ok());
+    /// assert_eq!(run.replay_from(0).output(), Some(1));
+    /// ```
+    pub fn swap_stage<I2, O2, T>(&mut self, index: usize, act: T) -> Out<()>
+    where
+        I2: Any,
+        O2: Any + Debug + Clone,
+        T: Act<I2, O2, E> + 'static,
+    {
+        let Some((info, types, stage)) = self.stages.get_mut(index) else {
+            return Err(Failure::InvalidInput(format!(
+                "there is no stage {}",
+                index
+            )));
+        };
+        if *types != [TypeId::of::<I2>(), TypeId::of::<O2>()] {
+            return Err(Failure::InvalidInput(format!(
+                "stage '{}' is {} -> {}, the replacement is {} -> {}",
+                info.name,
+                info.input_type,
+                info.output_type,
+                type_name::<I2>(),
+                type_name::<O2>()
+            )));
+        }
+        *stage = recorded_stage(act);
+        Ok(())
+    }
+
+    /// re-execute the run from stage `index` with the value captured for it,
+    /// replacing everything recorded after it
+    pub fn replay_from(&mut self, index: usize) -> &mut Self {
+        assert!(
+            index < self.values.len(),
+            "stage {} never received a value; replay from an earlier stage",
+            index
+        );
+        self.values.truncate(index + 1);
+        self.error = None;
+        self.run_from(index);
+        self
+    }
+
+    /// a copy of the final output, if every stage succeeded
+    pub fn output(&self) -> Option<O> {
+        if self.values.len() <= self.stages.len() {
+            return None;
+        }
+        let last = (**self.values.last()?).clone_captured();
+        last.into_value()
+            .into_any()
+            .downcast::<O>()
+            .ok()
+            .map(|o| *o)
+    }
+
+    fn run_from(&mut self, index: usize) {
+        for (_, _, stage) in &self.stages[index..] {
+            let input = (**self.values.last().unwrap()).clone_captured();
+            match stage(input) {
+                Ok(v) => self.values.push(v),
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<O, E> Recording<O, E> {
+    /// the value that entered stage `index`, if it ran
+    pub fn value_at(&self, index: usize) -> Option<&dyn Debug> {
+        self.values.get(index).map(|v| v.as_debug())
+    }
+
+    /// the stage that failed, if any
+    pub fn failed_stage(&self) -> Option<&StageInfo> {
+        match self.error {
+            Some(_) => self
+                .stages
+                .get(self.values.len() - 1)
+                .map(|(info, _, _)| info),
+            None => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&E> {
+        self.error.as_ref()
+    }
+
+    pub fn stages(&self) -> impl Iterator<Item = &StageInfo> {
+        self.stages.iter().map(|(info, _, _)| info)
+    }
+
+    /// the persisted form of this run, to load and replay in another process.
+    /// values are type-erased here, so `encode` turns each captured value into
+    /// text, returning `None` for types it doesn't know; saving fails if any
+    /// value can't be encoded. the error is kept as its `Debug` text.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let run = Recording::<String>::input("12,x,3".into())
+    ///     .stage("split", |s: String| Ok(s.split(',').map(String::from).collect::<Vec<_>>()))
+    ///     .stage("parse", |parts: Vec<String>| {
+    ///         parts.iter().map(|p| p.parse::<i64>().map_err(|e| Failure::InvalidInput(e.to_string()))).collect::<Out<Vec<_>>>()
+    ///     })
+    ///     .stage("sum", |ns: Vec<i64>| Ok(ns.iter().sum::<i64>()));
+    ///
+    /// let encode = |value: &dyn std::any::Any| {
+    ///     value
+    ///         .downcast_ref::<String>()
+    ///         .cloned()
+    ///         .or_else(|| value.downcast_ref::<Vec<String>>().map(|parts| parts.join(",")))
+    /// };
+    /// let line = run.save(encode).unwrap().to_wire();
+    ///
+    /// // later, somewhere else
+    /// let saved = SavedRun::from_wire(&line).unwrap();
+    /// assert_eq!(saved.failed_at, Some(1));
+    /// assert_eq!(saved.stages[1].name, "parse");
+    /// assert_eq!(saved.values[1], "12,x,3");
+    ///
+    /// let mut replay = saved
+    ///     .resume::<Vec<String>, Failure>(1, |s| Ok(s.split(',').map(String::from).collect()))
+    ///     .unwrap()
+    ///     .stage("parse", |parts: Vec<String>| Ok(parts.iter().filter_map(|p| p.parse().ok()).collect::<Vec<i64>>()))
+    ///     .stage("sum", |ns: Vec<i64>| Ok(ns.iter().sum::<i64>()));
+    /// assert_eq!(replay.replay_from(0).output(), Some(15));
+    /// ```
+    pub fn save<F>(&self, encode: F) -> Out<SavedRun>
+    where
+        E: Debug,
+        F: Fn(&dyn Any) -> Option<String>,
+    {
+        let values = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(n, value)| {
+                encode((**value).as_any()).ok_or_else(|| {
+                    let type_name = match self.stages.get(n) {
+                        Some((info, _, _)) => info.input_type,
+                        None => type_name::<O>(),
+                    };
+                    Failure::InvalidInput(format!("can't encode a captured {}", type_name))
+                })
+            })
+            .collect::<Out<Vec<_>>>()?;
+        Ok(SavedRun {
+            stages: self
+                .stages
+                .iter()
+                .map(|(info, _, _)| SavedStage {
+                    name: info.name.clone(),
+                    input_type: info.input_type.to_string(),
+                    output_type: info.output_type.to_string(),
+                })
+                .collect(),
+            values,
+            failed_at: self.failed_stage().map(|info| info.index),
+            error: self.error.as_ref().map(|e| format!("{:?}", e)),
+        })
+    }
+}
+
+/// a stage of a `SavedRun`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedStage {
+    pub name: String,
+    pub input_type: String,
+    pub output_type: String,
+}
+
+/// a `Recording` saved with `Recording::save`, read back with `Wire::from_wire`.
+/// it holds the captured values as text, not the stages, so replaying means
+/// resuming from a captured value and adding the stages again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedRun {
+    pub stages: Vec<SavedStage>,
+    /// the encoded value that entered each stage that ran, followed by the
+    /// output if every stage succeeded
+    pub values: Vec<String>,
+    pub failed_at: Option<usize>,
+    /// the `Debug` text of the error, if a stage failed
+    pub error: Option<String>,
+}
+
+impl SavedRun {
+    /// a new recording starting with the value that entered stage `index`,
+    /// decoded with `decode`. the stages from `index` on have to be added to
+    /// it again, fixed or not.
+    pub fn resume<I, E>(
+        &self,
+        index: usize,
+        decode: impl Fn(&str) -> Out<I>,
+    ) -> Out<Recording<I, E>>
+    where
+        I: Any + Debug + Clone,
+    {
+        let value = self.values.get(index).ok_or_else(|| {
+            Failure::InvalidInput(format!("stage {} never received a value", index))
+        })?;
+        if let Some(stage) = self.stages.get(index) {
+            if stage.input_type != type_name::<I>() {
+                return Err(Failure::InvalidInput(format!(
+                    "stage '{}' takes {}, not {}",
+                    stage.name,
+                    stage.input_type,
+                    type_name::<I>()
+                )));
+            }
+        }
+        Ok(Recording::input(decode(value)?))
+    }
+}
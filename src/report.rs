@@ -0,0 +1,167 @@
+use super::*;
+use crate::resilience::thread_retries;
+use std::any::type_name;
+
+/// what one stage did during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageReport {
+    pub name: String,
+    /// how often the stage ran: once for `then`, once per item for `for_each`
+    pub items: u64,
+    pub failures: u64,
+    pub duration: Duration,
+    /// retries made by `Retry` wrappers inside the stage
+    pub retries: u64,
+}
+
+/// a summary of a run: every reported stage in the order they were added,
+/// including the ones that never ran because an earlier one failed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RunReport {
+    pub stages: Vec<StageReport>,
+    /// the index of the first stage that failed
+    pub failed_at: Option<usize>,
+}
+
+impl RunReport {
+    pub fn total_duration(&self) -> Duration {
+        self.stages.iter().map(|s| s.duration).sum()
+    }
+
+    /// the stage that failed, if any
+    pub fn failed_stage(&self) -> Option<&StageReport> {
+        self.failed_at.and_then(|i| self.stages.get(i))
+    }
+}
+
+impl std::fmt::Display for RunReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, s) in self.stages.iter().enumerate() {
+            write!(
+                f,
+                "{}. {}: {} item(s), {} failure(s), {} retries, {:?}",
+                i, s.name, s.items, s.failures, s.retries, s.duration
+            )?;
+            if self.failed_at == Some(i) {
+                write!(f, " <- failed here")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "total: {:?}", self.total_duration())
+    }
+}
+
+/// collects a `RunReport` from the stages it wraps. use it as a layer and
+/// finish with `run_with_report`, or wrap acts with `stage` and read
+/// `report` afterwards. clones share the same report.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let (out, report) = Reactor::<Vec<i32>>::input(vec![1, 2, 3])
+///     .layer(Reporter::new())
+///     .then(|v: Vec<i32>| Ok(v.iter().sum::<i32>()))
+///     .then(|n: i32| Ok(n * 2))
+///     .run_with_report();
+/// assert_eq!(out.unwrap(), 12);
+/// assert_eq!(report.stages.len(), 2);
+/// assert_eq!(report.failed_at, None);
+/// ```
+#[derive(Clone, Default)]
+pub struct Reporter {
+    report: Rc<RefCell<RunReport>>,
+}
+
+impl Reporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// report on `act` under `name`
+    pub fn stage<A>(&self, name: &str, act: A) -> Reported<A> {
+        let mut report = self.report.borrow_mut();
+        report.stages.push(StageReport {
+            name: name.to_string(),
+            items: 0,
+            failures: 0,
+            duration: Duration::ZERO,
+            retries: 0,
+        });
+        Reported {
+            act,
+            index: report.stages.len() - 1,
+            report: self.report.clone(),
+        }
+    }
+
+    pub fn report(&self) -> RunReport {
+        self.report.borrow().clone()
+    }
+}
+
+/// an act whose runs are recorded by a `Reporter`. see `Reporter::stage`.
+#[derive(Clone)]
+pub struct Reported<A> {
+    act: A,
+    index: usize,
+    report: Rc<RefCell<RunReport>>,
+}
+
+impl<A, I, O, E> Act<I, O, E> for Reported<A>
+where
+    A: Act<I, O, E>,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let retries = thread_retries();
+        let start = Instant::now();
+        let out = self.act.act(input);
+        let elapsed = start.elapsed();
+
+        let mut report = self.report.borrow_mut();
+        let stage = &mut report.stages[self.index];
+        stage.items += 1;
+        stage.failures += out.is_err() as u64;
+        stage.duration += elapsed;
+        stage.retries += thread_retries() - retries;
+        if out.is_err() && report.failed_at.is_none() {
+            report.failed_at = Some(self.index);
+        }
+        out
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.act.label()
+    }
+}
+
+/// reports on every stage, naming each after its label or else its input and
+/// output types
+impl Layer for Reporter {
+    fn wrap<A, I, O, E>(&self, act: A) -> impl Act<I, O, E> + use<A, I, O, E>
+    where
+        A: Act<I, O, E>,
+        E: Debug,
+    {
+        let name = match act.label() {
+            Some(label) => label.to_string(),
+            None => format!(
+                "{}->{}",
+                short_type_name(type_name::<I>()),
+                short_type_name(type_name::<O>())
+            ),
+        };
+        self.stage(&name, act)
+    }
+}
+
+impl<I, E> Layered<I, Reporter, E>
+where
+    E: Debug,
+{
+    /// finish the chain like `run`, along with the report of every stage
+    pub fn run_with_report(self) -> (Out<I, E>, RunReport) {
+        let reporter = self.layer.clone();
+        (self.run(), reporter.report())
+    }
+}
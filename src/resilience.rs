@@ -0,0 +1,449 @@
+use super::*;
+use std::cell::Cell;
+use std::thread;
+
+thread_local! {
+    /// retries made on this thread, so reports can attribute them to stages
+    static THREAD_RETRIES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// the number of retries any `Retry` has made on the current thread
+pub(crate) fn thread_retries() -> u64 {
+    THREAD_RETRIES.with(|r| r.get())
+}
+
+/// how often and how patiently a failing act is retried. shared by `Retry`,
+/// `ClientConfig`, `StagePolicy` and `Reactor::then_retry_with_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// wait before the first retry
+    pub backoff: Duration,
+    /// what each wait is multiplied by for the next one
+    pub multiplier: f64,
+    /// the longest single wait, however many retries came before
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// retry up to `max_retries` times, waiting 100ms and doubling the wait
+    /// after each retry, up to a minute
+    pub fn new(max_retries: u32) -> Self {
+        RetryPolicy {
+            max_retries,
+            backoff: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// the wait after `delay`, clamped to `max_delay` rather than overflowing
+    fn next_delay(&self, delay: Duration) -> Duration {
+        Duration::try_from_secs_f64(delay.as_secs_f64() * self.multiplier)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+/// retries a failing act, waiting `backoff` before the first retry and
+/// multiplying the wait by `multiplier` after each one, never waiting longer
+/// than `max_delay`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::cell::Cell;
+/// use std::time::Duration;
+///
+/// let calls = Cell::new(0);
+/// let flaky = retry(
+///     |x: i32| {
+///         calls.set(calls.get() + 1);
+///         if calls.get() < 40 { Err(Failure::Custom("busy".into())) } else { Ok(x) }
+///     },
+///     100,
+///     Duration::from_nanos(1),
+/// )
+/// .multiplier(1e300)
+/// .max_delay(Duration::from_micros(10));
+/// assert_eq!(flaky.act(3).unwrap(), 3);
+/// assert_eq!(flaky.retries(), 39);
+/// ```
+pub struct Retry<A> {
+    act: A,
+    policy: RetryPolicy,
+    retries: Cell<u64>,
+}
+
+impl<A> Retry<A> {
+    pub fn new(act: A, max_retries: u32, backoff: Duration) -> Self {
+        Self::with_policy(act, RetryPolicy::new(max_retries).backoff(backoff))
+    }
+
+    pub fn with_policy(act: A, policy: RetryPolicy) -> Self {
+        Retry {
+            act,
+            policy,
+            retries: Cell::new(0),
+        }
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.policy.multiplier = multiplier;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.policy.max_delay = max_delay;
+        self
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// total number of retries made so far
+    pub fn retries(&self) -> u64 {
+        self.retries.get()
+    }
+}
+
+impl<A, I, O, E> Act<I, O, E> for Retry<A>
+where
+    A: Act<I, O, E>,
+    I: Clone,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let mut delay = self.policy.backoff.min(self.policy.max_delay);
+        let mut attempt = 0;
+        loop {
+            match self.act.act(input.clone()) {
+                Err(_) if attempt < self.policy.max_retries => {
+                    attempt += 1;
+                    self.retries.set(self.retries.get() + 1);
+                    THREAD_RETRIES.with(|r| r.set(r.get() + 1));
+                    thread::sleep(delay);
+                    delay = self.policy.next_delay(delay);
+                }
+                out => return out,
+            }
+        }
+    }
+}
+
+/// retry `act` up to `max_retries` times with exponential backoff
+pub fn retry<A>(act: A, max_retries: u32, backoff: Duration) -> Retry<A> {
+    Retry::new(act, max_retries, backoff)
+}
+
+/// retry `act` as `policy` says
+pub fn retry_with<A>(act: A, policy: RetryPolicy) -> Retry<A> {
+    Retry::with_policy(act, policy)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+/// stops calling an act after `threshold` consecutive failures, failing fast
+/// for `open_for` before letting a single trial call through again.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::time::Duration;
+///
+/// let breaker = circuit_breaker(|_: ()| Err::<(), _>(Failure::Custom("down".into())), 2, Duration::from_secs(60));
+/// assert!(breaker.act(()).is_err());
+/// assert!(!breaker.is_open());
+/// assert!(breaker.act(()).is_err());
+/// assert!(breaker.is_open());
+/// assert_eq!(breaker.act(()).unwrap_err().to_string(), "Custom error: circuit breaker is open");
+/// assert_eq!(breaker.rejected(), 1);
+/// ```
+pub struct CircuitBreaker<A> {
+    act: A,
+    threshold: u32,
+    open_for: Duration,
+    state: Cell<BreakerState>,
+    rejected: Cell<u64>,
+}
+
+impl<A> CircuitBreaker<A> {
+    pub fn new(act: A, threshold: u32, open_for: Duration) -> Self {
+        CircuitBreaker {
+            act,
+            threshold,
+            open_for,
+            state: Cell::new(BreakerState::Closed { failures: 0 }),
+            rejected: Cell::new(0),
+        }
+    }
+
+    /// true while calls are being rejected
+    pub fn is_open(&self) -> bool {
+        matches!(self.state.get(), BreakerState::Open { until } if Instant::now() < until)
+    }
+
+    /// number of calls rejected while open
+    pub fn rejected(&self) -> u64 {
+        self.rejected.get()
+    }
+}
+
+impl<A, I, O, E> Act<I, O, E> for CircuitBreaker<A>
+where
+    A: Act<I, O, E>,
+    E: Debug + From<Failure>,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        if let BreakerState::Open { until } = self.state.get() {
+            if Instant::now() < until {
+                self.rejected.set(self.rejected.get() + 1);
+                return Err(Failure::Custom("circuit breaker is open".to_string()).into());
+            }
+            self.state.set(BreakerState::HalfOpen);
+        }
+        let out = self.act.act(input);
+        let next = match (out.is_ok(), self.state.get()) {
+            (true, _) => BreakerState::Closed { failures: 0 },
+            (false, BreakerState::Closed { failures }) if failures + 1 < self.threshold => {
+                BreakerState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (false, _) => BreakerState::Open {
+                until: Instant::now() + self.open_for,
+            },
+        };
+        self.state.set(next);
+        out
+    }
+}
+
+/// open the circuit after `threshold` consecutive failures of `act`
+pub fn circuit_breaker<A>(act: A, threshold: u32, open_for: Duration) -> CircuitBreaker<A> {
+    CircuitBreaker::new(act, threshold, open_for)
+}
+
+/// a token bucket allowing `limit` calls per `per`, blocking the caller until
+/// a call is allowed.
+pub struct RateLimit<A> {
+    act: A,
+    limit: u32,
+    per: Duration,
+    bucket: Cell<(f64, Instant)>,
+}
+
+impl<A> RateLimit<A> {
+    pub fn new(act: A, limit: u32, per: Duration) -> Self {
+        assert!(limit > 0, "rate limit must allow at least one call");
+        RateLimit {
+            act,
+            limit,
+            per,
+            bucket: Cell::new((limit as f64, Instant::now())),
+        }
+    }
+
+    fn acquire(&self) {
+        let rate = self.limit as f64 / self.per.as_secs_f64();
+        let (tokens, last) = self.bucket.get();
+        let now = Instant::now();
+        let tokens =
+            (tokens + now.duration_since(last).as_secs_f64() * rate).min(self.limit as f64);
+        if tokens >= 1.0 {
+            self.bucket.set((tokens - 1.0, now));
+        } else {
+            let wait = Duration::from_secs_f64((1.0 - tokens) / rate);
+            thread::sleep(wait);
+            self.bucket.set((0.0, now + wait));
+        }
+    }
+}
+
+impl<A, I, O, E> Act<I, O, E> for RateLimit<A>
+where
+    A: Act<I, O, E>,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        self.acquire();
+        self.act.act(input)
+    }
+}
+
+/// allow at most `limit` calls of `act` per `per`
+pub fn rate_limit<A>(act: A, limit: u32, per: Duration) -> RateLimit<A> {
+    RateLimit::new(act, limit, per)
+}
+
+/// call counts and latency recorded by `Metered`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CallMetrics {
+    pub calls: u64,
+    pub failures: u64,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+}
+
+impl CallMetrics {
+    /// the average latency of the calls so far, zero before the first one
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::time::Duration;
+    ///
+    /// let metrics = CallMetrics {
+    ///     calls: u32::MAX as u64 + 2,
+    ///     total_latency: Duration::from_secs(u32::MAX as u64 + 2),
+    ///     ..CallMetrics::default()
+    /// };
+    /// assert_eq!(metrics.mean_latency(), Duration::from_secs(1));
+    /// assert_eq!(CallMetrics::default().mean_latency(), Duration::ZERO);
+    ///
+    /// let parse = metered(|s: &str| s.parse::<i32>().map_err(|e| Failure::InvalidInput(e.to_string())));
+    /// assert!(parse.act("1").is_ok());
+    /// assert!(parse.act("x").is_err());
+    /// assert_eq!((parse.metrics().calls, parse.metrics().failures), (2, 1));
+    /// ```
+    pub fn mean_latency(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total_latency.div_f64(self.calls as f64)
+        }
+    }
+}
+
+/// records call counts, failures and latency of an act.
+pub struct Metered<A> {
+    act: A,
+    metrics: Cell<CallMetrics>,
+}
+
+impl<A> Metered<A> {
+    pub fn new(act: A) -> Self {
+        Metered {
+            act,
+            metrics: Cell::new(CallMetrics::default()),
+        }
+    }
+
+    pub fn metrics(&self) -> CallMetrics {
+        self.metrics.get()
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.act
+    }
+}
+
+impl<A, I, O, E> Act<I, O, E> for Metered<A>
+where
+    A: Act<I, O, E>,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let start = Instant::now();
+        let out = self.act.act(input);
+        let elapsed = start.elapsed();
+        let mut m = self.metrics.get();
+        m.calls += 1;
+        m.failures += out.is_err() as u64;
+        m.total_latency += elapsed;
+        m.max_latency = m.max_latency.max(elapsed);
+        self.metrics.set(m);
+        out
+    }
+}
+
+/// record call metrics for `act`
+pub fn metered<A>(act: A) -> Metered<A> {
+    Metered::new(act)
+}
+
+/// turns `429` and `5xx` responses into failures, so the resilience wrappers
+/// treat them like transport errors.
+pub struct FailOnServerError<A> {
+    act: A,
+}
+
+impl<A, E> Act<Request, Response, E> for FailOnServerError<A>
+where
+    A: Act<Request, Response, E>,
+    E: Debug + From<Failure>,
+{
+    fn act(&self, input: Request) -> Out<Response, E> {
+        let response = self.act.act(input)?;
+        if response.status == 429 || response.status >= 500 {
+            return Err(Failure::Custom(format!(
+                "upstream responded with status {}",
+                response.status
+            ))
+            .into());
+        }
+        Ok(response)
+    }
+}
+
+/// settings for `resilient_client`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    pub retry: RetryPolicy,
+    /// consecutive failed calls (after retries) that open the circuit
+    pub failure_threshold: u32,
+    pub open_for: Duration,
+    /// at most this many requests per interval, if set
+    pub rate_limit: Option<(u32, Duration)>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            retry: RetryPolicy::new(3),
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+            rate_limit: None,
+        }
+    }
+}
+
+/// the act built by `resilient_client`: metrics around a circuit breaker around
+/// retries around the (optionally rate-limited) http act.
+pub type ResilientClient<A> = Metered<CircuitBreaker<Retry<RateLimit<FailOnServerError<A>>>>>;
+
+/// wrap an http-fetching act with retries, a circuit breaker, an optional rate
+/// limit and call metrics, all configured from one `ClientConfig`. `429` and
+/// `5xx` responses count as failures. read the metrics with `.metrics()`.
+pub fn resilient_client<A>(fetch: A, config: ClientConfig) -> ResilientClient<A> {
+    // without a configured limit the bucket is effectively unbounded
+    let (limit, per) = config
+        .rate_limit
+        .unwrap_or((u32::MAX, Duration::from_secs(1)));
+    metered(circuit_breaker(
+        retry_with(
+            rate_limit(FailOnServerError { act: fetch }, limit, per),
+            config.retry,
+        ),
+        config.failure_threshold,
+        config.open_for,
+    ))
+}
@@ -0,0 +1,127 @@
+use super::*;
+use std::collections::VecDeque;
+
+/// a `for_each_resumable` run that stopped at a failing item. it keeps the
+/// outputs completed so far and the items still to do, so the run can go on
+/// from the failure point instead of starting over.
+pub struct Interrupted<T, O, A, E = Failure> {
+    /// outputs of the items that succeeded, in order
+    pub done: Vec<O>,
+    pub error: E,
+    /// the item that failed, or `None` if the chain had already failed before
+    /// reaching this stage
+    pub item: Option<T>,
+    /// items after the failing one
+    pub remaining: VecDeque<T>,
+    transform: A,
+}
+
+fn run_items<T, O, A, E>(
+    mut done: Vec<O>,
+    mut items: VecDeque<T>,
+    transform: A,
+) -> Result<Vec<O>, Interrupted<T, O, A, E>>
+where
+    T: Clone,
+    A: Act<T, O, E>,
+    E: Debug,
+{
+    while let Some(item) = items.pop_front() {
+        match transform.act(item.clone()) {
+            Ok(o) => done.push(o),
+            Err(error) => {
+                return Err(Interrupted {
+                    done,
+                    error,
+                    item: Some(item),
+                    remaining: items,
+                    transform,
+                })
+            }
+        }
+    }
+    Ok(done)
+}
+
+impl<T, O, A, E> Interrupted<T, O, A, E>
+where
+    T: Clone,
+    A: Act<T, O, E>,
+    E: Debug,
+{
+    /// retry the failed item, then carry on with the rest
+    pub fn resume(mut self) -> Result<Vec<O>, Self> {
+        match self.item.take() {
+            Some(item) => {
+                self.remaining.push_front(item);
+                run_items(self.done, self.remaining, self.transform)
+            }
+            None => Err(self),
+        }
+    }
+
+    /// carry on with `item` in place of the one that failed
+    pub fn resume_with(mut self, item: T) -> Result<Vec<O>, Self> {
+        self.item = Some(item);
+        self.resume()
+    }
+
+    /// drop the failed item and carry on with the rest
+    pub fn skip(mut self) -> Result<Vec<O>, Self> {
+        if self.item.take().is_none() {
+            return Err(self);
+        }
+        run_items(self.done, self.remaining, self.transform)
+    }
+}
+
+impl<T, O, A, E: Debug> Debug for Interrupted<T, O, A, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Interrupted")
+            .field("done", &self.done.len())
+            .field("error", &self.error)
+            .field("remaining", &self.remaining.len())
+            .finish()
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// like `for_each`, but a failing item doesn't throw the finished work
+    /// away: the error comes with an `Interrupted` handle to resume from.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let parse = |s: &str| s.parse::<i32>().map_err(|e| Failure::InvalidInput(e.to_string()));
+    /// let interrupted = Reactor::<Vec<&str>>::input(vec!["1", "2", "x", "4"])
+    ///     .for_each_resumable(parse)
+    ///     .unwrap_err();
+    /// assert_eq!(interrupted.done, vec![1, 2]);
+    /// assert_eq!(interrupted.item, Some("x"));
+    ///
+    /// assert_eq!(interrupted.resume_with("3").unwrap(), vec![1, 2, 3, 4]);
+    /// ```
+    pub fn for_each_resumable<O, T>(
+        self,
+        transform: T,
+    ) -> Result<Vec<O>, Interrupted<I::Item, O, T, E>>
+    where
+        I: IntoIterator,
+        I::Item: Clone,
+        T: Act<I::Item, O, E>,
+    {
+        match self.run() {
+            Ok(items) => run_items(Vec::new(), items.into_iter().collect(), transform),
+            Err(error) => Err(Interrupted {
+                done: Vec::new(),
+                error,
+                item: None,
+                remaining: VecDeque::new(),
+                transform,
+            }),
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use super::*;
+use serde_json::{Map, Value};
+
+/// one place a value doesn't match its schema. `path` is a JSON pointer to the
+/// offending part of the value (`""` for the value itself).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.path.as_str() {
+            "" => write!(f, "{}", self.message),
+            path => write!(f, "{}: {}", path, self.message),
+        }
+    }
+}
+
+/// check `value` against a JSON Schema, returning every violation rather
+/// than stopping at the first.
+///
+/// the common keywords are checked: `type`, `enum`, `const`, `properties`,
+/// `required`, `additionalProperties`, `items`, `minItems`, `maxItems`,
+/// `uniqueItems`, `minLength`, `maxLength`, `minimum`, `maximum`,
+/// `exclusiveMinimum`, `exclusiveMaximum`, `multipleOf`, `allOf`, `anyOf`,
+/// `oneOf`, `not` and `$ref` to a definition in the same schema (`#/$defs/..`
+/// or `#/definitions/..`). any other keyword, such as `pattern` or `format`,
+/// is ignored.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "type": "object",
+///     "required": ["id", "tags"],
+///     "properties": {
+///         "id": {"type": "integer", "minimum": 1},
+///         "tags": {"type": "array", "items": {"type": "string"}}
+///     }
+/// });
+/// let violations = schema_violations(&schema, &json!({"id": 0, "tags": ["a", 2]}));
+/// assert_eq!(
+///     violations.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+///     ["/id: 0 is less than the minimum of 1", "/tags/1: expected string, got integer"]
+/// );
+/// assert!(schema_violations(&schema, &json!({"id": 3, "tags": []})).is_empty());
+/// ```
+pub fn schema_violations(schema: &Value, value: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    check(schema, schema, value, "", &mut violations);
+    violations
+}
+
+impl<E> Reactor<Value, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// fail unless the value matches `schema`. the failure is `InvalidInput`
+    /// with one field per violation, keyed by its path, and the full
+    /// `Vec<SchemaViolation>` as its payload; see `schema_violations` for the
+    /// keywords checked.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use serde_json::json;
+    ///
+    /// let schema = json!({
+    ///     "type": "object",
+    ///     "required": ["item", "qty"],
+    ///     "additionalProperties": false,
+    ///     "properties": {
+    ///         "item": {"type": "string", "minLength": 1},
+    ///         "qty": {"type": "integer", "minimum": 1}
+    ///     }
+    /// });
+    ///
+    /// let out = Reactor::<&str>::input(r#"{"item":"","note":"rush"}"#)
+    ///     .then(from_json::<serde_json::Value>())
+    ///     .validate_schema(&schema)
+    ///     .run();
+    /// let failure = out.unwrap_err();
+    /// assert!(matches!(failure.root(), Failure::InvalidInput(m) if m == "3 schema violations"));
+    /// assert_eq!(failure.field("/item"), Some("shorter than 1 characters"));
+    /// assert_eq!(failure.field("/note"), Some("property not allowed"));
+    /// let violations = failure.payload::<Vec<SchemaViolation>>().unwrap();
+    /// assert_eq!(violations[0].to_string(), "missing required property 'qty'");
+    ///
+    /// let out = Reactor::<serde_json::Value>::input(json!({"item": "pen", "qty": 2}))
+    ///     .validate_schema(&schema)
+    ///     .run();
+    /// assert!(out.is_ok());
+    /// ```
+    pub fn validate_schema(self, schema: &Value) -> Reactor<Value, E> {
+        Reactor {
+            input: self.input.and_then(|value| {
+                let violations = schema_violations(schema, &value);
+                if violations.is_empty() {
+                    return Ok(value);
+                }
+                let noun = match violations.len() {
+                    1 => "violation",
+                    _ => "violations",
+                };
+                let mut failure =
+                    Failure::InvalidInput(format!("{} schema {}", violations.len(), noun));
+                for v in &violations {
+                    failure = failure.with_field(&v.path, &v.message);
+                }
+                Err(failure.with_payload(violations).into())
+            }),
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        // a float with no fraction, like 2.0, is an integer too
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn violation(out: &mut Vec<SchemaViolation>, path: &str, message: impl Into<String>) {
+    out.push(SchemaViolation {
+        path: path.to_string(),
+        message: message.into(),
+    });
+}
+
+/// the `~` and `/` escapes of a JSON pointer segment
+fn pointer(path: &str, segment: &str) -> String {
+    format!("{}/{}", path, segment.replace('~', "~0").replace('/', "~1"))
+}
+
+/// the schema a local `$ref` points at
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+fn check(root: &Value, schema: &Value, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => return violation(out, path, "no value is allowed here"),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match resolve(root, reference) {
+            Some(target) => check(root, target, value, path, out),
+            None => violation(
+                out,
+                path,
+                format!("schema reference '{}' not found", reference),
+            ),
+        }
+        return;
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let names: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !names.is_empty() && !names.iter().any(|name| has_type(value, name)) {
+            violation(
+                out,
+                path,
+                format!("expected {}, got {}", names.join(" or "), type_name(value)),
+            );
+            // the other keywords would only restate the mismatch
+            return;
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            violation(
+                out,
+                path,
+                format!("{} is not one of {}", value, Value::from(allowed.clone())),
+            );
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            violation(out, path, format!("expected {}, got {}", expected, value));
+        }
+    }
+
+    match value {
+        Value::Number(n) => check_number(schema, n.as_f64().unwrap_or(f64::NAN), path, out),
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    violation(out, path, format!("shorter than {} characters", min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    violation(out, path, format!("longer than {} characters", max));
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if len < min {
+                    violation(out, path, format!("fewer than {} items", min));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if len > max {
+                    violation(out, path, format!("more than {} items", max));
+                }
+            }
+            if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+                if let Some(i) = (1..items.len()).find(|&i| items[..i].contains(&items[i])) {
+                    violation(out, path, format!("item {} repeats an earlier item", i));
+                }
+            }
+        }
+        _ => {}
+    }
+    match value {
+        Value::Object(object) => check_object(root, schema, object, path, out),
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(root, item_schema, item, &pointer(path, &i.to_string()), out);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    check_combined(root, schema, value, path, out);
+}
+
+fn check_number(schema: &Map<String, Value>, n: f64, path: &str, out: &mut Vec<SchemaViolation>) {
+    let mut fail = |message: String| violation(out, path, message);
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(min) = bound("minimum") {
+        if n < min {
+            fail(format!("{} is less than the minimum of {}", n, min));
+        }
+    }
+    if let Some(max) = bound("maximum") {
+        if n > max {
+            fail(format!("{} is more than the maximum of {}", n, max));
+        }
+    }
+    if let Some(min) = bound("exclusiveMinimum") {
+        if n <= min {
+            fail(format!("{} is not more than {}", n, min));
+        }
+    }
+    if let Some(max) = bound("exclusiveMaximum") {
+        if n >= max {
+            fail(format!("{} is not less than {}", n, max));
+        }
+    }
+    if let Some(step) = bound("multipleOf").filter(|step| *step > 0.0) {
+        if (n / step).fract() != 0.0 {
+            fail(format!("{} is not a multiple of {}", n, step));
+        }
+    }
+}
+
+fn check_object(
+    root: &Value,
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    out: &mut Vec<SchemaViolation>,
+) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                violation(out, path, format!("missing required property '{}'", name));
+            }
+        }
+    }
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (name, value) in object {
+        let item_path = pointer(path, name);
+        match (
+            properties.and_then(|p| p.get(name)),
+            schema.get("additionalProperties"),
+        ) {
+            (Some(property), _) => check(root, property, value, &item_path, out),
+            (None, Some(Value::Bool(false))) => violation(out, &item_path, "property not allowed"),
+            (None, Some(additional)) => check(root, additional, value, &item_path, out),
+            (None, None) => {}
+        }
+    }
+}
+
+fn check_combined(
+    root: &Value,
+    schema: &Map<String, Value>,
+    value: &Value,
+    path: &str,
+    out: &mut Vec<SchemaViolation>,
+) {
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub in all {
+            check(root, sub, value, path, out);
+        }
+    }
+    let matches = |sub: &Value| schema_matches(root, sub, value);
+    if let Some(Value::Array(any)) = schema.get("anyOf") {
+        if !any.iter().any(matches) {
+            violation(out, path, "matches none of the schemas in anyOf");
+        }
+    }
+    if let Some(Value::Array(one)) = schema.get("oneOf") {
+        match one.iter().filter(|sub| matches(sub)).count() {
+            1 => {}
+            0 => violation(out, path, "matches none of the schemas in oneOf"),
+            n => violation(
+                out,
+                path,
+                format!("matches {} of the schemas in oneOf, not exactly one", n),
+            ),
+        }
+    }
+    if let Some(not) = schema.get("not") {
+        if matches(not) {
+            violation(out, path, "matches the schema in not");
+        }
+    }
+}
+
+fn schema_matches(root: &Value, schema: &Value, value: &Value) -> bool {
+    let mut violations = Vec::new();
+    check(root, schema, value, "", &mut violations);
+    violations.is_empty()
+}
@@ -0,0 +1,184 @@
+use super::*;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::thread::{self, Scope, ScopedJoinHandle};
+
+thread_local! {
+    /// the cancellation flags of the task running on this thread and of every
+    /// scope and task it was started from
+    static CANCEL_FLAGS: RefCell<Vec<Arc<AtomicBool>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// true once the task running on this thread was cancelled, directly or
+/// because its scope (or an enclosing one) was. acts that run inside a
+/// `task_scope` can check this to stop early; outside of one it's always false.
+pub fn is_cancelled() -> bool {
+    CANCEL_FLAGS.with(|flags| {
+        flags
+            .borrow()
+            .iter()
+            .any(|f| f.load(AtomicOrdering::Relaxed))
+    })
+}
+
+/// the cancellation flags in effect on this thread, to hand to another
+pub(crate) fn cancel_flags() -> Vec<Arc<AtomicBool>> {
+    CANCEL_FLAGS.with(|f| f.borrow().clone())
+}
+
+pub(crate) fn set_cancel_flags(flags: Vec<Arc<AtomicBool>>) {
+    CANCEL_FLAGS.with(|f| *f.borrow_mut() = flags);
+}
+
+/// run `f` with `flag` also counted by `is_cancelled`
+pub(crate) fn with_cancel_flag<R>(flag: &Arc<AtomicBool>, f: impl FnOnce() -> R) -> R {
+    let flags = cancel_flags();
+    let mut inner = flags.clone();
+    inner.push(flag.clone());
+    set_cancel_flags(inner);
+    let out = f();
+    set_cancel_flags(flags);
+    out
+}
+
+/// where `task_scope` spawns tasks. every task is joined before the scope
+/// returns; see `task_scope`.
+pub struct TaskScope<'scope, 'env: 'scope> {
+    scope: &'scope Scope<'scope, 'env>,
+    /// this scope's flag followed by those inherited from the spawning task
+    flags: Vec<Arc<AtomicBool>>,
+}
+
+/// a task spawned in a `TaskScope`.
+pub struct ScopedTask<'scope, T, E> {
+    handle: ScopedJoinHandle<'scope, Out<T, E>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl<'scope, 'env> TaskScope<'scope, 'env> {
+    /// run `task` on its own thread. a task that fails cancels the rest of the
+    /// scope, so its siblings can give up early.
+    pub fn spawn<T, E, F>(&self, task: F) -> ScopedTask<'scope, T, E>
+    where
+        T: Send + 'scope,
+        E: Send + 'scope,
+        F: FnOnce() -> Out<T, E> + Send + 'scope,
+    {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut flags = self.flags.clone();
+        flags.push(cancel.clone());
+        let scope_flag = self.flags[0].clone();
+        let handle = self.scope.spawn(move || {
+            CANCEL_FLAGS.with(|f| *f.borrow_mut() = flags);
+            let out = task();
+            if out.is_err() {
+                scope_flag.store(true, AtomicOrdering::Relaxed);
+            }
+            out
+        });
+        ScopedTask { handle, cancel }
+    }
+
+    /// ask every task in the scope to stop
+    pub fn cancel(&self) {
+        self.flags[0].store(true, AtomicOrdering::Relaxed);
+    }
+
+    /// true once the scope, or one it was started from, was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.flags.iter().any(|f| f.load(AtomicOrdering::Relaxed))
+    }
+}
+
+impl<T, E> ScopedTask<'_, T, E> {
+    /// wait for the task and take its result. a panic in the task is resumed
+    /// on the joining thread.
+    pub fn join(self) -> Out<T, E> {
+        self.handle
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+
+    /// ask this task to stop. the scope still waits for it before returning.
+    pub fn cancel(&self) {
+        self.cancel.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+/// run `f` with a scope to spawn tasks in, and return only once every one of
+/// them has finished, so no work outlives the call. if `f` fails, or any task
+/// does, the remaining tasks are cancelled before they are waited for.
+/// threads can't be killed, so cancelled tasks stop only when they check
+/// `is_cancelled`; the results of tasks that weren't joined are dropped.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::time::Duration;
+///
+/// let out: Out<u64> = task_scope(|scope| {
+///     let slow = scope.spawn(|| {
+///         while !is_cancelled() {
+///             std::thread::sleep(Duration::from_millis(1));
+///         }
+///         Ok(0)
+///     });
+///     let broken = scope.spawn(|| Err::<u64, _>(Failure::Custom("down".into())));
+///     broken.join()?;
+///     slow.join()
+/// });
+/// // the failure cancelled the slow task, which was finished before returning
+/// assert!(out.is_err());
+/// ```
+pub fn task_scope<'env, R, E, F>(f: F) -> Out<R, E>
+where
+    F: for<'scope> FnOnce(&TaskScope<'scope, 'env>) -> Out<R, E>,
+{
+    let mut flags = vec![Arc::new(AtomicBool::new(false))];
+    CANCEL_FLAGS.with(|f| flags.extend(f.borrow().iter().cloned()));
+    thread::scope(|scope| {
+        let tasks = TaskScope { scope, flags };
+        let out = f(&tasks);
+        if out.is_err() {
+            tasks.cancel();
+        }
+        out
+    })
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// run `f` on the value with a `TaskScope` for spawning the work it splits
+    /// into; the stage only completes once every task spawned in it has.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let sums = Reactor::<Vec<u64>>::input((1..=100).collect())
+    ///     .then_scoped(|scope, items: Vec<u64>| {
+    ///         let tasks: Vec<_> = items
+    ///             .chunks(25)
+    ///             .map(|chunk| chunk.to_vec())
+    ///             .map(|chunk| scope.spawn(move || Ok(chunk.iter().sum::<u64>())))
+    ///             .collect();
+    ///         tasks.into_iter().map(|t| t.join()).collect::<Out<Vec<u64>>>()
+    ///     })
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(sums.iter().sum::<u64>(), 5050);
+    /// ```
+    pub fn then_scoped<O, F>(self, f: F) -> Reactor<O, E>
+    where
+        F: for<'scope, 'env> FnOnce(&TaskScope<'scope, 'env>, I) -> Out<O, E>,
+    {
+        Reactor {
+            input: self.input.and_then(|i| task_scope(|scope| f(scope, i))),
+        }
+    }
+}
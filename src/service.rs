@@ -0,0 +1,127 @@
+use super::*;
+use std::error::Error;
+use std::future::{poll_fn, Future, Ready};
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// a service's error, kept as the cause of the failure it became
+#[derive(Debug)]
+struct ServiceError(BoxError);
+
+impl std::fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ServiceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// the async act returned by `from_service`
+#[derive(Debug, Clone)]
+pub struct ServiceAct<S>(S);
+
+/// use a `tower::Service` as an async act, so a client built from tower
+/// middleware (timeouts, retries, rate limits) can be a stage of an
+/// `AsyncReactor`. as tower's `oneshot` does, every call clones the service,
+/// waits for it to be ready and calls it. an error from the service fails
+/// with `Custom`, keeping the error as its cause.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::future::{ready, Ready};
+/// use std::task::{Context, Poll};
+/// use tower_service::Service;
+///
+/// #[derive(Clone)]
+/// struct Inventory;
+///
+/// impl Service<&'static str> for Inventory {
+///     type Response = u32;
+///     type Error = std::io::Error;
+///     type Future = Ready<Result<u32, std::io::Error>>;
+///
+///     fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+///         Poll::Ready(Ok(()))
+///     }
+///
+///     fn call(&mut self, item: &'static str) -> Self::Future {
+///         ready(match item {
+///             "pen" => Ok(12),
+///             _ => Err(std::io::Error::other(format!("no stock record for {}", item))),
+///         })
+///     }
+/// }
+///
+/// let stock = from_service(Inventory);
+/// let out = block_on(AsyncReactor::input("pen").then(|i| stock.act_async(i)).run());
+/// assert_eq!(out.unwrap(), 12);
+///
+/// let failure = block_on(stock.act_async("ink")).unwrap_err();
+/// assert_eq!(failure.to_string(), "Custom error: service failed: no stock record for ink");
+/// ```
+pub fn from_service<S>(service: S) -> ServiceAct<S> {
+    ServiceAct(service)
+}
+
+impl<S, I> AsyncAct<I, S::Response> for ServiceAct<S>
+where
+    S: Service<I> + Clone,
+    S::Error: Into<BoxError>,
+{
+    fn act_async(&self, input: I) -> impl Future<Output = Out<S::Response>> {
+        let mut service = self.0.clone();
+        async move {
+            let failed = |e: S::Error| {
+                let e = ServiceError(e.into());
+                Failure::Custom(format!("service failed: {}", e)).with_cause(e)
+            };
+            poll_fn(|cx| service.poll_ready(cx)).await.map_err(failed)?;
+            service.call(input).await.map_err(failed)
+        }
+    }
+}
+
+/// a pipeline is a `tower::Service` that is always ready and runs each request
+/// to completion when it's called, so it can sit behind tower middleware. its
+/// error is the pipeline's own.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::future::poll_fn;
+/// use tower_service::Service;
+///
+/// let mut service = Pipeline::<String>::new("greet")
+///     .stage("trim", |s: String| Ok(s.trim().to_string()))
+///     .stage("greet", |s: String| match s.is_empty() {
+///         true => Err(Failure::InvalidInput("no name".into())),
+///         false => Ok(format!("hello, {}", s)),
+///     });
+///
+/// block_on(poll_fn(|cx| service.poll_ready(cx))).unwrap();
+/// assert_eq!(block_on(service.call(" ada ".to_string())).unwrap(), "hello, ada");
+/// assert!(block_on(service.call("  ".to_string())).is_err());
+/// ```
+impl<I, O, E> Service<I> for Pipeline<I, O, E>
+where
+    I: 'static,
+    O: 'static,
+    E: Debug + 'static,
+{
+    type Response = O;
+    type Error = E;
+    type Future = Ready<Out<O, E>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Out<(), E>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: I) -> Self::Future {
+        std::future::ready(self.run(request))
+    }
+}
@@ -0,0 +1,193 @@
+use super::*;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// fnv-1a, so file names stay the same across processes and compiler versions
+fn stable_hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn io_failure(what: &str, e: std::io::Error) -> Failure {
+    Failure::Custom(format!("shared cache: failed to {}: {}", what, e))
+}
+
+/// memoizes an act in a directory that several processes on the same host can
+/// share. each key gets a file with the encoded output; while one process
+/// computes a key it holds a file lock on it, so other processes asking for
+/// the same key wait and then read its result instead of computing it again.
+/// only successful outputs are stored.
+///
+/// a lock file only lives while its key is computed. a process that was still
+/// waiting on it when it's removed finds the finished entry; one that arrives
+/// just then takes a new lock, so at worst a key is computed twice, and each
+/// write replaces the entry whole.
+pub struct SharedCache<A, K, C, D> {
+    act: A,
+    dir: PathBuf,
+    key: K,
+    encode: C,
+    decode: D,
+    ttl: Option<Duration>,
+}
+
+impl<A, K, C, D> SharedCache<A, K, C, D> {
+    /// recompute entries older than `ttl`
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// remove every entry in the cache directory, along with lock and
+    /// temporary files left behind by processes that died mid-computation
+    pub fn clear(&self) -> Out<()> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(io_failure("list the cache directory", e)),
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|x| x == "entry" || x == "lock" || x == "tmp")
+            {
+                fs::remove_file(path).map_err(|e| io_failure("remove an entry", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// the stored text for `key`, if there is a fresh entry for it
+    fn lookup(&self, path: &PathBuf, key: &str) -> Option<String> {
+        if let Some(ttl) = self.ttl {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+            if SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default()
+                > ttl
+            {
+                return None;
+            }
+        }
+        let text = fs::read_to_string(path).ok()?;
+        // the first line holds the key, in case two keys hash alike
+        let (stored_key, value) = text.split_once('\n')?;
+        (stored_key == key.replace('\n', " ")).then(|| value.to_string())
+    }
+}
+
+impl<A, K, C, D, I, O, E> Act<I, O, E> for SharedCache<A, K, C, D>
+where
+    A: Act<I, O, E>,
+    K: Fn(&I) -> String,
+    C: Fn(&O) -> String,
+    D: Fn(&str) -> Out<O, E>,
+    E: Debug + From<Failure>,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let key = (self.key)(&input);
+        let name = format!("{:016x}", stable_hash(&key));
+        let path = self.dir.join(format!("{}.entry", name));
+        if let Some(value) = self.lookup(&path, &key) {
+            return (self.decode)(&value);
+        }
+
+        fs::create_dir_all(&self.dir).map_err(|e| io_failure("create the cache directory", e))?;
+        let lock_path = self.dir.join(format!("{}.lock", name));
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| io_failure("open a lock file", e))?;
+        lock.lock().map_err(|e| io_failure("lock an entry", e))?;
+        // another process may have finished this key while we waited
+        if let Some(value) = self.lookup(&path, &key) {
+            return (self.decode)(&value);
+        }
+
+        let computed = self.compute(input, &key, &name, &path);
+        // removed while still held, so nobody locks a file that is going away
+        let _ = fs::remove_file(&lock_path);
+        drop(lock);
+        computed
+    }
+}
+
+impl<A, K, C, D> SharedCache<A, K, C, D> {
+    fn compute<I, O, E>(&self, input: I, key: &str, name: &str, path: &PathBuf) -> Out<O, E>
+    where
+        A: Act<I, O, E>,
+        C: Fn(&O) -> String,
+        E: Debug + From<Failure>,
+    {
+        let output = self.act.act(input)?;
+        let tmp = self
+            .dir
+            .join(format!("{}.{}.tmp", name, std::process::id()));
+        let written = File::create(&tmp).and_then(|mut f| {
+            write!(f, "{}\n{}", key.replace('\n', " "), (self.encode)(&output))?;
+            f.sync_all()
+        });
+        written
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| io_failure("write an entry", e))?;
+        Ok(output)
+    }
+}
+
+/// cache the outputs of `act` in `dir`, shared with every process using the
+/// same directory. `key` identifies an input; `encode` and `decode` store an
+/// output as text.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let dir = std::env::temp_dir().join("chain_reaction-shared-cache-doc");
+/// let square = shared_cached(
+///     |x: u64| Ok(x * x),
+///     &dir,
+///     |x: &u64| x.to_string(),
+///     |y: &u64| y.to_string(),
+///     |s: &str| s.parse().map_err(|_| Failure::InvalidInput(s.to_string())),
+/// );
+/// square.clear().unwrap();
+/// assert_eq!(square.act(12).unwrap(), 144);
+/// // served from the cache directory this time
+/// assert_eq!(square.act(12).unwrap(), 144);
+///
+/// // no lock files are left behind
+/// let leftovers = std::fs::read_dir(&dir)
+///     .unwrap()
+///     .filter(|e| e.as_ref().unwrap().path().extension().is_some_and(|x| x == "lock"))
+///     .count();
+/// assert_eq!(leftovers, 0);
+///
+/// // and a stale one from a crashed process is cleared
+/// std::fs::write(dir.join("0123456789abcdef.lock"), "").unwrap();
+/// square.clear().unwrap();
+/// assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+/// ```
+pub fn shared_cached<A, K, C, D, P>(
+    act: A,
+    dir: P,
+    key: K,
+    encode: C,
+    decode: D,
+) -> SharedCache<A, K, C, D>
+where
+    P: Into<PathBuf>,
+{
+    SharedCache {
+        act,
+        dir: dir.into(),
+        key,
+        encode,
+        decode,
+        ttl: None,
+    }
+}
@@ -0,0 +1,221 @@
+use super::*;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// identifies a batch of output, usually the source offset it was read up to.
+/// batch ids must increase from one batch to the next.
+pub type BatchId = u64;
+
+/// records which batches have been fully written.
+pub trait CommitStore {
+    /// the last batch recorded as committed, if any
+    fn last_committed(&self) -> Out<Option<BatchId>>;
+
+    /// durably record `batch` as committed
+    fn commit(&mut self, batch: BatchId) -> Out<()>;
+}
+
+/// a sink that can write a batch more than once without duplicating it, e.g. by
+/// overwriting the previous attempt. this is what lets a batch that was written
+/// but never committed be safely redone after a restart.
+pub trait BatchSink<T> {
+    fn write_batch(&mut self, batch: BatchId, items: &[T]) -> Out<()>;
+}
+
+/// replace `path` with `contents` through a synced temp file and a rename,
+/// then sync the directory so the rename itself survives a crash
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    sync_parent(path)
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::File::open(dir)?.sync_all()
+}
+
+// directories can't be opened for syncing elsewhere; the rename is as
+// durable as the platform makes it
+#[cfg(not(unix))]
+fn sync_parent(_: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// keeps the last committed batch id in a file, replaced atomically on commit.
+pub struct FileCommitStore {
+    path: PathBuf,
+}
+
+impl FileCommitStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        FileCommitStore { path: path.into() }
+    }
+}
+
+impl CommitStore for FileCommitStore {
+    fn last_committed(&self) -> Out<Option<BatchId>> {
+        match fs::read_to_string(&self.path) {
+            Ok(s) => s.trim().parse().map(Some).map_err(|e| {
+                Failure::InvalidInput(format!("commit log {}: {}", self.path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Failure::Custom(format!(
+                "commit log {}: {}",
+                self.path.display(),
+                e
+            ))),
+        }
+    }
+
+    fn commit(&mut self, batch: BatchId) -> Out<()> {
+        write_atomically(&self.path, batch.to_string().as_bytes())
+            .map_err(|e| Failure::Custom(format!("commit log {}: {}", self.path.display(), e)))
+    }
+}
+
+/// writes each batch to its own file in `dir`, one rendered item per line.
+/// rewriting a batch replaces its file, so retries never duplicate output.
+pub struct DirSink<F> {
+    dir: PathBuf,
+    format: F,
+}
+
+impl<F> DirSink<F> {
+    pub fn new<P: Into<PathBuf>>(dir: P, format: F) -> Self {
+        DirSink {
+            dir: dir.into(),
+            format,
+        }
+    }
+
+    /// the file a batch is written to
+    pub fn batch_path(&self, batch: BatchId) -> PathBuf {
+        self.dir.join(format!("batch-{:020}.out", batch))
+    }
+}
+
+impl<T, F> BatchSink<T> for DirSink<F>
+where
+    F: Fn(&T) -> String,
+{
+    fn write_batch(&mut self, batch: BatchId, items: &[T]) -> Out<()> {
+        let mut contents = String::new();
+        for item in items {
+            contents.push_str(&(self.format)(item));
+            contents.push('\n');
+        }
+        let path = self.batch_path(batch);
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| write_atomically(&path, contents.as_bytes()))
+            .map_err(|e| Failure::Custom(format!("writing {}: {}", path.display(), e)))
+    }
+}
+
+/// pairs a sink with a commit store so each batch is emitted exactly once:
+/// batches at or below the last commit are skipped, and a batch is only
+/// recorded after the sink has written it. a crash in between leaves the batch
+/// uncommitted, and the idempotent sink overwrites it on the next attempt.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let dir = std::env::temp_dir().join(format!("exactly-once-{}", std::process::id()));
+/// let _ = std::fs::remove_dir_all(&dir);
+/// let open = || {
+///     ExactlyOnce::new(
+///         DirSink::new(&dir, |n: &i32| n.to_string()),
+///         FileCommitStore::new(dir.join("committed")),
+///     )
+/// };
+///
+/// let mut sink = open();
+/// assert_eq!(sink.resume_from().unwrap(), 0);
+/// let written = Reactor::<Vec<i32>>::input(vec![1, 2]).sink_exactly_once(&mut sink, 0).run();
+/// assert!(written.unwrap());
+///
+/// // after a restart the committed batch is skipped rather than written twice
+/// let mut sink = open();
+/// assert_eq!(sink.resume_from().unwrap(), 1);
+/// assert!(!sink.write(0, &[9]).unwrap());
+/// assert!(sink.write(1, &[3]).unwrap());
+///
+/// let (files, _) = sink.into_inner();
+/// assert_eq!(std::fs::read_to_string(files.batch_path(0)).unwrap(), "1\n2\n");
+/// assert_eq!(std::fs::read_to_string(files.batch_path(1)).unwrap(), "3\n");
+/// assert_eq!(std::fs::read_to_string(dir.join("committed")).unwrap(), "1");
+/// assert!(!dir.join("committed.tmp").exists());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct ExactlyOnce<S, C> {
+    sink: S,
+    store: C,
+}
+
+impl<S, C> ExactlyOnce<S, C>
+where
+    C: CommitStore,
+{
+    pub fn new(sink: S, store: C) -> Self {
+        ExactlyOnce { sink, store }
+    }
+
+    /// where a restarted pipeline should resume: the batch after the last commit
+    pub fn resume_from(&self) -> Out<BatchId> {
+        Ok(self.store.last_committed()?.map_or(0, |b| b + 1))
+    }
+
+    /// write and commit `batch`, returning false if it was already committed
+    pub fn write<T>(&mut self, batch: BatchId, items: &[T]) -> Out<bool>
+    where
+        S: BatchSink<T>,
+    {
+        if self
+            .store
+            .last_committed()?
+            .is_some_and(|last| batch <= last)
+        {
+            return Ok(false);
+        }
+        self.sink.write_batch(batch, items)?;
+        self.store.commit(batch)?;
+        Ok(true)
+    }
+
+    pub fn into_inner(self) -> (S, C) {
+        (self.sink, self.store)
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// emit the collection as `batch` through an exactly-once sink. passes on
+    /// true if the batch was written, false if it had already been committed.
+    pub fn sink_exactly_once<T, S, C>(
+        self,
+        sink: &mut ExactlyOnce<S, C>,
+        batch: BatchId,
+    ) -> Reactor<bool, E>
+    where
+        I: IntoIterator<Item = T>,
+        S: BatchSink<T>,
+        C: CommitStore,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let items: Vec<T> = i.into_iter().collect();
+                Ok(sink.write(batch, &items)?)
+            }),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::*;
+use std::fmt::Write;
+
+/// the longest snapshot kept by `snapshot` unless a limit is given
+pub const DEFAULT_SNAPSHOT_LIMIT: usize = 1024;
+
+/// the failure of a stage together with the value that entered it.
+#[derive(Debug)]
+pub struct StageSnapshot {
+    pub stage: String,
+    /// the `Debug` rendering of the input, cut off at the snapshot limit
+    pub input: String,
+    pub source: Failure,
+}
+
+/// keeps the `Debug` rendering of every input so that, if the stage fails, the
+/// value that entered it travels with the error as a `Failure::Snapshot`.
+/// the act consumes its input, so rendering happens before every call, but it
+/// stops at the limit: a large input costs no more than a small one.
+pub struct Snapshot<A> {
+    act: A,
+    stage: String,
+    limit: usize,
+}
+
+impl<A> Snapshot<A> {
+    /// cut snapshots off after `limit` bytes
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+/// a string that takes up to `limit` bytes and then refuses more, so `Debug`
+/// rendering stops there instead of building the rest
+struct Capped {
+    text: String,
+    limit: usize,
+}
+
+impl Write for Capped {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let room = self.limit - self.text.len();
+        if s.len() <= room {
+            self.text.push_str(s);
+            return Ok(());
+        }
+        let mut end = room;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.text.push_str(&s[..end]);
+        Err(std::fmt::Error)
+    }
+}
+
+impl<A, I, O> Act<I, O, Failure> for Snapshot<A>
+where
+    A: Act<I, O, Failure>,
+    I: Debug,
+{
+    fn act(&self, input: I) -> Out<O, Failure> {
+        let mut rendered = Capped {
+            text: String::new(),
+            limit: self.limit,
+        };
+        // stopping at the limit ends the rendering with an error
+        if write!(rendered, "{:?}", input).is_err() {
+            rendered.text.push_str("...");
+        }
+        self.act.act(input).map_err(|e| {
+            Failure::Snapshot(Box::new(StageSnapshot {
+                stage: self.stage.clone(),
+                input: rendered.text,
+                source: e,
+            }))
+        })
+    }
+}
+
+/// attach the input of `act` to its failures, under the stage name `stage`
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let parse = snapshot("parse", |s: String| {
+///     s.parse::<i32>()
+///         .map_err(|e| Failure::InvalidInput(e.to_string()))
+/// });
+/// match Reactor::input("12x".to_string()).then(parse).run() {
+///     Err(Failure::Snapshot(s)) => {
+///         assert_eq!(s.stage, "parse");
+///         assert_eq!(s.input, "\"12x\"");
+///     }
+///     other => panic!("unexpected {:?}", other),
+/// }
+///
+/// // big inputs are cut off at the limit
+/// let sum = snapshot("sum", |v: Vec<u32>| Err::<u32, _>(Failure::Custom("overflow".into()))).limit(8);
+/// let Err(Failure::Snapshot(s)) = sum.act(vec![1000; 100_000]) else { panic!() };
+/// assert_eq!(s.input, "[1000, 1...");
+/// ```
+pub fn snapshot<A>(stage: &str, act: A) -> Snapshot<A> {
+    Snapshot {
+        act,
+        stage: stage.to_string(),
+        limit: DEFAULT_SNAPSHOT_LIMIT,
+    }
+}
@@ -0,0 +1,111 @@
+use super::*;
+use crate::scope::{cancel_flags, set_cancel_flags};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// a branch running on its own, detached thread
+struct Branch<O, E> {
+    handle: JoinHandle<Out<O, E>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl<O, E> Branch<O, E> {
+    /// run `act` on its own thread, seeing this thread's cancellation flags
+    /// and one of its own through `is_cancelled`
+    fn spawn<I, T>(act: T, input: I) -> Self
+    where
+        I: Send + 'static,
+        O: Send + 'static,
+        E: Debug + Send + 'static,
+        T: Act<I, O, E> + Send + 'static,
+    {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut flags = cancel_flags();
+        flags.push(cancel.clone());
+        let handle = thread::spawn(move || {
+            set_cancel_flags(flags);
+            act.act(input)
+        });
+        Branch { handle, cancel }
+    }
+
+    /// wait for the branch; a panic in it is resumed here
+    fn join(self) -> Out<O, E> {
+        self.handle
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+
+    /// ask the branch to stop and stop caring about it. it keeps running in
+    /// the background until it checks `is_cancelled` or finishes.
+    fn abandon(self) {
+        self.cancel.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// like `if_else`, but both branches start on their own threads while the
+    /// condition is evaluated, and only the one the condition picks is waited
+    /// for, so the stage takes as long as the slower of the condition and the
+    /// chosen branch. the other branch is cancelled and left running in the
+    /// background: it stops early if it checks `is_cancelled`, otherwise it
+    /// runs to the end and its result is thrown away. this only pays off when
+    /// the condition is slow and the branches have no side effects.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// let out = Reactor::<u64>::input(7)
+    ///     .if_else_speculative(
+    ///         |n: &u64| {
+    ///             std::thread::sleep(Duration::from_millis(20)); // an expensive check
+    ///             n % 2 == 1
+    ///         },
+    ///         |n: u64| Ok(n * 3 + 1),
+    ///         |n: u64| {
+    ///             // a slow loser that never checks is_cancelled doesn't hold the stage up
+    ///             std::thread::sleep(Duration::from_secs(2));
+    ///             Ok(n / 2)
+    ///         },
+    ///     )
+    ///     .run();
+    /// assert!(matches!(out, Ok(Either::Left(22))));
+    /// assert!(start.elapsed() < Duration::from_secs(1));
+    /// ```
+    pub fn if_else_speculative<O1, O2, C, T1, T2>(
+        self,
+        condition: C,
+        true_transform: T1,
+        false_transform: T2,
+    ) -> Reactor<Either<O1, O2>, E>
+    where
+        I: Clone + Send + 'static,
+        O1: Send + 'static,
+        O2: Send + 'static,
+        E: Send + 'static,
+        C: Fn(&I) -> bool,
+        T1: Act<I, O1, E> + Send + 'static,
+        T2: Act<I, O2, E> + Send + 'static,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let left = Branch::spawn(true_transform, i.clone());
+                let right = Branch::spawn(false_transform, i.clone());
+                if condition(&i) {
+                    right.abandon();
+                    left.join().map(Either::Left)
+                } else {
+                    left.abandon();
+                    right.join().map(Either::Right)
+                }
+            }),
+        }
+    }
+}
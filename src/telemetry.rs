@@ -0,0 +1,207 @@
+use super::*;
+use crate::pipeline::stage_name;
+use opentelemetry::global::BoxedTracer;
+use opentelemetry::metrics::{Counter, Histogram};
+use opentelemetry::trace::{Span, Status, Tracer};
+use opentelemetry::{global, KeyValue};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn new_run_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!(
+        "{:x}-{:x}-{}",
+        nanos,
+        std::process::id(),
+        RUN_COUNTER.fetch_add(1, AtomicOrdering::Relaxed)
+    )
+}
+
+struct Instruments {
+    pipeline: String,
+    run_id: RefCell<String>,
+    tracer: BoxedTracer,
+    runs: Counter<u64>,
+    durations: Histogram<f64>,
+}
+
+/// a layer that reports every stage it wraps to OpenTelemetry, through the
+/// global tracer and meter providers. each stage run is a span named after
+/// the stage, carrying `pipeline.name`, `pipeline.run_id` and `stage.name`
+/// and marked as an error when the stage fails; it's a child of whatever span
+/// is current, so chains show up inside the request that ran them.
+///
+/// each stage run also adds to the `chain_reaction.stage.runs` counter and
+/// records its duration in seconds in the `chain_reaction.stage.duration`
+/// histogram, both with `pipeline.name`, `stage.name` and `outcome` (`ok` or
+/// `error`) but not the run id, which would give every run a series of its
+/// own.
+///
+/// the crate only uses the OpenTelemetry API: install an exporter, such as
+/// `opentelemetry-otlp`, in the application to send the data anywhere.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use opentelemetry::global;
+/// use opentelemetry::trace::Status;
+/// use opentelemetry_sdk::metrics::{InMemoryMetricExporter, SdkMeterProvider};
+/// use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+///
+/// let spans = InMemorySpanExporter::default();
+/// global::set_tracer_provider(
+///     SdkTracerProvider::builder()
+///         .with_simple_exporter(spans.clone())
+///         .build(),
+/// );
+/// let metrics = InMemoryMetricExporter::default();
+/// let meters = SdkMeterProvider::builder()
+///     .with_periodic_exporter(metrics.clone())
+///     .build();
+/// global::set_meter_provider(meters.clone());
+///
+/// let telemetry = Telemetry::new("orders");
+/// let checkout = Pipeline::<u32>::new("orders")
+///     .with_layer(telemetry.clone())
+///     .stage("reserve", |qty: u32| Ok(qty))
+///     .stage("charge", |qty: u32| match qty {
+///         0 => Err(Failure::InvalidInput("nothing to charge".into())),
+///         n => Ok(n * 250),
+///     })
+///     .into_pipeline();
+///
+/// assert_eq!(checkout.run(2).unwrap(), 500);
+/// let first_run = telemetry.run_id();
+/// telemetry.next_run();
+/// assert!(checkout.run(0).is_err());
+///
+/// let finished = spans.get_finished_spans().unwrap();
+/// let names: Vec<_> = finished.iter().map(|s| s.name.as_ref()).collect();
+/// assert_eq!(names, ["reserve", "charge", "reserve", "charge"]);
+/// let run_of = |i: usize| {
+///     finished[i]
+///         .attributes
+///         .iter()
+///         .find(|kv| kv.key.as_str() == "pipeline.run_id")
+///         .map(|kv| kv.value.to_string())
+///         .unwrap()
+/// };
+/// assert_eq!(run_of(0), first_run);
+/// assert_eq!(run_of(3), telemetry.run_id());
+/// assert_ne!(run_of(0), run_of(3));
+/// assert!(matches!(finished[3].status, Status::Error { .. }));
+///
+/// meters.force_flush().unwrap();
+/// let exported = metrics.get_finished_metrics().unwrap();
+/// let mut names: Vec<_> = exported
+///     .iter()
+///     .flat_map(|m| m.scope_metrics())
+///     .flat_map(|s| s.metrics())
+///     .map(|m| m.name().to_string())
+///     .collect();
+/// names.dedup();
+/// assert_eq!(names, ["chain_reaction.stage.runs", "chain_reaction.stage.duration"]);
+/// ```
+#[derive(Clone)]
+pub struct Telemetry {
+    instruments: Rc<Instruments>,
+}
+
+impl Telemetry {
+    /// report the stages of `pipeline`, starting a new run
+    pub fn new(pipeline: &str) -> Self {
+        let meter = global::meter("chain_reaction");
+        Telemetry {
+            instruments: Rc::new(Instruments {
+                pipeline: pipeline.to_string(),
+                run_id: RefCell::new(new_run_id()),
+                tracer: global::tracer("chain_reaction"),
+                runs: meter
+                    .u64_counter("chain_reaction.stage.runs")
+                    .with_description("stage runs, by outcome")
+                    .build(),
+                durations: meter
+                    .f64_histogram("chain_reaction.stage.duration")
+                    .with_description("how long stages took")
+                    .with_unit("s")
+                    .build(),
+            }),
+        }
+    }
+
+    /// the id spans are tagged with, shared by every clone
+    pub fn run_id(&self) -> String {
+        self.instruments.run_id.borrow().clone()
+    }
+
+    /// start a new run, for a pipeline that's built once and run many times:
+    /// stages from here on are tagged with a new run id, which is returned
+    pub fn next_run(&self) -> String {
+        let id = new_run_id();
+        *self.instruments.run_id.borrow_mut() = id.clone();
+        id
+    }
+}
+
+struct Reported<A> {
+    act: A,
+    stage: String,
+    instruments: Rc<Instruments>,
+}
+
+impl<A, I, O, E> Act<I, O, E> for Reported<A>
+where
+    A: Act<I, O, E>,
+    E: Debug,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let i = &self.instruments;
+        let mut span = i
+            .tracer
+            .span_builder(self.stage.clone())
+            .with_attributes([
+                KeyValue::new("pipeline.name", i.pipeline.clone()),
+                KeyValue::new("pipeline.run_id", i.run_id.borrow().clone()),
+                KeyValue::new("stage.name", self.stage.clone()),
+            ])
+            .start(&i.tracer);
+        let start = Instant::now();
+        let out = self.act.act(input);
+        let elapsed = start.elapsed();
+        if let Err(e) = &out {
+            span.set_status(Status::error(format!("{:?}", e)));
+        }
+        span.end();
+
+        let attributes = [
+            KeyValue::new("pipeline.name", i.pipeline.clone()),
+            KeyValue::new("stage.name", self.stage.clone()),
+            KeyValue::new("outcome", if out.is_ok() { "ok" } else { "error" }),
+        ];
+        i.runs.add(1, &attributes);
+        i.durations.record(elapsed.as_secs_f64(), &attributes);
+        out
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.act.label()
+    }
+}
+
+impl Layer for Telemetry {
+    fn wrap<A, I, O, E>(&self, act: A) -> impl Act<I, O, E> + use<A, I, O, E>
+    where
+        A: Act<I, O, E>,
+        E: Debug,
+    {
+        Reported {
+            stage: stage_name::<I, O, E, A>(&act),
+            act,
+            instruments: self.instruments.clone(),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use super::*;
+use crate::scope::{cancel_flags, set_cancel_flags, with_cancel_flag};
+use std::any::type_name;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+
+/// the details of `Failure::Timeout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedOut {
+    pub stage: String,
+    pub limit: Duration,
+}
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "stage '{}' ran past its limit of {:?}",
+            self.stage, self.limit
+        )
+    }
+}
+
+/// an act that fails with `Failure::Timeout` once it runs longer than its
+/// limit. see `ChainableAct::with_timeout`.
+///
+/// the act runs on its own thread, so the chain moves on as soon as the limit
+/// is up. threads can't be killed: the abandoned act is marked cancelled, so
+/// `is_cancelled` turns true inside it, and its output is dropped whenever it
+/// does finish.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::time::Duration;
+///
+/// let slow = |x: u64| {
+///     while !is_cancelled() {
+///         std::thread::sleep(Duration::from_millis(1));
+///     }
+///     Ok(x)
+/// };
+/// let out = Reactor::<u64>::input(1)
+///     .then(slow.named("poll").with_timeout(Duration::from_millis(20)))
+///     .run();
+/// assert_eq!(
+///     out.unwrap_err().to_string(),
+///     "Timed out: stage 'poll' ran past its limit of 20ms"
+/// );
+/// ```
+pub struct Timeout<A> {
+    act: Arc<A>,
+    stage: String,
+    limit: Duration,
+}
+
+impl<A> Timeout<A> {
+    pub(crate) fn new<I, O, E>(act: A, limit: Duration) -> Self
+    where
+        A: Act<I, O, E>,
+        E: Debug,
+    {
+        let stage = match act.label() {
+            Some(label) => label.to_string(),
+            None => format!(
+                "{}->{}",
+                short_type_name(type_name::<I>()),
+                short_type_name(type_name::<O>())
+            ),
+        };
+        Timeout {
+            act: Arc::new(act),
+            stage,
+            limit,
+        }
+    }
+}
+
+impl<A, I, O, E> Act<I, O, E> for Timeout<A>
+where
+    A: Act<I, O, E> + Send + Sync + 'static,
+    I: Send + 'static,
+    O: Send + 'static,
+    E: Debug + From<Failure> + Send + 'static,
+{
+    fn act(&self, input: I) -> Out<O, E> {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut flags = cancel_flags();
+        flags.push(cancel.clone());
+        let act = self.act.clone();
+        let worker = thread::spawn(move || {
+            set_cancel_flags(flags);
+            // the receiver is gone once the stage timed out
+            let _ = tx.send(act.act(input));
+        });
+        match rx.recv_timeout(self.limit) {
+            Ok(out) => out,
+            Err(RecvTimeoutError::Timeout) => {
+                cancel.store(true, AtomicOrdering::Relaxed);
+                Err(Failure::Timeout(Box::new(TimedOut {
+                    stage: self.stage.clone(),
+                    limit: self.limit,
+                }))
+                .into())
+            }
+            Err(RecvTimeoutError::Disconnected) => match worker.join() {
+                Err(panic) => panic::resume_unwind(panic),
+                Ok(()) => unreachable!("the worker sends before it exits"),
+            },
+        }
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.act.label()
+    }
+}
+
+/// a handle for cancelling a chain from another thread. clones share the same
+/// state. see `Reactor::cancellable`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(AtomicOrdering::Relaxed)
+    }
+
+    fn check<E: From<Failure>>(&self) -> Out<(), E> {
+        match self.is_cancelled() {
+            true => Err(Failure::Cancelled("the chain was cancelled".to_string()).into()),
+            false => Ok(()),
+        }
+    }
+}
+
+/// a reactor that stops between stages once its token is cancelled. see
+/// `Reactor::cancellable`.
+pub struct Cancellable<I, E = Failure> {
+    reactor: Reactor<I, E>,
+    token: CancellationToken,
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// check `token` before every stage from here on, failing with
+    /// `Failure::Cancelled` once it's cancelled. the stages also see it
+    /// through `is_cancelled`, so long ones can stop part way.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let token = CancellationToken::new();
+    /// let out = Reactor::<Vec<u32>>::input((1..=10).collect())
+    ///     .cancellable(&token)
+    ///     .for_each(|x: u32| {
+    ///         if x == 3 {
+    ///             // e.g. a shutdown requested from another thread
+    ///             token.cancel();
+    ///         }
+    ///         Ok(x)
+    ///     })
+    ///     .then(|v: Vec<u32>| Ok(v.len()))
+    ///     .run();
+    /// assert!(matches!(out, Err(Failure::Cancelled(_))));
+    /// ```
+    pub fn cancellable(self, token: &CancellationToken) -> Cancellable<I, E> {
+        Cancellable {
+            reactor: self,
+            token: token.clone(),
+        }
+    }
+}
+
+impl<I, E> Cancellable<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    pub fn then<O, T>(self, transform: T) -> Cancellable<O, E>
+    where
+        T: Act<I, O, E>,
+    {
+        let token = &self.token;
+        let reactor = self.reactor.and_then(|i| {
+            token.check()?;
+            with_cancel_flag(&token.flag, || transform.act(i))
+        });
+        Cancellable {
+            reactor,
+            token: self.token,
+        }
+    }
+
+    /// run `transform` on every item, checking the token before each one
+    pub fn for_each<O, T>(self, transform: T) -> Cancellable<Vec<O>, E>
+    where
+        I: IntoIterator,
+        T: Act<I::Item, O, E>,
+    {
+        let token = &self.token;
+        let reactor = self.reactor.and_then(|i| {
+            with_cancel_flag(&token.flag, || {
+                i.into_iter()
+                    .map(|item| {
+                        token.check()?;
+                        transform.act(item)
+                    })
+                    .collect()
+            })
+        });
+        Cancellable {
+            reactor,
+            token: self.token,
+        }
+    }
+
+    /// stop checking the token and continue with a plain reactor
+    pub fn into_reactor(self) -> Reactor<I, E> {
+        self.reactor
+    }
+
+    pub fn run(self) -> Out<I, E> {
+        self.reactor.run()
+    }
+}
@@ -0,0 +1,142 @@
+use super::*;
+use std::ops::{Add, Div, Mul, Sub};
+use uom::num_traits::{Num, Zero};
+use uom::si::{Dimension, Quantity, Units};
+use uom::Conversion;
+
+// the acts below take `uom` quantities, so adding a length to a duration, or
+// passing a speed where a length is expected, fails to compile instead of
+// quietly mixing numbers. quantities come out of and go into plain numbers
+// at the edges: `Length::new::<meter>(2.0)` and `length.get::<meter>()`.
+
+/// add `amount` to the quantity. it has to be of the same kind: a length to a
+/// length, a duration to a duration.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use uom::si::f64::{Length, Time, Velocity};
+/// use uom::si::length::{kilometer, meter};
+/// use uom::si::time::second;
+/// use uom::si::velocity::meter_per_second;
+///
+/// let speed = Reactor::<Length>::input(Length::new::<kilometer>(1.0))
+///     .then(add_quantity(Length::new::<meter>(200.0)))
+///     .then(divide_quantity(Time::new::<second>(60.0)))
+///     .run()
+///     .unwrap();
+/// let speed: Velocity = speed;
+/// assert_eq!(speed.get::<meter_per_second>(), 20.0);
+///
+/// let stalled = divide_quantity::<Length, Time>(Time::new::<second>(0.0)).act(Length::new::<meter>(5.0));
+/// assert!(matches!(stalled, Err(Failure::ArithmeticError(_))));
+/// ```
+///
+/// mixing kinds is a compile error:
+///
+/// ```compile_fail
+/// use chain_reaction::*;
+/// use uom::si::f64::{Length, Time};
+/// use uom::si::{length::meter, time::second};
+///
+/// let out = Reactor::<Length>::input(Length::new::<meter>(1.0))
+///     .then(add_quantity(Time::new::<second>(1.0)))
+///     .run();
+/// ```
+pub fn add_quantity<D, U, V>(
+    amount: Quantity<D, U, V>,
+) -> impl Fn(Quantity<D, U, V>) -> Out<Quantity<D, U, V>>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V>,
+    Quantity<D, U, V>: Add<Output = Quantity<D, U, V>> + Copy,
+{
+    move |q| Ok(q + amount)
+}
+
+/// take `amount`, of the same kind, off the quantity
+pub fn subtract_quantity<D, U, V>(
+    amount: Quantity<D, U, V>,
+) -> impl Fn(Quantity<D, U, V>) -> Out<Quantity<D, U, V>>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V>,
+    Quantity<D, U, V>: Sub<Output = Quantity<D, U, V>> + Copy,
+{
+    move |q| Ok(q - amount)
+}
+
+/// multiply the quantity by a plain number, keeping its kind
+pub fn scale_quantity<D, U, V>(factor: V) -> impl Fn(Quantity<D, U, V>) -> Out<Quantity<D, U, V>>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V> + Copy,
+    Quantity<D, U, V>: Mul<V, Output = Quantity<D, U, V>>,
+{
+    move |q| Ok(q * factor)
+}
+
+/// multiply by another quantity, giving one of the derived kind: a force by a
+/// length is energy
+pub fn multiply_quantity<Q, R>(by: R) -> impl Fn(Q) -> Out<Q::Output>
+where
+    Q: Mul<R>,
+    R: Copy,
+{
+    move |q| Ok(q * by)
+}
+
+/// divide by another quantity, giving one of the derived kind: a length by a
+/// time is a velocity. dividing by zero fails with `ArithmeticError` rather
+/// than giving an infinite quantity.
+pub fn divide_quantity<Q, R>(by: R) -> impl Fn(Q) -> Out<Q::Output>
+where
+    Q: Div<R>,
+    R: Zero + Copy,
+{
+    move |q| match by.is_zero() {
+        true => Err(Failure::ArithmeticError(
+            "division by a zero quantity".to_string(),
+        )),
+        false => Ok(q / by),
+    }
+}
+
+/// fail with `InvalidInput` unless the quantity is within `min..=max`, for
+/// rejecting readings outside what a sensor can measure
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use uom::si::f64::ThermodynamicTemperature as Temperature;
+/// use uom::si::thermodynamic_temperature::degree_celsius;
+///
+/// let plausible = quantity_within(
+///     Temperature::new::<degree_celsius>(-40.0),
+///     Temperature::new::<degree_celsius>(85.0),
+/// );
+/// assert!(plausible.act(Temperature::new::<degree_celsius>(21.5)).is_ok());
+/// assert!(matches!(
+///     plausible.act(Temperature::new::<degree_celsius>(300.0)),
+///     Err(Failure::InvalidInput(_))
+/// ));
+/// ```
+pub fn quantity_within<D, U, V>(
+    min: Quantity<D, U, V>,
+    max: Quantity<D, U, V>,
+) -> impl Fn(Quantity<D, U, V>) -> Out<Quantity<D, U, V>>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V>,
+    Quantity<D, U, V>: PartialOrd + Debug,
+{
+    move |q| match min <= q && q <= max {
+        true => Ok(q),
+        false => Err(Failure::InvalidInput(format!(
+            "{:?} is outside {:?} to {:?}",
+            q, min, max
+        ))),
+    }
+}
@@ -0,0 +1,86 @@
+use super::*;
+
+/// invariants a type checks about itself, so values can be validated as a
+/// stage of a chain with `Reactor::validated`.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// #[derive(Debug)]
+/// struct Order {
+///     quantity: u32,
+/// }
+///
+/// impl Validate for Order {
+///     fn validate(&self) -> Out<()> {
+///         if self.quantity == 0 {
+///             return Err(Failure::InvalidInput("an order needs a quantity".into()));
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let out = Reactor::<Order>::input(Order { quantity: 0 }).validated().run();
+/// assert_eq!(out.unwrap_err().to_string(), "Invalid input: an order needs a quantity");
+/// ```
+pub trait Validate<E = Failure> {
+    fn validate(&self) -> Out<(), E>;
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// fail with `error` unless `check` holds for the value
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let out = Reactor::<i32>::input(-3)
+    ///     .ensure(|x| *x >= 0, |x| Failure::InvalidInput(format!("{} is negative", x)))
+    ///     .then(|x: i32| Ok(x * 2))
+    ///     .run();
+    /// assert_eq!(out.unwrap_err().to_string(), "Invalid input: -3 is negative");
+    /// ```
+    pub fn ensure<P, F>(self, check: P, error: F) -> Reactor<I, E>
+    where
+        P: FnOnce(&I) -> bool,
+        F: FnOnce(&I) -> E,
+    {
+        Reactor {
+            input: self.input.and_then(|i| match check(&i) {
+                true => Ok(i),
+                false => Err(error(&i)),
+            }),
+        }
+    }
+
+    /// keep only the items `keep` holds for, dropping the rest without failing
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let evens = Reactor::<Vec<i32>>::input((1..=6).collect())
+    ///     .filter(|x| x % 2 == 0)
+    ///     .for_each(|x: i32| Ok(x * 10))
+    ///     .run();
+    /// assert_eq!(evens.unwrap(), [20, 40, 60]);
+    /// ```
+    pub fn filter<P>(self, keep: P) -> Reactor<Vec<I::Item>, E>
+    where
+        I: IntoIterator,
+        P: FnMut(&I::Item) -> bool,
+    {
+        self.map(|i| i.into_iter().filter(keep).collect())
+    }
+
+    /// fail unless the value passes its own `Validate` checks
+    pub fn validated(self) -> Reactor<I, E>
+    where
+        I: Validate<E>,
+    {
+        Reactor {
+            input: self.input.and_then(|i| i.validate().map(|_| i)),
+        }
+    }
+}
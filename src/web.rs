@@ -0,0 +1,389 @@
+use super::*;
+use std::str::FromStr;
+
+/// a minimal, framework-agnostic http request, so handler bodies can be written
+/// as chains. adapt your framework's request into this at the edge.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Search {
+///     term: String,
+///     page: u32,
+/// }
+///
+/// let handler = |req: Request| {
+///     Reactor::input(req)
+///         .then(require_header("x-api-key"))
+///         .then(from_query())
+///         .then(|q: Query| Ok(Search { term: q.get("q")?, page: q.optional("page")?.unwrap_or(1) }))
+///         .then(|s: Search| Ok(format!("{} results for '{}' on page {}", 0, s.term, s.page)))
+///         .then(text_response(200))
+///         .run()
+///         .unwrap_or_else(|e| Response::from_failure(&e))
+/// };
+///
+/// let ok = handler(Request::new("GET", "/search?q=red%20shoes&page=2").with_header("X-Api-Key", "k"));
+/// assert_eq!(ok.status, 200);
+/// assert_eq!(ok.body, b"0 results for 'red shoes' on page 2");
+/// assert_eq!(ok.header("Content-Type"), Some("text/plain; charset=utf-8"));
+///
+/// assert_eq!(handler(Request::new("GET", "/search?q=x")).status, 400);
+/// let bad_page = handler(Request::new("GET", "/search?q=x&page=two").with_header("x-api-key", "k"));
+/// assert_eq!(bad_page.status, 400);
+/// assert!(String::from_utf8(bad_page.body).unwrap().contains("invalid query parameter 'page'"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// the raw query string, without the leading `?`
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: &str, path: &str) -> Self {
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// the first value of header `name`, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// a minimal http response, built by the response acts at the end of a chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// the first value of header `name`, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// the response to send when a chain fails with `failure`. client errors
+    /// (`400`, `401`) carry the failure's message; a `500` only says that
+    /// something went wrong, and the failure is written to stderr instead.
+    /// use `from_failure_with` to log it elsewhere.
+    pub fn from_failure(failure: &Failure) -> Self {
+        Self::from_failure_with(failure, |f| eprintln!("internal error: {}", f))
+    }
+
+    /// like `from_failure`, handing the failure behind a `500` to `log`
+    /// rather than to the client
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let mut logged = Vec::new();
+    /// let failure = Failure::Custom("db at 10.0.0.5 down".into());
+    /// let response = Response::from_failure_with(&failure, |f| logged.push(f.to_string()));
+    /// assert_eq!(response.status, 500);
+    /// assert_eq!(response.body, b"Internal Server Error");
+    /// assert_eq!(logged, ["Custom error: db at 10.0.0.5 down"]);
+    ///
+    /// let bad = Response::from_failure_with(&Failure::InvalidInput("bad id".into()), |_| unreachable!());
+    /// assert_eq!(bad.body, b"Invalid input: bad id");
+    /// ```
+    pub fn from_failure_with<L: FnOnce(&Failure)>(failure: &Failure, log: L) -> Self {
+        let status = match failure.root() {
+            Failure::InvalidInput(_) => 400,
+            Failure::Unauthorized(_) => 401,
+            _ => 500,
+        };
+        let body = if status >= 500 {
+            log(failure);
+            "Internal Server Error".to_string()
+        } else {
+            failure.to_string()
+        };
+        Response::new(status)
+            .with_header("content-type", "text/plain; charset=utf-8")
+            .with_body(body)
+    }
+}
+
+/// decoded query string parameters, in order of appearance.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub params: Vec<(String, String)>,
+}
+
+impl Query {
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let q = Query::parse("a=1&b=x+y%21&flag&a=2&bad=%zz&&");
+    /// assert_eq!(q.raw("a"), Some("1"));
+    /// assert_eq!(q.raw("b"), Some("x y!"));
+    /// assert_eq!(q.raw("flag"), Some(""));
+    /// assert_eq!(q.raw("bad"), Some("%zz"));
+    /// assert_eq!(q.params.len(), 5);
+    /// assert_eq!(q.optional::<u8>("missing").unwrap(), None);
+    /// ```
+    pub fn parse(query: &str) -> Self {
+        let params = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                (percent_decode(k), percent_decode(v))
+            })
+            .collect();
+        Query { params }
+    }
+
+    /// the raw value of parameter `name`, if present
+    pub fn raw(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// parameter `name` parsed as `T`, failing if it's missing or malformed
+    pub fn get<T>(&self, name: &str) -> Out<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let raw = self
+            .raw(name)
+            .ok_or_else(|| Failure::InvalidInput(format!("missing query parameter '{}'", name)))?;
+        raw.parse().map_err(|e| {
+            Failure::InvalidInput(format!("invalid query parameter '{}': {}", name, e))
+        })
+    }
+
+    /// like `get`, but a missing parameter is `None` rather than an error
+    pub fn optional<T>(&self, name: &str) -> Out<Option<T>>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match self.raw(name) {
+            Some(_) => self.get(name).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(h), Some(l)) => {
+                        out.push((h * 16 + l) as u8);
+                        i += 2;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// pass the request through only if it carries header `name`
+pub fn require_header(name: &str) -> impl Fn(Request) -> Out<Request> {
+    let name = name.to_string();
+    move |req| match req.header(&name) {
+        Some(_) => Ok(req),
+        None => Err(Failure::InvalidInput(format!(
+            "missing required header '{}'",
+            name
+        ))),
+    }
+}
+
+/// extract the decoded query parameters
+pub fn from_query() -> impl Fn(Request) -> Out<Query> {
+    |req| Ok(Query::parse(&req.query))
+}
+
+/// extract the body as utf-8 text
+pub fn body_text() -> impl Fn(Request) -> Out<String> {
+    |req| {
+        String::from_utf8(req.body)
+            .map_err(|e| Failure::InvalidInput(format!("request body is not utf-8: {}", e)))
+    }
+}
+
+/// extract the body with `parse`, for formats other than json
+pub fn from_body<T, P>(parse: P) -> impl Fn(Request) -> Out<T>
+where
+    P: Fn(&[u8]) -> Out<T>,
+{
+    move |req| parse(&req.body)
+}
+
+/// extract a json body into a `T`. requests without a json `content-type`,
+/// with a body that isn't utf-8, or with json that doesn't fit `T` fail with
+/// `InvalidInput`, i.e. `400`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct NewItem {
+///     name: String,
+/// }
+///
+/// let created = Reactor::<Request>::input(
+///     Request::new("POST", "/items")
+///         .with_header("content-type", "application/json")
+///         .with_body(r#"{"name":"pen"}"#),
+/// )
+/// .then(from_json_body::<NewItem>())
+/// .then(|item: NewItem| Ok(item.name))
+/// .then(respond_with(201, "application/json", |name: String| Ok(to_json().act(name)?.into_bytes())))
+/// .run()
+/// .unwrap();
+/// assert_eq!(created.body, br#""pen""#);
+///
+/// let json = |body: &str| Request::new("POST", "/items").with_header("content-type", "application/json").with_body(body);
+/// let failure = from_json_body::<NewItem>()(json(r#"{"title":"pen"}"#)).unwrap_err();
+/// assert_eq!(Response::from_failure(&failure).status, 400);
+/// ```
+#[cfg(feature = "serde")]
+pub fn from_json_body<T: serde::de::DeserializeOwned>() -> impl Fn(Request) -> Out<T> {
+    from_json_body_with(|s: &str| from_json().act(s))
+}
+
+/// extract a json body, decoded from its text with `decode`, for when the
+/// `serde` feature is off or `T` needs a decoder of its own. requests without
+/// a json `content-type` or with a body that isn't utf-8 fail with
+/// `InvalidInput`, i.e. `400`.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let extract = from_json_body_with(|s: &str| s.trim().parse::<i64>().map_err(|e| Failure::InvalidInput(e.to_string())));
+/// let json = |body: &[u8]| Request::new("POST", "/n").with_header("content-type", "application/json; charset=utf-8").with_body(body);
+///
+/// assert_eq!(extract(json(b" 42 ")).unwrap(), 42);
+/// assert!(extract(json(b"\xff")).is_err());
+/// assert!(extract(json(b"x")).is_err());
+/// let failure = extract(Request::new("POST", "/n").with_body("42")).unwrap_err();
+/// assert_eq!(Response::from_failure(&failure).status, 400);
+/// ```
+pub fn from_json_body_with<T, D>(decode: D) -> impl Fn(Request) -> Out<T>
+where
+    D: Fn(&str) -> Out<T>,
+{
+    move |req| {
+        let json = req.header("content-type").is_some_and(|t| {
+            let t = t.split(';').next().unwrap_or("").trim();
+            t.eq_ignore_ascii_case("application/json") || t.to_ascii_lowercase().ends_with("+json")
+        });
+        if !json {
+            return Err(Failure::InvalidInput(
+                "expected a json request body".to_string(),
+            ));
+        }
+        let text = std::str::from_utf8(&req.body)
+            .map_err(|e| Failure::InvalidInput(format!("request body is not utf-8: {}", e)))?;
+        decode(text)
+    }
+}
+
+/// respond with `status` and the value rendered as plain text
+pub fn text_response<T>(status: u16) -> impl Fn(T) -> Out<Response>
+where
+    T: std::fmt::Display,
+{
+    move |value| {
+        Ok(Response::new(status)
+            .with_header("content-type", "text/plain; charset=utf-8")
+            .with_body(value.to_string()))
+    }
+}
+
+/// respond with `status`, rendering the value with `render` (with the
+/// `serde` feature, e.g. `|v| Ok(to_json().act(v)?.into_bytes())`, as in
+/// `from_json_body`) and labelling it with `content_type`
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let created = Reactor::<Request>::input(Request::new("POST", "/items").with_body("pen"))
+///     .then(body_text())
+///     .then(respond_with(201, "application/json", |name: String| Ok(format!("{{\"name\":\"{}\"}}", name).into_bytes())))
+///     .run()
+///     .unwrap();
+/// assert_eq!(created.status, 201);
+/// assert_eq!(created.header("content-type"), Some("application/json"));
+/// assert_eq!(created.body, br#"{"name":"pen"}"#);
+///
+/// let denied = Response::from_failure(&Failure::Unauthorized("no token".into()).with_field("user", 7));
+/// assert_eq!(denied.status, 401);
+/// assert_eq!(Response::from_failure(&Failure::Custom("db down".into())).status, 500);
+/// ```
+pub fn respond_with<T, R>(status: u16, content_type: &str, render: R) -> impl Fn(T) -> Out<Response>
+where
+    R: Fn(T) -> Out<Vec<u8>>,
+{
+    let content_type = content_type.to_string();
+    move |value| {
+        Ok(Response::new(status)
+            .with_header("content-type", &content_type)
+            .with_body(render(value)?))
+    }
+}
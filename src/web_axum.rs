@@ -0,0 +1,190 @@
+use super::*;
+use axum::extract::{Path, Query as AxumQuery};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::thread;
+use tokio::sync::oneshot;
+
+type Job<Req, Resp> = (Req, oneshot::Sender<Out<Resp>>);
+
+/// what the closures from `PipelineHandler::json`, `query` and `path` return
+pub type HandlerFuture = Pin<Box<dyn Future<Output = axum::response::Response> + Send>>;
+
+/// a `Pipeline<Req, Resp>` served as axum handlers. the request is extracted
+/// into `Req` from the json body (`json`), the query string (`query`) or the
+/// path parameters (`path`), run through the pipeline, and `Resp` is sent back
+/// as json. a failure becomes the response `Response::from_failure` gives it:
+/// `400` or `401` with the message, or a bare `500`.
+///
+/// pipelines aren't `Send`, so the one behind a handler is built by `build` on
+/// a thread of its own and runs the requests there, one at a time. a stage
+/// that panics fails only its own request.
+///
+/// ```rust
+/// use axum::extract::{Json, Path};
+/// use axum::routing::{get, post};
+/// use axum::Router;
+/// use chain_reaction::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct NewOrder {
+///     item: String,
+///     qty: u32,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Quote {
+///     item: String,
+///     total: u32,
+/// }
+///
+/// let quotes = PipelineHandler::new(|| {
+///     Pipeline::<NewOrder>::new("quote")
+///         .stage("check", |o: NewOrder| match o.qty {
+///             0 => Err(Failure::InvalidInput("qty must be positive".into())),
+///             _ => Ok(o),
+///         })
+///         .stage("price", |o: NewOrder| Ok(Quote { total: o.qty * 250, item: o.item }))
+/// });
+/// let orders = PipelineHandler::new(|| {
+///     Pipeline::<u32>::new("order").stage("look up", |id: u32| Ok(format!("order {}", id)))
+/// });
+/// let app: Router = Router::new()
+///     .route("/quotes", post(quotes.json()))
+///     .route("/orders/{id}", get(orders.path()));
+///
+/// let body = |response: axum::response::Response| {
+///     let bytes = block_on(axum::body::to_bytes(response.into_body(), 1024)).unwrap();
+///     String::from_utf8(bytes.to_vec()).unwrap()
+/// };
+///
+/// let ok = block_on(quotes.json()(Json(NewOrder { item: "pen".into(), qty: 4 })));
+/// assert_eq!(ok.status(), 200);
+/// assert_eq!(body(ok), r#"{"item":"pen","total":1000}"#);
+///
+/// let bad = block_on(quotes.json()(Json(NewOrder { item: "pen".into(), qty: 0 })));
+/// assert_eq!(bad.status(), 400);
+/// assert_eq!(body(bad), "Invalid input: qty must be positive [step=check]");
+///
+/// assert_eq!(body(block_on(orders.path()(Path(7)))), r#""order 7""#);
+/// ```
+pub struct PipelineHandler<Req, Resp> {
+    jobs: mpsc::Sender<Job<Req, Resp>>,
+}
+
+impl<Req, Resp> Clone for PipelineHandler<Req, Resp> {
+    fn clone(&self) -> Self {
+        PipelineHandler {
+            jobs: self.jobs.clone(),
+        }
+    }
+}
+
+impl<Req, Resp> PipelineHandler<Req, Resp>
+where
+    Req: Send + 'static,
+    Resp: Serialize + Send + 'static,
+{
+    /// start the thread that builds the pipeline with `build` and runs it. the
+    /// thread ends once the handler and all its clones are dropped.
+    pub fn new<B>(build: B) -> Self
+    where
+        B: FnOnce() -> Pipeline<Req, Resp> + Send + 'static,
+    {
+        let (jobs, requests) = mpsc::channel::<Job<Req, Resp>>();
+        thread::spawn(move || {
+            let pipeline = build();
+            for (request, reply) in requests {
+                let out =
+                    catch_unwind(AssertUnwindSafe(|| pipeline.run(request))).unwrap_or_else(|_| {
+                        Err(Failure::Custom(format!(
+                            "pipeline '{}' panicked",
+                            pipeline.name()
+                        )))
+                    });
+                // the client may have gone away; nothing to do about it
+                let _ = reply.send(out);
+            }
+        });
+        PipelineHandler { jobs }
+    }
+
+    /// run the pipeline on `request` and build the response
+    pub async fn respond(&self, request: Req) -> axum::response::Response {
+        let (reply, answer) = oneshot::channel();
+        let out = match self.jobs.send((request, reply)) {
+            Ok(()) => answer.await.unwrap_or_else(|_| Err(stopped())),
+            Err(_) => Err(stopped()),
+        };
+        match out {
+            Ok(response) => Json(response).into_response(),
+            Err(failure) => Response::from_failure(&failure).into_response(),
+        }
+    }
+
+    fn handler<X: 'static>(
+        &self,
+        input: fn(X) -> Req,
+    ) -> impl Fn(X) -> HandlerFuture + Clone + Send + Sync + 'static {
+        let this = self.clone();
+        move |extracted| {
+            let (this, request) = (this.clone(), input(extracted));
+            Box::pin(async move { this.respond(request).await })
+        }
+    }
+
+    /// a handler taking `Req` from a json body
+    pub fn json(&self) -> impl Fn(Json<Req>) -> HandlerFuture + Clone + Send + Sync + 'static
+    where
+        Req: DeserializeOwned,
+    {
+        self.handler(|Json(request)| request)
+    }
+
+    /// a handler taking `Req` from the query string
+    pub fn query(&self) -> impl Fn(AxumQuery<Req>) -> HandlerFuture + Clone + Send + Sync + 'static
+    where
+        Req: DeserializeOwned,
+    {
+        self.handler(|AxumQuery(request)| request)
+    }
+
+    /// a handler taking `Req` from the path parameters
+    pub fn path(&self) -> impl Fn(Path<Req>) -> HandlerFuture + Clone + Send + Sync + 'static
+    where
+        Req: DeserializeOwned,
+    {
+        self.handler(|Path(request)| request)
+    }
+}
+
+fn stopped() -> Failure {
+    Failure::Custom("the pipeline thread stopped".to_string())
+}
+
+/// lets axum handlers return this crate's `Response`. headers that aren't
+/// valid http are left out.
+impl IntoResponse for Response {
+    fn into_response(self) -> axum::response::Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = axum::response::Response::new(axum::body::Body::from(self.body));
+        *response.status_mut() = status;
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::try_from(name.as_str()),
+                HeaderValue::try_from(value.as_str()),
+            ) {
+                response.headers_mut().append(name, value);
+            }
+        }
+        response
+    }
+}
@@ -0,0 +1,543 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+/// event time, in whatever unit the pipeline uses (usually milliseconds since
+/// the unix epoch).
+pub type EventTime = u64;
+
+/// tracks how far event time has provably advanced. records may arrive up to
+/// `max_delay` out of order; once a record with time `t` has been seen, nothing
+/// older than `t - max_delay` is expected anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watermark {
+    max_delay: EventTime,
+    max_seen: Option<EventTime>,
+}
+
+impl Watermark {
+    pub fn new(max_delay: EventTime) -> Self {
+        Watermark {
+            max_delay,
+            max_seen: None,
+        }
+    }
+
+    /// record that an event with time `ts` was seen
+    pub fn observe(&mut self, ts: EventTime) {
+        self.max_seen = Some(self.max_seen.map_or(ts, |m| m.max(ts)));
+    }
+
+    /// the current watermark, or `None` before the first event
+    pub fn current(&self) -> Option<EventTime> {
+        self.max_seen.map(|m| m.saturating_sub(self.max_delay))
+    }
+
+    /// true once event time has advanced to or past `ts`
+    pub fn has_passed(&self, ts: EventTime) -> bool {
+        self.current().is_some_and(|w| w >= ts)
+    }
+}
+
+/// what to do with a record whose window has already been emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lateness {
+    /// silently drop it (it's still counted in `WindowOutput::dropped`)
+    Drop,
+    /// fail the chain
+    Fail,
+    /// keep it aside in `WindowOutput::late`
+    Collect,
+}
+
+/// an aggregated window, covering event times in `start..end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window<A> {
+    pub start: EventTime,
+    pub end: EventTime,
+    pub value: A,
+}
+
+/// everything an event-time window stage produced.
+#[derive(Debug)]
+pub struct WindowOutput<T, A> {
+    /// windows in the order they were closed
+    pub windows: Vec<Window<A>>,
+    /// late records, when the policy is `Lateness::Collect`
+    pub late: Vec<T>,
+    /// number of late records that were not aggregated
+    pub dropped: usize,
+    /// number of records that fell in a gap between hopping windows
+    pub outside: usize,
+}
+
+/// how records are assigned to windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSpec {
+    /// back-to-back windows `width` wide; every record lands in exactly one
+    Tumbling(EventTime),
+    /// windows `width` wide starting every `slide`; a record lands in every
+    /// window that covers it. with `slide` greater than `width` these are
+    /// hopping windows, and records in the gaps between them land nowhere.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// // 10 wide, every 20: 0..10, 20..30, ...
+    /// let mut hopping = EventWindows::sliding(10, 20, 0, |n: u32, _: &u64| n + 1).on_late(Lateness::Fail);
+    /// let mut closed = hopping.push(5, 5).unwrap();
+    /// // between 0..10 and 20..30: ignored rather than late
+    /// closed.extend(hopping.push(12, 12).unwrap());
+    /// closed.extend(hopping.push(25, 25).unwrap());
+    /// let out = hopping.finish();
+    /// closed.extend(out.windows);
+    /// assert_eq!(closed.iter().map(|w| (w.start, w.value)).collect::<Vec<_>>(), [(0, 1), (20, 1)]);
+    /// assert_eq!(out.outside, 1);
+    /// assert_eq!(out.dropped, 0);
+    /// ```
+    Sliding { width: EventTime, slide: EventTime },
+}
+
+impl WindowSpec {
+    pub fn width(&self) -> EventTime {
+        match *self {
+            WindowSpec::Tumbling(width) | WindowSpec::Sliding { width, .. } => width,
+        }
+    }
+
+    /// start of every window covering `ts`, earliest first
+    fn starts(&self, ts: EventTime) -> Vec<EventTime> {
+        match *self {
+            WindowSpec::Tumbling(width) => vec![ts - ts % width],
+            WindowSpec::Sliding { width, slide } => {
+                let mut starts = Vec::new();
+                let mut start = ts - ts % slide;
+                loop {
+                    if start + width <= ts {
+                        break;
+                    }
+                    starts.push(start);
+                    match start.checked_sub(slide) {
+                        Some(s) => start = s,
+                        None => break,
+                    }
+                }
+                starts.reverse();
+                starts
+            }
+        }
+    }
+}
+
+/// event-time windows folded with `fold`, emitted once the watermark passes the
+/// end of the window plus the allowed lateness.
+///
+/// can be fed incrementally with `push` for streaming use, or run over a whole
+/// collection with `Reactor::event_windows`. keyed aggregations (`count_by_key`,
+/// `sum_by_key`, `fold_by_key`) produce one map per window holding a value per key.
+pub struct EventWindows<T, A, F> {
+    spec: WindowSpec,
+    watermark: Watermark,
+    allowed_lateness: EventTime,
+    policy: Lateness,
+    init: A,
+    fold: F,
+    open: BTreeMap<EventTime, A>,
+    late: Vec<T>,
+    dropped: usize,
+    outside: usize,
+}
+
+impl<T, A, F> EventWindows<T, A, F>
+where
+    A: Clone,
+    F: Fn(A, &T) -> A,
+{
+    /// windows laid out by `spec`, folding each record into a copy of `init`
+    pub fn new(spec: WindowSpec, init: A, fold: F) -> Self {
+        match spec {
+            WindowSpec::Tumbling(width) => {
+                assert!(width > 0, "window width must be greater than zero")
+            }
+            WindowSpec::Sliding { width, slide } => assert!(
+                width > 0 && slide > 0,
+                "window width and slide must be greater than zero"
+            ),
+        }
+        EventWindows {
+            spec,
+            watermark: Watermark::new(0),
+            allowed_lateness: 0,
+            policy: Lateness::Drop,
+            init,
+            fold,
+            open: BTreeMap::new(),
+            late: Vec::new(),
+            dropped: 0,
+            outside: 0,
+        }
+    }
+
+    /// back-to-back windows `width` wide
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let mut windows = EventWindows::tumbling(10, 0, |sum: u64, x: &u64| sum + x);
+    /// assert!(windows.push(3, 3).unwrap().is_empty());
+    /// assert!(windows.push(7, 7).unwrap().is_empty());
+    /// // event time reaching 10 closes 0..10
+    /// let closed = windows.push(14, 14).unwrap();
+    /// assert_eq!((closed[0].start, closed[0].end, closed[0].value), (0, 10, 10));
+    /// assert_eq!(windows.finish().windows[0].value, 14);
+    /// ```
+    pub fn tumbling(width: EventTime, init: A, fold: F) -> Self {
+        Self::new(WindowSpec::Tumbling(width), init, fold)
+    }
+
+    /// windows `width` wide, a new one starting every `slide`
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// // 10 wide, every 5: a record at 7 is in 0..10 and 5..15
+    /// let mut windows = EventWindows::sliding(10, 5, 0, |n: u32, _: &u64| n + 1);
+    /// windows.push(2, 2).unwrap();
+    /// windows.push(7, 7).unwrap();
+    /// let out = windows.finish();
+    /// let counts: Vec<_> = out.windows.iter().map(|w| (w.start, w.value)).collect();
+    /// assert_eq!(counts, [(0, 2), (5, 1)]);
+    /// ```
+    pub fn sliding(width: EventTime, slide: EventTime, init: A, fold: F) -> Self {
+        Self::new(WindowSpec::Sliding { width, slide }, init, fold)
+    }
+
+    /// how far out of order records may arrive before the watermark moves past them
+    pub fn max_delay(mut self, max_delay: EventTime) -> Self {
+        self.watermark = Watermark::new(max_delay);
+        self
+    }
+
+    /// keep windows open this much longer after the watermark passes their end
+    pub fn allowed_lateness(mut self, lateness: EventTime) -> Self {
+        self.allowed_lateness = lateness;
+        self
+    }
+
+    /// what to do with records that arrive after their window was emitted
+    pub fn on_late(mut self, policy: Lateness) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn watermark(&self) -> &Watermark {
+        &self.watermark
+    }
+
+    fn window(&self, start: EventTime, value: A) -> Window<A> {
+        Window {
+            start,
+            end: start + self.spec.width(),
+            value,
+        }
+    }
+
+    fn is_closed(&self, start: EventTime) -> bool {
+        let deadline = (start + self.spec.width()).saturating_add(self.allowed_lateness);
+        self.watermark.has_passed(deadline)
+    }
+
+    /// feed one record and return the windows it caused to close. a record is
+    /// late when every window covering it has already been emitted; one that
+    /// no window covers (between hopping windows) is counted in
+    /// `WindowOutput::outside` and otherwise ignored.
+    pub fn push(&mut self, ts: EventTime, item: T) -> Out<Vec<Window<A>>> {
+        let starts = self.spec.starts(ts);
+        if starts.is_empty() {
+            self.outside += 1;
+            self.watermark.observe(ts);
+            return Ok(self.drain_closed());
+        }
+        let starts: Vec<_> = starts.into_iter().filter(|s| !self.is_closed(*s)).collect();
+        if starts.is_empty() {
+            match self.policy {
+                Lateness::Fail => {
+                    return Err(Failure::InvalidInput(format!(
+                        "record at {} arrived after its window closed (watermark {})",
+                        ts,
+                        self.watermark.current().unwrap_or_default()
+                    )))
+                }
+                Lateness::Collect => self.late.push(item),
+                Lateness::Drop => {}
+            }
+            self.dropped += 1;
+            return Ok(Vec::new());
+        }
+        for start in starts {
+            let acc = self
+                .open
+                .remove(&start)
+                .unwrap_or_else(|| self.init.clone());
+            self.open.insert(start, (self.fold)(acc, &item));
+        }
+        self.watermark.observe(ts);
+        Ok(self.drain_closed())
+    }
+
+    fn drain_closed(&mut self) -> Vec<Window<A>> {
+        let mut closed = Vec::new();
+        while let Some((&start, _)) = self.open.first_key_value() {
+            if !self.is_closed(start) {
+                break;
+            }
+            let value = self.open.remove(&start).unwrap();
+            closed.push(self.window(start, value));
+        }
+        closed
+    }
+
+    /// close every remaining window, e.g. at the end of a bounded input
+    pub fn finish(mut self) -> WindowOutput<T, A> {
+        let windows = mem::take(&mut self.open)
+            .into_iter()
+            .map(|(start, value)| self.window(start, value))
+            .collect();
+        WindowOutput {
+            windows,
+            late: self.late,
+            dropped: self.dropped,
+            outside: self.outside,
+        }
+    }
+}
+
+impl<T, K, V> EventWindows<T, HashMap<K, V>, ()>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    /// per-key windows: each window's value maps every key seen in it to the
+    /// fold of that key's records, starting from `init`
+    #[allow(clippy::type_complexity)]
+    pub fn fold_by_key<KF, F>(
+        spec: WindowSpec,
+        key: KF,
+        init: V,
+        fold: F,
+    ) -> EventWindows<T, HashMap<K, V>, impl Fn(HashMap<K, V>, &T) -> HashMap<K, V>>
+    where
+        KF: Fn(&T) -> K,
+        F: Fn(V, &T) -> V,
+    {
+        EventWindows::new(
+            spec,
+            HashMap::new(),
+            move |mut acc: HashMap<K, V>, item: &T| {
+                let k = key(item);
+                let v = acc.remove(&k).unwrap_or_else(|| init.clone());
+                acc.insert(k, fold(v, item));
+                acc
+            },
+        )
+    }
+}
+
+impl<T, K> EventWindows<T, HashMap<K, usize>, ()>
+where
+    K: Hash + Eq + Clone,
+{
+    /// number of records per key per window
+    #[allow(clippy::type_complexity)]
+    pub fn count_by_key<KF>(
+        spec: WindowSpec,
+        key: KF,
+    ) -> EventWindows<T, HashMap<K, usize>, impl Fn(HashMap<K, usize>, &T) -> HashMap<K, usize>>
+    where
+        KF: Fn(&T) -> K,
+    {
+        EventWindows::fold_by_key(spec, key, 0, |n, _: &T| n + 1)
+    }
+}
+
+impl<T, K, V> EventWindows<T, HashMap<K, V>, ()>
+where
+    K: Hash + Eq + Clone,
+    V: Clone + Default + std::ops::Add<Output = V>,
+{
+    /// sum of `value` per key per window
+    #[allow(clippy::type_complexity)]
+    pub fn sum_by_key<KF, VF>(
+        spec: WindowSpec,
+        key: KF,
+        value: VF,
+    ) -> EventWindows<T, HashMap<K, V>, impl Fn(HashMap<K, V>, &T) -> HashMap<K, V>>
+    where
+        KF: Fn(&T) -> K,
+        VF: Fn(&T) -> V,
+    {
+        EventWindows::fold_by_key(spec, key, V::default(), move |sum, item: &T| {
+            sum + value(item)
+        })
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug + From<Failure>,
+{
+    /// aggregate a collection of events into event-time windows. `timestamp`
+    /// extracts each record's event time; windows are emitted in the order the
+    /// watermark closes them, with whatever is still open closed at the end.
+    /// the whole collection is read before the stage completes; for unbounded
+    /// inputs use `event_windows_lazy`.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// // (time, page) views, counted per page per 60s
+    /// let views = vec![(5, "home"), (20, "cart"), (30, "home"), (65, "home")];
+    /// let out = Reactor::<Vec<(u64, &str)>>::input(views)
+    ///     .event_windows(
+    ///         EventWindows::count_by_key(WindowSpec::Tumbling(60), |v: &(u64, &str)| v.1),
+    ///         |v| v.0,
+    ///     )
+    ///     .run()
+    ///     .unwrap();
+    /// assert_eq!(out.windows[0].value["home"], 2);
+    /// assert_eq!(out.windows[0].value["cart"], 1);
+    /// assert_eq!(out.windows[1].value["home"], 1);
+    /// ```
+    pub fn event_windows<T, A, F, TS>(
+        self,
+        mut windows: EventWindows<T, A, F>,
+        timestamp: TS,
+    ) -> Reactor<WindowOutput<T, A>, E>
+    where
+        I: IntoIterator<Item = T>,
+        A: Clone,
+        F: Fn(A, &T) -> A,
+        TS: Fn(&T) -> EventTime,
+    {
+        Reactor {
+            input: self.input.and_then(|i| {
+                let mut emitted = Vec::new();
+                for item in i {
+                    let ts = timestamp(&item);
+                    emitted.extend(windows.push(ts, item)?);
+                }
+                let mut out = windows.finish();
+                emitted.append(&mut out.windows);
+                out.windows = emitted;
+                Ok(out)
+            }),
+        }
+    }
+}
+
+/// windows emitted as the watermark closes them while the records are pulled
+/// through. see `Reactor::event_windows_lazy`.
+pub struct WindowStream<It, T, A, F, TS> {
+    records: It,
+    windows: Option<EventWindows<T, A, F>>,
+    timestamp: TS,
+    ready: std::collections::VecDeque<Window<A>>,
+    late: Vec<T>,
+    dropped: usize,
+    outside: usize,
+}
+
+impl<It, T, A, F, TS> WindowStream<It, T, A, F, TS> {
+    /// late records, when the policy is `Lateness::Collect`, once the stream
+    /// has run out
+    pub fn late(&self) -> &[T] {
+        &self.late
+    }
+
+    /// late records that were not aggregated, once the stream has run out
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// records between hopping windows, once the stream has run out
+    pub fn outside(&self) -> usize {
+        self.outside
+    }
+}
+
+impl<It, T, A, F, TS> Iterator for WindowStream<It, T, A, F, TS>
+where
+    It: Iterator<Item = T>,
+    A: Clone,
+    F: Fn(A, &T) -> A,
+    TS: Fn(&T) -> EventTime,
+{
+    type Item = Out<Window<A>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(window) = self.ready.pop_front() {
+                return Some(Ok(window));
+            }
+            let windows = self.windows.as_mut()?;
+            match self.records.next() {
+                Some(item) => match windows.push((self.timestamp)(&item), item) {
+                    Ok(closed) => self.ready.extend(closed),
+                    Err(e) => {
+                        self.windows = None;
+                        return Some(Err(e));
+                    }
+                },
+                None => {
+                    let out = self.windows.take()?.finish();
+                    self.ready.extend(out.windows);
+                    self.late = out.late;
+                    self.dropped = out.dropped;
+                    self.outside = out.outside;
+                }
+            }
+        }
+    }
+}
+
+impl<I, E> Reactor<I, E>
+where
+    E: Debug,
+{
+    /// like `event_windows`, but the reactor holds a stream that reads records
+    /// only as windows are pulled from it, emitting each window as soon as the
+    /// watermark closes it.
+    ///
+    /// ```rust
+    /// use chain_reaction::*;
+    ///
+    /// let mut stream = Reactor::<std::ops::Range<u64>>::input(0..1_000_000)
+    ///     .event_windows_lazy(EventWindows::tumbling(1000, 0, |n: u64, _: &u64| n + 1), |t| *t)
+    ///     .run()
+    ///     .unwrap();
+    /// let first = stream.next().unwrap().unwrap();
+    /// assert_eq!((first.start, first.value), (0, 1000));
+    /// assert_eq!(stream.count(), 999);
+    /// ```
+    pub fn event_windows_lazy<T, A, F, TS>(
+        self,
+        windows: EventWindows<T, A, F>,
+        timestamp: TS,
+    ) -> Reactor<WindowStream<I::IntoIter, T, A, F, TS>, E>
+    where
+        I: IntoIterator<Item = T>,
+        A: Clone,
+        F: Fn(A, &T) -> A,
+        TS: Fn(&T) -> EventTime,
+    {
+        self.map(|i| WindowStream {
+            records: i.into_iter(),
+            windows: Some(windows),
+            timestamp,
+            ready: std::collections::VecDeque::new(),
+            late: Vec::new(),
+            dropped: 0,
+            outside: 0,
+        })
+    }
+}
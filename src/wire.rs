@@ -0,0 +1,300 @@
+use super::*;
+
+// values travel as one line of tab separated fields, the first being the
+// schema version. fields are escaped so they can't contain tabs or newlines,
+// and nested values are the escaped encoding of the inner value.
+
+/// the schema version written by `Wire::to_wire`
+pub const WIRE_VERSION: &str = "v1";
+
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub(crate) fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn encode(fields: &[String]) -> String {
+    let mut line = WIRE_VERSION.to_string();
+    for field in fields {
+        line.push('\t');
+        line.push_str(&escape(field));
+    }
+    line
+}
+
+fn invalid(what: &str, s: &str) -> Failure {
+    Failure::InvalidInput(format!("not a {} in the wire format: '{}'", what, s))
+}
+
+/// the fields of an encoded value, checked for the version
+fn decode(what: &str, s: &str) -> Out<Vec<String>> {
+    let mut fields = s.split('\t');
+    if fields.next() != Some(WIRE_VERSION) {
+        return Err(invalid(what, s));
+    }
+    Ok(fields.map(unescape).collect())
+}
+
+fn number<T: std::str::FromStr>(what: &str, field: &str) -> Out<T> {
+    field.parse().map_err(|_| invalid(what, field))
+}
+
+/// a versioned text encoding for sending values between processes, e.g.
+/// errors from distributed workers to the coordinator. one value is one line.
+/// with the `serde` feature the same types also implement `Serialize` and
+/// `Deserialize` in the same `v1` schema, for any serde format.
+///
+/// a `Failure` is encoded as `v1`, its `message_key`, then its fields:
+///
+/// | key | fields |
+/// |---|---|
+/// | `invalid_input`, `arithmetic_error`, `unauthorized`, `custom`, `cancelled` | message |
+/// | `snapshot` | stage, input, encoded source |
+/// | `quota_time` | stage, limit in nanoseconds, used in nanoseconds |
+/// | `quota_memory` | stage, limit in bytes, used in bytes |
+/// | `timeout` | stage, limit in nanoseconds |
+/// | `detailed` | encoded source, then alternating field names and values |
+///
+/// everything but a detailed failure's payload and cause round-trips: payloads
+/// attached with `with_payload` and causes kept with `with_cause` are dropped.
+/// the serde impls keep the cause's message.
+///
+/// a `RunReport` is `v1`, `report`, the failed stage's index or `-`, then five
+/// fields per stage: name, items, failures, duration in nanoseconds, retries.
+/// a `SavedRun` is `v1`, `recording`, the number of stages, the failed stage's
+/// index or `-`, the error or `-`, three fields per stage: name, input type,
+/// output type, then the captured values.
+///
+/// ```rust
+/// use chain_reaction::*;
+///
+/// let failure = Failure::InvalidInput("bad\tdate".into()).with_field("row", 7);
+/// let line = failure.to_wire();
+/// assert!(!line.contains('\n'));
+/// let back = Failure::from_wire(&line).unwrap();
+/// assert_eq!(back.field("row"), Some("7"));
+/// assert_eq!(back.to_string(), failure.to_string());
+///
+/// // a report whose failed stage isn't among its stages is rejected
+/// assert!(RunReport::from_wire("v1\treport\t3").is_err());
+/// let one = RunReport::from_wire("v1\treport\t0\tparse\t1\t1\t5\t0").unwrap();
+/// assert_eq!(one.failed_stage().unwrap().name, "parse");
+/// ```
+pub trait Wire: Sized {
+    fn to_wire(&self) -> String;
+    fn from_wire(s: &str) -> Out<Self>;
+}
+
+impl Wire for Failure {
+    fn to_wire(&self) -> String {
+        let key = self.message_key().to_string();
+        let fields = match self {
+            Failure::InvalidInput(s)
+            | Failure::ArithmeticError(s)
+            | Failure::Unauthorized(s)
+            | Failure::Custom(s)
+            | Failure::Cancelled(s) => vec![key, s.clone()],
+            Failure::Snapshot(s) => vec![key, s.stage.clone(), s.input.clone(), s.source.to_wire()],
+            Failure::QuotaExceeded(q) => {
+                let (limit, used) = match q.resource {
+                    QuotaResource::Time { limit, used } => {
+                        (limit.as_nanos().to_string(), used.as_nanos().to_string())
+                    }
+                    QuotaResource::Memory { limit, used } => (limit.to_string(), used.to_string()),
+                };
+                vec![key, q.stage.clone(), limit, used]
+            }
+            Failure::Timeout(t) => vec![key, t.stage.clone(), t.limit.as_nanos().to_string()],
+            Failure::Detailed(d) => {
+                let mut fields = vec![key, d.source.to_wire()];
+                for (k, v) in &d.fields {
+                    fields.push(k.clone());
+                    fields.push(v.clone());
+                }
+                fields
+            }
+        };
+        encode(&fields)
+    }
+
+    fn from_wire(s: &str) -> Out<Self> {
+        let fields = decode("failure", s)?;
+        let field = |i: usize| -> Out<&str> {
+            fields
+                .get(i)
+                .map(String::as_str)
+                .ok_or_else(|| invalid("failure", s))
+        };
+        let nanos =
+            |i: usize| -> Out<Duration> { Ok(Duration::from_nanos(number("failure", field(i)?)?)) };
+        let failure = match field(0)? {
+            "invalid_input" => Failure::InvalidInput(field(1)?.to_string()),
+            "arithmetic_error" => Failure::ArithmeticError(field(1)?.to_string()),
+            "unauthorized" => Failure::Unauthorized(field(1)?.to_string()),
+            "custom" => Failure::Custom(field(1)?.to_string()),
+            "cancelled" => Failure::Cancelled(field(1)?.to_string()),
+            "timeout" => Failure::Timeout(Box::new(TimedOut {
+                stage: field(1)?.to_string(),
+                limit: nanos(2)?,
+            })),
+            "snapshot" => Failure::Snapshot(Box::new(StageSnapshot {
+                stage: field(1)?.to_string(),
+                input: field(2)?.to_string(),
+                source: Failure::from_wire(field(3)?)?,
+            })),
+            "quota_time" => Failure::QuotaExceeded(Box::new(QuotaViolation {
+                stage: field(1)?.to_string(),
+                resource: QuotaResource::Time {
+                    limit: nanos(2)?,
+                    used: nanos(3)?,
+                },
+            })),
+            "quota_memory" => Failure::QuotaExceeded(Box::new(QuotaViolation {
+                stage: field(1)?.to_string(),
+                resource: QuotaResource::Memory {
+                    limit: number("failure", field(2)?)?,
+                    used: number("failure", field(3)?)?,
+                },
+            })),
+            "detailed" => {
+                if fields.len() % 2 != 0 {
+                    return Err(invalid("failure", s));
+                }
+                Failure::Detailed(Box::new(FailureDetails {
+                    source: Failure::from_wire(field(1)?)?,
+                    fields: fields[2..]
+                        .chunks(2)
+                        .map(|kv| (kv[0].clone(), kv[1].clone()))
+                        .collect(),
+                    payload: None,
+                    cause: None,
+                }))
+            }
+            _ => return Err(invalid("failure", s)),
+        };
+        Ok(failure)
+    }
+}
+
+impl Wire for RunReport {
+    fn to_wire(&self) -> String {
+        let mut fields = vec![
+            "report".to_string(),
+            self.failed_at.map_or("-".to_string(), |i| i.to_string()),
+        ];
+        for stage in &self.stages {
+            fields.extend([
+                stage.name.clone(),
+                stage.items.to_string(),
+                stage.failures.to_string(),
+                stage.duration.as_nanos().to_string(),
+                stage.retries.to_string(),
+            ]);
+        }
+        encode(&fields)
+    }
+
+    fn from_wire(s: &str) -> Out<Self> {
+        let fields = decode("report", s)?;
+        if fields.len() < 2 || fields[0] != "report" || (fields.len() - 2) % 5 != 0 {
+            return Err(invalid("report", s));
+        }
+        let failed_at = match fields[1].as_str() {
+            "-" => None,
+            i => Some(number("report", i)?),
+        };
+        let stages = fields[2..]
+            .chunks(5)
+            .map(|f| {
+                Ok(StageReport {
+                    name: f[0].clone(),
+                    items: number("report", &f[1])?,
+                    failures: number("report", &f[2])?,
+                    duration: Duration::from_nanos(number("report", &f[3])?),
+                    retries: number("report", &f[4])?,
+                })
+            })
+            .collect::<Out<Vec<_>>>()?;
+        if failed_at.is_some_and(|i| i >= stages.len()) {
+            return Err(invalid("report", s));
+        }
+        Ok(RunReport { stages, failed_at })
+    }
+}
+
+impl Wire for SavedRun {
+    fn to_wire(&self) -> String {
+        let mut fields = vec![
+            "recording".to_string(),
+            self.stages.len().to_string(),
+            self.failed_at.map_or("-".to_string(), |i| i.to_string()),
+            self.error.clone().unwrap_or_else(|| "-".to_string()),
+        ];
+        for stage in &self.stages {
+            fields.extend([
+                stage.name.clone(),
+                stage.input_type.clone(),
+                stage.output_type.clone(),
+            ]);
+        }
+        fields.extend(self.values.iter().cloned());
+        encode(&fields)
+    }
+
+    fn from_wire(s: &str) -> Out<Self> {
+        let fields = decode("recording", s)?;
+        if fields.len() < 4 || fields[0] != "recording" {
+            return Err(invalid("recording", s));
+        }
+        let stages: usize = number("recording", &fields[1])?;
+        let values = stages
+            .checked_mul(3)
+            .map(|n| n + 4)
+            .filter(|start| *start <= fields.len())
+            .ok_or_else(|| invalid("recording", s))?;
+        let failed_at = match fields[2].as_str() {
+            "-" => None,
+            i => Some(number("recording", i)?),
+        };
+        Ok(SavedRun {
+            stages: fields[4..values]
+                .chunks(3)
+                .map(|f| SavedStage {
+                    name: f[0].clone(),
+                    input_type: f[1].clone(),
+                    output_type: f[2].clone(),
+                })
+                .collect(),
+            values: fields[values..].to_vec(),
+            failed_at,
+            error: (fields[3] != "-").then(|| fields[3].clone()),
+        })
+    }
+}
@@ -0,0 +1,394 @@
+use super::*;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// `Failure`, `RunReport` and `SavedRun` serialize through the mirror types
+// below, which are the documented schema. the top-level value carries
+// `WIRE_VERSION`; nested failures don't.
+
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: String,
+    #[serde(flatten)]
+    value: T,
+}
+
+fn serialize_versioned<T, S>(value: T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    Versioned {
+        version: WIRE_VERSION.to_string(),
+        value,
+    }
+    .serialize(serializer)
+}
+
+fn deserialize_versioned<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DeserializeOwned,
+    D: Deserializer<'de>,
+{
+    let versioned = Versioned::<T>::deserialize(deserializer)?;
+    if versioned.version != WIRE_VERSION {
+        return Err(D::Error::custom(format!(
+            "unsupported schema version '{}', expected '{}'",
+            versioned.version, WIRE_VERSION
+        )));
+    }
+    Ok(versioned.value)
+}
+
+fn nanos(d: Duration) -> u64 {
+    d.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+/// a cause that came over the wire: its message, without its original type
+#[derive(Debug)]
+struct RemoteCause(String);
+
+impl std::fmt::Display for RemoteCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for RemoteCause {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum FailureV1 {
+    InvalidInput {
+        message: String,
+    },
+    ArithmeticError {
+        message: String,
+    },
+    Unauthorized {
+        message: String,
+    },
+    Custom {
+        message: String,
+    },
+    Cancelled {
+        message: String,
+    },
+    Snapshot {
+        stage: String,
+        input: String,
+        source: Box<FailureV1>,
+    },
+    QuotaTime {
+        stage: String,
+        limit_ns: u64,
+        used_ns: u64,
+    },
+    QuotaMemory {
+        stage: String,
+        limit: usize,
+        used: usize,
+    },
+    Timeout {
+        stage: String,
+        limit_ns: u64,
+    },
+    Detailed {
+        source: Box<FailureV1>,
+        fields: Vec<(String, String)>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cause: Option<String>,
+    },
+}
+
+impl From<&Failure> for FailureV1 {
+    fn from(failure: &Failure) -> Self {
+        match failure {
+            Failure::InvalidInput(s) => FailureV1::InvalidInput { message: s.clone() },
+            Failure::ArithmeticError(s) => FailureV1::ArithmeticError { message: s.clone() },
+            Failure::Unauthorized(s) => FailureV1::Unauthorized { message: s.clone() },
+            Failure::Custom(s) => FailureV1::Custom { message: s.clone() },
+            Failure::Cancelled(s) => FailureV1::Cancelled { message: s.clone() },
+            Failure::Snapshot(s) => FailureV1::Snapshot {
+                stage: s.stage.clone(),
+                input: s.input.clone(),
+                source: Box::new((&s.source).into()),
+            },
+            Failure::QuotaExceeded(q) => match q.resource {
+                QuotaResource::Time { limit, used } => FailureV1::QuotaTime {
+                    stage: q.stage.clone(),
+                    limit_ns: nanos(limit),
+                    used_ns: nanos(used),
+                },
+                QuotaResource::Memory { limit, used } => FailureV1::QuotaMemory {
+                    stage: q.stage.clone(),
+                    limit,
+                    used,
+                },
+            },
+            Failure::Timeout(t) => FailureV1::Timeout {
+                stage: t.stage.clone(),
+                limit_ns: nanos(t.limit),
+            },
+            Failure::Detailed(d) => FailureV1::Detailed {
+                source: Box::new((&d.source).into()),
+                fields: d.fields.clone(),
+                cause: d.cause.as_ref().map(|c| c.to_string()),
+            },
+        }
+    }
+}
+
+impl From<FailureV1> for Failure {
+    fn from(failure: FailureV1) -> Self {
+        match failure {
+            FailureV1::InvalidInput { message } => Failure::InvalidInput(message),
+            FailureV1::ArithmeticError { message } => Failure::ArithmeticError(message),
+            FailureV1::Unauthorized { message } => Failure::Unauthorized(message),
+            FailureV1::Custom { message } => Failure::Custom(message),
+            FailureV1::Cancelled { message } => Failure::Cancelled(message),
+            FailureV1::Snapshot {
+                stage,
+                input,
+                source,
+            } => Failure::Snapshot(Box::new(StageSnapshot {
+                stage,
+                input,
+                source: (*source).into(),
+            })),
+            FailureV1::QuotaTime {
+                stage,
+                limit_ns,
+                used_ns,
+            } => Failure::QuotaExceeded(Box::new(QuotaViolation {
+                stage,
+                resource: QuotaResource::Time {
+                    limit: Duration::from_nanos(limit_ns),
+                    used: Duration::from_nanos(used_ns),
+                },
+            })),
+            FailureV1::QuotaMemory { stage, limit, used } => {
+                Failure::QuotaExceeded(Box::new(QuotaViolation {
+                    stage,
+                    resource: QuotaResource::Memory { limit, used },
+                }))
+            }
+            FailureV1::Timeout { stage, limit_ns } => Failure::Timeout(Box::new(TimedOut {
+                stage,
+                limit: Duration::from_nanos(limit_ns),
+            })),
+            FailureV1::Detailed {
+                source,
+                fields,
+                cause,
+            } => Failure::Detailed(Box::new(FailureDetails {
+                source: (*source).into(),
+                fields,
+                payload: None,
+                cause: cause.map(|c| Box::new(RemoteCause(c)) as _),
+            })),
+        }
+    }
+}
+
+/// with the `serde` feature, in the same `v1` schema as the `Wire` encoding:
+/// an object with `version`, then `kind` (the `message_key`) and the fields
+/// of that kind, named as below. durations are whole nanoseconds.
+///
+/// | kind | fields |
+/// |---|---|
+/// | `invalid_input`, `arithmetic_error`, `unauthorized`, `custom`, `cancelled` | `message` |
+/// | `snapshot` | `stage`, `input`, `source` |
+/// | `quota_time` | `stage`, `limit_ns`, `used_ns` |
+/// | `quota_memory` | `stage`, `limit`, `used` (bytes) |
+/// | `timeout` | `stage`, `limit_ns` |
+/// | `detailed` | `source`, `fields` as `[name, value]` pairs, `cause` if any |
+///
+/// a `source` is a nested failure without its own `version`. a cause is
+/// kept as its message and comes back as an error with that message, not as
+/// its original type; payloads can't be serialized and are dropped.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::error::Error;
+///
+/// let parse = "x1".parse::<u8>().unwrap_err();
+/// let failure = Failure::InvalidInput("bad level".into())
+///     .with_field("row", 7)
+///     .with_cause(parse.clone());
+/// let json = serde_json::to_string(&failure).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"version":"v1","kind":"detailed","source":{"kind":"invalid_input","message":"bad level"},"fields":[["row","7"]],"cause":"invalid digit found in string"}"#
+/// );
+///
+/// let back: Failure = serde_json::from_str(&json).unwrap();
+/// assert_eq!(back.to_string(), failure.to_string());
+/// assert_eq!(back.field("row"), Some("7"));
+/// assert_eq!(back.source().unwrap().to_string(), parse.to_string());
+///
+/// let newer = json.replace("\"v1\"", "\"v2\"");
+/// assert!(serde_json::from_str::<Failure>(&newer).is_err());
+/// ```
+impl Serialize for Failure {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_versioned(FailureV1::from(self), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Failure {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_versioned::<FailureV1, D>(deserializer).map(Failure::from)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StageReportV1 {
+    name: String,
+    items: u64,
+    failures: u64,
+    duration_ns: u64,
+    retries: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RunReportV1 {
+    failed_at: Option<usize>,
+    stages: Vec<StageReportV1>,
+}
+
+/// with the `serde` feature: an object with `version`, `failed_at` (the
+/// failed stage's index or null) and `stages`, each with `name`, `items`,
+/// `failures`, `duration_ns` and `retries`.
+///
+/// ```rust
+/// use chain_reaction::*;
+/// use std::time::Duration;
+///
+/// let report = RunReport {
+///     stages: vec![StageReport {
+///         name: "parse".into(),
+///         items: 3,
+///         failures: 1,
+///         duration: Duration::from_micros(5),
+///         retries: 0,
+///     }],
+///     failed_at: Some(0),
+/// };
+/// let json = serde_json::to_string(&report).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"version":"v1","failed_at":0,"stages":[{"name":"parse","items":3,"failures":1,"duration_ns":5000,"retries":0}]}"#
+/// );
+/// assert_eq!(serde_json::from_str::<RunReport>(&json).unwrap(), report);
+///
+/// // a failed stage the report doesn't have is rejected
+/// let past = r#"{"version":"v1","failed_at":3,"stages":[]}"#;
+/// assert!(serde_json::from_str::<RunReport>(past).is_err());
+/// ```
+impl Serialize for RunReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let report = RunReportV1 {
+            failed_at: self.failed_at,
+            stages: self
+                .stages
+                .iter()
+                .map(|s| StageReportV1 {
+                    name: s.name.clone(),
+                    items: s.items,
+                    failures: s.failures,
+                    duration_ns: nanos(s.duration),
+                    retries: s.retries,
+                })
+                .collect(),
+        };
+        serialize_versioned(report, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RunReport {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let report = deserialize_versioned::<RunReportV1, D>(deserializer)?;
+        if let Some(i) = report.failed_at.filter(|&i| i >= report.stages.len()) {
+            return Err(D::Error::custom(format!(
+                "failed_at {} is past the report's {} stages",
+                i,
+                report.stages.len()
+            )));
+        }
+        let stages = report
+            .stages
+            .into_iter()
+            .map(|s| StageReport {
+                name: s.name,
+                items: s.items,
+                failures: s.failures,
+                duration: Duration::from_nanos(s.duration_ns),
+                retries: s.retries,
+            })
+            .collect();
+        Ok(RunReport {
+            stages,
+            failed_at: report.failed_at,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedStageV1 {
+    name: String,
+    input_type: String,
+    output_type: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedRunV1 {
+    stages: Vec<SavedStageV1>,
+    values: Vec<String>,
+    failed_at: Option<usize>,
+    error: Option<String>,
+}
+
+/// with the `serde` feature: an object with `version`, `stages` (each with
+/// `name`, `input_type` and `output_type`), the captured `values`,
+/// `failed_at` and `error`.
+impl Serialize for SavedRun {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let run = SavedRunV1 {
+            stages: self
+                .stages
+                .iter()
+                .map(|s| SavedStageV1 {
+                    name: s.name.clone(),
+                    input_type: s.input_type.clone(),
+                    output_type: s.output_type.clone(),
+                })
+                .collect(),
+            values: self.values.clone(),
+            failed_at: self.failed_at,
+            error: self.error.clone(),
+        };
+        serialize_versioned(run, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SavedRun {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let run = deserialize_versioned::<SavedRunV1, D>(deserializer)?;
+        let stages = run
+            .stages
+            .into_iter()
+            .map(|s| SavedStage {
+                name: s.name,
+                input_type: s.input_type,
+                output_type: s.output_type,
+            })
+            .collect();
+        Ok(SavedRun {
+            stages,
+            values: run.values,
+            failed_at: run.failed_at,
+            error: run.error,
+        })
+    }
+}